@@ -0,0 +1,95 @@
+use std::{
+    path::PathBuf,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+};
+
+use tracing::{info, warn};
+
+const MIB: u64 = 1024 * 1024;
+
+/// Free-space guard for the output volume.
+///
+/// While free space is below the threshold the job queue is paused: new submissions
+/// are rejected and running jobs fail before their next image.
+#[derive(Debug, Clone)]
+pub struct DiskGuard {
+    inner: Arc<DiskGuardInner>,
+}
+
+#[derive(Debug)]
+struct DiskGuardInner {
+    root: PathBuf,
+    min_free_bytes: u64,
+    paused: AtomicBool,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct DiskStatus {
+    /// `None` when the volume could not be queried.
+    pub free_bytes: Option<u64>,
+    pub min_free_bytes: u64,
+    pub paused: bool,
+}
+
+impl DiskGuard {
+    pub fn new(root: PathBuf, min_free_mb: u64) -> Self {
+        Self {
+            inner: Arc::new(DiskGuardInner {
+                root,
+                min_free_bytes: min_free_mb * MIB,
+                paused: AtomicBool::new(false),
+            }),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.inner.min_free_bytes > 0
+    }
+
+    /// Query free space and update the paused flag.
+    pub fn refresh(&self) -> DiskStatus {
+        let free_bytes = match fs4::available_space(&self.inner.root) {
+            Ok(b) => Some(b),
+            Err(e) => {
+                warn!(error = %e, root = %self.inner.root.display(), "failed to query free disk space");
+                None
+            }
+        };
+        let paused =
+            self.is_enabled() && free_bytes.is_some_and(|free| free < self.inner.min_free_bytes);
+        let was_paused = self.inner.paused.swap(paused, Ordering::SeqCst);
+        if paused && !was_paused {
+            warn!(
+                free_mb = free_bytes.unwrap_or(0) / MIB,
+                min_free_mb = self.inner.min_free_bytes / MIB,
+                "low disk space on output volume; job queue paused"
+            );
+        } else if !paused && was_paused {
+            info!("disk space recovered; job queue resumed");
+        }
+        DiskStatus {
+            free_bytes,
+            min_free_bytes: self.inner.min_free_bytes,
+            paused,
+        }
+    }
+
+    /// Fail fast when the output volume is low on space.
+    pub fn check(&self) -> anyhow::Result<()> {
+        if !self.is_enabled() {
+            return Ok(());
+        }
+        let status = self.refresh();
+        if status.paused {
+            anyhow::bail!(
+                "insufficient disk space on output volume: {} MiB free, {} MiB required; job queue paused",
+                status.free_bytes.unwrap_or(0) / MIB,
+                status.min_free_bytes / MIB
+            );
+        }
+        Ok(())
+    }
+}
@@ -64,25 +64,7 @@ impl LastGenerationStore {
     pub async fn set(&self, record: LastGenerationRecord) -> anyhow::Result<()> {
         self.db
             .with_conn_blocking("last_generation set", move |conn| {
-                let base_json = serde_json::to_string(&record.base).context("serialize base")?;
-                let character_prompts_json = serde_json::to_string(
-                    &record.base.character_prompts.clone().unwrap_or_default(),
-                )
-                .context("serialize character_prompts")?;
-
-                conn.execute(
-                    "INSERT OR REPLACE INTO last_generation (id, updated_at_ms, base_json, model, positive, negative, character_prompts_json) VALUES (1, ?1, ?2, ?3, ?4, ?5, ?6)",
-                    params![
-                        record.updated_at_ms,
-                        base_json,
-                        record.base.model,
-                        record.base.positive,
-                        record.base.negative,
-                        character_prompts_json,
-                    ],
-                )?;
-
-                Ok(())
+                write_last_generation(conn, &record)
             })
             .await
     }
@@ -121,6 +103,30 @@ impl LastGenerationStore {
     }
 }
 
+/// Replace the last generation. Runs inside the caller's transaction, if any.
+pub(crate) fn write_last_generation(
+    conn: &Connection,
+    record: &LastGenerationRecord,
+) -> anyhow::Result<()> {
+    let base_json = serde_json::to_string(&record.base).context("serialize base")?;
+    let character_prompts_json =
+        serde_json::to_string(&record.base.character_prompts.clone().unwrap_or_default())
+            .context("serialize character_prompts")?;
+
+    conn.execute(
+        "INSERT OR REPLACE INTO last_generation (id, updated_at_ms, base_json, model, positive, negative, character_prompts_json) VALUES (1, ?1, ?2, ?3, ?4, ?5, ?6)",
+        params![
+            record.updated_at_ms,
+            base_json,
+            record.base.model,
+            record.base.positive,
+            record.base.negative,
+            character_prompts_json,
+        ],
+    )?;
+    Ok(())
+}
+
 fn default_base_for_model(
     model: &str,
     positive: &str,
@@ -141,6 +147,10 @@ fn default_base_for_model(
         noise_schedule: None,
         cfg_rescale: None,
         seed: -1,
+        seed_mode: None,
+        seeds: None,
+        wildcard_seed: None,
+        combinatorial: None,
         add_quality_tags: Some(true),
         undesired_content_preset: Some("None".to_string()),
         sm: if is_v3 { Some(false) } else { None },
@@ -155,6 +165,9 @@ fn default_base_for_model(
         reference_image_multiple: None,
         reference_information_extracted_multiple: None,
         reference_strength_multiple: None,
+        preset_name: None,
+        output_format: None,
+        grid: None,
     }
 }
 
@@ -1,18 +1,57 @@
 mod character_preset_store;
 mod db;
+mod disk_guard;
 mod last_generation;
+mod maintenance;
+mod output_annotation_store;
+mod output_counter_store;
+mod output_metadata_store;
 mod preset_store;
+mod prompt_lint;
+mod prompt_pipeline;
 mod prompt_preset_store;
 mod prompt_snippet_expand;
 mod prompt_snippet_store;
+mod retention;
+mod revision_store;
 mod routes;
+mod settings_bundle;
 mod simple_json_store;
+mod snippet_graph;
+mod snippet_transfer;
+mod tag_store;
+mod wildcard_expand;
+mod wildcard_store;
 
 pub use character_preset_store::{CharacterPresetStore, CharacterSlotPreset};
 pub use db::Database;
+pub use disk_guard::{DiskGuard, DiskStatus};
 pub use last_generation::{LastGenerationRecord, LastGenerationStore};
+pub use maintenance::spawn_maintenance;
+pub use output_annotation_store::{AnnotationPatch, OutputAnnotationStore, OutputFilter};
+pub use output_counter_store::OutputCounterStore;
+pub use output_metadata_store::OutputMetadataDb;
 pub use preset_store::{DEFAULT_PRESET_NAME, GeneratePreset, PresetStore};
+pub use prompt_lint::lint_prompts;
 pub use prompt_preset_store::{DEFAULT_PROMPT_PRESET_NAME, PromptPreset, PromptPresetStore};
 pub use prompt_snippet_expand::{SnippetExpansionResult, expand_prompts_pair};
-pub use prompt_snippet_store::{PromptSnippet, PromptSnippetStore};
+pub use prompt_snippet_store::{
+    PromptSnippet, PromptSnippetStore, PromptSnippetSummary, SnippetFolder, SnippetPage,
+    SnippetParam, SnippetQuery, SnippetSubfolder, TagMode,
+};
+pub use retention::{RetentionReport, run_retention};
+pub use revision_store::{Revision, RevisionDiff, RevisionKind, RevisionStore, RevisionSummary};
 pub use routes::{AppState, router};
+pub use settings_bundle::{
+    BundleSection, SETTINGS_BUNDLE_VERSION, SettingsBundle, SettingsImportReport, SettingsStores,
+};
+pub use snippet_graph::{ReferrerKind, SnippetEdge, SnippetGraph, SnippetReferrer};
+pub use snippet_transfer::{
+    BundleSnippet, ConflictStrategy, ImportReport, SnippetBundle, SnippetFormat,
+};
+pub use tag_store::{TagImportReport, TagInfo, TagMatch, TagStore, TagSuggestion};
+pub use wildcard_expand::{
+    count_combinations, expand_wildcards, expand_wildcards_pair, has_wildcards,
+    resolve_wildcard_seed,
+};
+pub use wildcard_store::{WildcardSource, WildcardStore, WildcardSummary};
@@ -0,0 +1,58 @@
+use std::{sync::Arc, time::Duration};
+
+use tracing::{info, warn};
+
+use crate::{retention::run_retention, routes::AppState};
+
+const TRASH_PURGE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+const DISK_GUARD_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Spawn periodic housekeeping tasks for the outputs directory.
+pub fn spawn_maintenance(state: Arc<AppState>) {
+    if state.config.trash_days > 0 {
+        tokio::spawn(trash_purge_loop(state.clone()));
+    }
+    if state.config.retention.is_enabled() {
+        tokio::spawn(retention_loop(state.clone()));
+    }
+    if state.disk_guard.is_enabled() {
+        tokio::spawn(disk_guard_loop(state));
+    }
+}
+
+async fn trash_purge_loop(state: Arc<AppState>) {
+    let max_age = Duration::from_secs(state.config.trash_days * 24 * 60 * 60);
+    let mut ticker = tokio::time::interval(TRASH_PURGE_INTERVAL);
+    loop {
+        ticker.tick().await;
+        match state.outputs.purge_trash_older_than(max_age).await {
+            Ok(0) => {}
+            Ok(purged) => info!(
+                purged,
+                trash_days = state.config.trash_days,
+                "purged expired trash"
+            ),
+            Err(e) => warn!(error = %e, "failed to purge trash"),
+        }
+    }
+}
+
+async fn retention_loop(state: Arc<AppState>) {
+    let period = Duration::from_secs(state.config.retention.interval_minutes * 60);
+    let mut ticker = tokio::time::interval(period);
+    loop {
+        ticker.tick().await;
+        if let Err(e) = run_retention(&state, false).await {
+            warn!(error = %e, "output retention failed");
+        }
+    }
+}
+
+async fn disk_guard_loop(state: Arc<AppState>) {
+    let mut ticker = tokio::time::interval(DISK_GUARD_INTERVAL);
+    loop {
+        ticker.tick().await;
+        // Logs pause/resume transitions.
+        state.disk_guard.refresh();
+    }
+}
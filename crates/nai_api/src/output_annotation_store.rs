@@ -0,0 +1,350 @@
+use std::collections::HashMap;
+
+use anyhow::Context;
+use rusqlite::{Connection, OptionalExtension, params};
+
+use nai_core::{
+    dto::{OutputAnnotation, OutputItem},
+    outputs::{OutputFileStat, OutputStore},
+};
+
+use crate::{db::Database, last_generation::now_ms, prompt_snippet_store::normalize_tags};
+
+/// Gallery filter shared by the outputs list and bulk operations.
+#[derive(Debug, Clone, Default, serde::Deserialize)]
+pub struct OutputFilter {
+    pub favorite: Option<bool>,
+    pub min_rating: Option<u8>,
+    /// All listed tags must be present.
+    #[serde(default)]
+    pub tags: Vec<String>,
+    pub op_type: Option<String>,
+}
+
+impl OutputFilter {
+    pub fn needs_annotations(&self) -> bool {
+        self.favorite.is_some() || self.min_rating.is_some() || !self.tags.is_empty()
+    }
+
+    pub fn matches(&self, item: &OutputItem) -> bool {
+        if let Some(op) = &self.op_type
+            && &item.op_type != op
+        {
+            return false;
+        }
+        let a = &item.annotation;
+        if let Some(fav) = self.favorite
+            && a.favorite != fav
+        {
+            return false;
+        }
+        if let Some(min) = self.min_rating
+            && a.rating.unwrap_or(0) < min
+        {
+            return false;
+        }
+        let wanted = normalize_tags(self.tags.iter().map(|s| s.as_str()));
+        wanted.iter().all(|t| a.tags.contains(t))
+    }
+}
+
+/// Partial update applied to a set of outputs.
+#[derive(Debug, Clone, Default)]
+pub struct AnnotationPatch {
+    pub favorite: Option<bool>,
+    /// `Some(0)` clears the rating.
+    pub rating: Option<u8>,
+    pub tags: Option<Vec<String>>,
+    pub add_tags: Vec<String>,
+    pub remove_tags: Vec<String>,
+}
+
+impl AnnotationPatch {
+    fn apply(&self, a: &mut OutputAnnotation) {
+        if let Some(fav) = self.favorite {
+            a.favorite = fav;
+        }
+        if let Some(r) = self.rating {
+            a.rating = (r > 0).then_some(r);
+        }
+        if let Some(tags) = &self.tags {
+            a.tags = tags.clone();
+        }
+        a.tags.extend(self.add_tags.iter().cloned());
+        let remove = normalize_tags(self.remove_tags.iter().map(|s| s.as_str()));
+        a.tags = normalize_tags(a.tags.iter().map(|s| s.as_str()))
+            .into_iter()
+            .filter(|t| !remove.contains(t))
+            .collect();
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct OutputTagCount {
+    pub tag: String,
+    pub count: usize,
+}
+
+/// Favourites, ratings and tags for outputs.
+///
+/// Annotations are keyed by content fingerprint rather than path, so they follow a file
+/// when it is moved or renamed. `output_index` caches path -> fingerprint using size/mtime.
+#[derive(Debug, Clone)]
+pub struct OutputAnnotationStore {
+    db: Database,
+}
+
+struct IndexRow {
+    path: String,
+    fingerprint: String,
+    size: i64,
+    modified_ms: i64,
+}
+
+impl OutputAnnotationStore {
+    pub fn new(db: Database) -> anyhow::Result<Self> {
+        db.with_conn(Self::init_schema)?;
+        Ok(Self { db })
+    }
+
+    /// Fill `annotation` on each item, fingerprinting files the index hasn't seen yet.
+    pub async fn annotate_items(
+        &self,
+        outputs: &OutputStore,
+        items: &mut [OutputItem],
+    ) -> anyhow::Result<()> {
+        let files = items
+            .iter()
+            .map(|i| {
+                let stat = OutputFileStat {
+                    size: i.size,
+                    modified_ms: i.modified_ms,
+                };
+                (i.path.clone(), Some(stat))
+            })
+            .collect();
+        let fingerprints = self.fingerprints(outputs, files).await?;
+        let wanted: Vec<String> = fingerprints.iter().flatten().cloned().collect();
+        let annotations = self.load(wanted).await?;
+        for (item, fp) in items.iter_mut().zip(fingerprints) {
+            item.annotation = fp
+                .and_then(|fp| annotations.get(&fp).cloned())
+                .unwrap_or_default();
+        }
+        Ok(())
+    }
+
+    /// Apply `patch` to every listed output. Returns how many were updated.
+    pub async fn update(
+        &self,
+        outputs: &OutputStore,
+        paths: &[String],
+        patch: AnnotationPatch,
+    ) -> anyhow::Result<usize> {
+        let paths: Vec<String> = paths.iter().map(|p| p.replace('\\', "/")).collect();
+        let stats = outputs.file_stats(&paths).await?;
+        let fingerprints = self
+            .fingerprints(outputs, paths.into_iter().zip(stats).collect())
+            .await?;
+        let targets: Vec<String> = fingerprints.into_iter().flatten().collect();
+
+        self.db
+            .with_conn_blocking("output annotations update", move |conn| {
+                let tx = conn.transaction()?;
+                let mut updated = 0usize;
+                for fp in targets {
+                    let mut a = load_one(&tx, &fp)?.unwrap_or_default();
+                    patch.apply(&mut a);
+                    if a == OutputAnnotation::default() {
+                        tx.execute(
+                            "DELETE FROM output_annotations WHERE fingerprint = ?1",
+                            params![fp],
+                        )?;
+                    } else {
+                        let tags_json = serde_json::to_string(&a.tags).context("serialize tags")?;
+                        tx.execute(
+                            "INSERT INTO output_annotations (fingerprint, favorite, rating, tags_json, updated_at_ms) VALUES (?1, ?2, ?3, ?4, ?5)\
+                             ON CONFLICT(fingerprint) DO UPDATE SET favorite=excluded.favorite, rating=excluded.rating, tags_json=excluded.tags_json, updated_at_ms=excluded.updated_at_ms",
+                            params![fp, a.favorite, a.rating, tags_json, now_ms()],
+                        )?;
+                    }
+                    updated += 1;
+                }
+                tx.commit()?;
+                Ok(updated)
+            })
+            .await
+    }
+
+    /// All tags in use with their output counts, most used first.
+    pub async fn tag_counts(&self) -> anyhow::Result<Vec<OutputTagCount>> {
+        self.db
+            .with_conn_blocking("output annotations tags", move |conn| {
+                let mut stmt = conn.prepare("SELECT tags_json FROM output_annotations")?;
+                let mut rows = stmt.query([])?;
+                let mut counts = HashMap::<String, usize>::new();
+                while let Some(r) = rows.next()? {
+                    let json: String = r.get(0)?;
+                    let tags: Vec<String> = serde_json::from_str(&json).unwrap_or_default();
+                    for t in tags {
+                        *counts.entry(t).or_default() += 1;
+                    }
+                }
+                let mut out: Vec<OutputTagCount> = counts
+                    .into_iter()
+                    .map(|(tag, count)| OutputTagCount { tag, count })
+                    .collect();
+                out.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.tag.cmp(&b.tag)));
+                Ok(out)
+            })
+            .await
+    }
+
+    /// Drop index rows for paths that no longer exist. Annotations stay keyed by content.
+    pub async fn forget_paths(&self, paths: &[String]) -> anyhow::Result<()> {
+        let paths: Vec<String> = paths.iter().map(|p| p.replace('\\', "/")).collect();
+        self.db
+            .with_conn_blocking("output index forget", move |conn| {
+                let tx = conn.transaction()?;
+                for p in paths {
+                    tx.execute("DELETE FROM output_index WHERE path = ?1", params![p])?;
+                }
+                tx.commit()?;
+                Ok(())
+            })
+            .await
+    }
+
+    /// Fingerprints of `files` (path and stat, `None` when missing), reusing the
+    /// index while a file's size and mtime are unchanged.
+    async fn fingerprints(
+        &self,
+        outputs: &OutputStore,
+        files: Vec<(String, Option<OutputFileStat>)>,
+    ) -> anyhow::Result<Vec<Option<String>>> {
+        let lookup: Vec<String> = files.iter().map(|(p, _)| p.clone()).collect();
+        let known = self
+            .db
+            .with_conn_blocking("output index lookup", move |conn| {
+                let mut stmt = conn.prepare_cached(
+                    "SELECT fingerprint, size, modified_ms FROM output_index WHERE path = ?1",
+                )?;
+                let mut out = HashMap::new();
+                for p in lookup {
+                    let row: Option<(String, i64, i64)> = stmt
+                        .query_row(params![p], |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?)))
+                        .optional()?;
+                    if let Some(row) = row {
+                        out.insert(p, row);
+                    }
+                }
+                Ok(out)
+            })
+            .await?;
+
+        let mut result = Vec::with_capacity(files.len());
+        let mut fresh = Vec::new();
+        for (path, stat) in files {
+            let Some(stat) = stat else {
+                result.push(None);
+                continue;
+            };
+            let size = stat.size as i64;
+            match known.get(&path) {
+                Some((fp, s, m)) if *s == size && *m == stat.modified_ms => {
+                    result.push(Some(fp.clone()));
+                }
+                _ => {
+                    let fp = outputs.fingerprint(&path, stat.size).await?;
+                    result.push(Some(fp.clone()));
+                    fresh.push(IndexRow {
+                        path,
+                        fingerprint: fp,
+                        size,
+                        modified_ms: stat.modified_ms,
+                    });
+                }
+            }
+        }
+
+        if !fresh.is_empty() {
+            self.db
+                .with_conn_blocking("output index save", move |conn| {
+                    let tx = conn.transaction()?;
+                    for row in fresh {
+                        tx.execute(
+                            "INSERT OR REPLACE INTO output_index (path, fingerprint, size, modified_ms) VALUES (?1, ?2, ?3, ?4)",
+                            params![row.path, row.fingerprint, row.size, row.modified_ms],
+                        )?;
+                    }
+                    tx.commit()?;
+                    Ok(())
+                })
+                .await?;
+        }
+        Ok(result)
+    }
+
+    async fn load(
+        &self,
+        fingerprints: Vec<String>,
+    ) -> anyhow::Result<HashMap<String, OutputAnnotation>> {
+        self.db
+            .with_conn_blocking("output annotations load", move |conn| {
+                let mut out = HashMap::new();
+                for fp in fingerprints {
+                    if out.contains_key(&fp) {
+                        continue;
+                    }
+                    if let Some(a) = load_one(conn, &fp)? {
+                        out.insert(fp, a);
+                    }
+                }
+                Ok(out)
+            })
+            .await
+    }
+
+    fn init_schema(conn: &mut Connection) -> anyhow::Result<()> {
+        conn.execute_batch(
+            "\
+            CREATE TABLE IF NOT EXISTS output_index (
+                path TEXT NOT NULL PRIMARY KEY,
+                fingerprint TEXT NOT NULL,
+                size INTEGER NOT NULL,
+                modified_ms INTEGER NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS output_index_fingerprint ON output_index(fingerprint);
+            CREATE TABLE IF NOT EXISTS output_annotations (
+                fingerprint TEXT NOT NULL PRIMARY KEY,
+                favorite INTEGER NOT NULL DEFAULT 0,
+                rating INTEGER,
+                tags_json TEXT NOT NULL,
+                updated_at_ms INTEGER NOT NULL
+            );
+            ",
+        )
+        .context("init output annotations schema")?;
+        Ok(())
+    }
+}
+
+fn load_one(conn: &Connection, fingerprint: &str) -> anyhow::Result<Option<OutputAnnotation>> {
+    let row: Option<(bool, Option<u8>, String)> = conn
+        .prepare_cached(
+            "SELECT favorite, rating, tags_json FROM output_annotations WHERE fingerprint = ?1",
+        )?
+        .query_row(params![fingerprint], |r| {
+            Ok((r.get(0)?, r.get(1)?, r.get(2)?))
+        })
+        .optional()?;
+    let Some((favorite, rating, tags_json)) = row else {
+        return Ok(None);
+    };
+    let tags: Vec<String> = serde_json::from_str(&tags_json).context("parse output tags")?;
+    Ok(Some(OutputAnnotation {
+        favorite,
+        rating,
+        tags,
+    }))
+}
@@ -0,0 +1,101 @@
+use std::{collections::BTreeMap, path::Path};
+
+use anyhow::Context;
+use async_trait::async_trait;
+use rusqlite::{Connection, params};
+use tracing::info;
+
+use nai_core::outputs::OutputCounters;
+
+use crate::db::Database;
+
+/// Legacy cursor file written by older versions into the outputs root.
+pub const LEGACY_COUNTERS_FILE: &str = "output_counters.json";
+
+/// `<编号>` cursors in SQLite. Each reservation is a single atomic upsert, so processes
+/// sharing an output dir never hand out the same index.
+#[derive(Debug, Clone)]
+pub struct OutputCounterStore {
+    db: Database,
+}
+
+impl OutputCounterStore {
+    pub fn new(db: Database) -> anyhow::Result<Self> {
+        db.with_conn(Self::init_schema)?;
+        Ok(Self { db })
+    }
+
+    /// One-time import of `output_counters.json`. The file is renamed afterwards so
+    /// later startups skip it.
+    pub fn migrate_legacy_json(&self, output_dir: &Path) -> anyhow::Result<()> {
+        let path = output_dir.join(LEGACY_COUNTERS_FILE);
+        let bytes = match std::fs::read(&path) {
+            Ok(b) => b,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e).with_context(|| format!("read {}", path.display())),
+        };
+        let floors: BTreeMap<String, usize> =
+            serde_json::from_slice(&bytes).with_context(|| format!("parse {}", path.display()))?;
+        let count = floors.len();
+        self.db.with_conn(|conn| raise_to(conn, &floors))?;
+
+        let migrated = path.with_extension("json.migrated");
+        std::fs::rename(&path, &migrated).with_context(|| format!("rename {}", path.display()))?;
+        info!(dirs = count, from = %path.display(), "migrated output counters to sqlite");
+        Ok(())
+    }
+
+    fn init_schema(conn: &mut Connection) -> anyhow::Result<()> {
+        conn.execute_batch(
+            "\
+            CREATE TABLE IF NOT EXISTS output_counters (
+                dir TEXT NOT NULL PRIMARY KEY,
+                next_index INTEGER NOT NULL
+            );
+            ",
+        )
+        .context("init output counters schema")?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl OutputCounters for OutputCounterStore {
+    async fn next_index(&self, dir_key: &str) -> anyhow::Result<usize> {
+        let dir = dir_key.to_string();
+        self.db
+            .with_conn_blocking("output counters next", move |conn| {
+                let idx: i64 = conn.query_row(
+                    "INSERT INTO output_counters (dir, next_index) VALUES (?1, 1) \
+                     ON CONFLICT(dir) DO UPDATE SET next_index = next_index + 1 \
+                     RETURNING next_index - 1",
+                    params![dir],
+                    |r| r.get(0),
+                )?;
+                Ok(idx as usize)
+            })
+            .await
+    }
+
+    async fn raise_to(&self, floors: BTreeMap<String, usize>) -> anyhow::Result<()> {
+        if floors.is_empty() {
+            return Ok(());
+        }
+        self.db
+            .with_conn_blocking("output counters raise", move |conn| raise_to(conn, &floors))
+            .await
+    }
+}
+
+fn raise_to(conn: &mut Connection, floors: &BTreeMap<String, usize>) -> anyhow::Result<()> {
+    let tx = conn.transaction()?;
+    for (dir, next) in floors {
+        tx.execute(
+            "INSERT INTO output_counters (dir, next_index) VALUES (?1, ?2) \
+             ON CONFLICT(dir) DO UPDATE SET next_index = MAX(next_index, excluded.next_index)",
+            params![dir, *next as i64],
+        )?;
+    }
+    tx.commit()?;
+    Ok(())
+}
@@ -0,0 +1,85 @@
+use std::collections::BTreeMap;
+
+use anyhow::Context;
+use async_trait::async_trait;
+use rusqlite::{Connection, OptionalExtension, params};
+
+use nai_core::metadata::OutputMetadataStore;
+
+use crate::{db::Database, last_generation::now_ms};
+
+/// PNG text chunks of outputs re-encoded to WebP/JPEG/AVIF, so their generation
+/// parameters survive the format change.
+#[derive(Debug, Clone)]
+pub struct OutputMetadataDb {
+    db: Database,
+}
+
+impl OutputMetadataDb {
+    pub fn new(db: Database) -> anyhow::Result<Self> {
+        db.with_conn(Self::init_schema)?;
+        Ok(Self { db })
+    }
+
+    fn init_schema(conn: &mut Connection) -> anyhow::Result<()> {
+        conn.execute_batch(
+            "\
+            CREATE TABLE IF NOT EXISTS output_metadata (
+                path TEXT NOT NULL PRIMARY KEY,
+                chunks_json TEXT NOT NULL,
+                created_at_ms INTEGER NOT NULL
+            );
+            ",
+        )
+        .context("init output metadata schema")?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl OutputMetadataStore for OutputMetadataDb {
+    async fn save(&self, rel: &str, chunks: BTreeMap<String, String>) -> anyhow::Result<()> {
+        let path = rel.replace('\\', "/");
+        let chunks_json = serde_json::to_string(&chunks).context("serialize output metadata")?;
+        self.db
+            .with_conn_blocking("output metadata save", move |conn| {
+                conn.execute(
+                    "INSERT OR REPLACE INTO output_metadata (path, chunks_json, created_at_ms) VALUES (?1, ?2, ?3)",
+                    params![path, chunks_json, now_ms()],
+                )?;
+                Ok(())
+            })
+            .await
+    }
+
+    async fn load(&self, rel: &str) -> anyhow::Result<Option<BTreeMap<String, String>>> {
+        let path = rel.replace('\\', "/");
+        self.db
+            .with_conn_blocking("output metadata load", move |conn| {
+                let json: Option<String> = conn
+                    .query_row(
+                        "SELECT chunks_json FROM output_metadata WHERE path = ?1",
+                        params![path],
+                        |r| r.get(0),
+                    )
+                    .optional()?;
+                json.map(|j| serde_json::from_str(&j).context("parse output metadata"))
+                    .transpose()
+            })
+            .await
+    }
+
+    async fn remove(&self, rels: &[String]) -> anyhow::Result<()> {
+        let paths: Vec<String> = rels.iter().map(|p| p.replace('\\', "/")).collect();
+        self.db
+            .with_conn_blocking("output metadata remove", move |conn| {
+                let tx = conn.transaction()?;
+                for p in paths {
+                    tx.execute("DELETE FROM output_metadata WHERE path = ?1", params![p])?;
+                }
+                tx.commit()?;
+                Ok(())
+            })
+            .await
+    }
+}
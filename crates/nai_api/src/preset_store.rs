@@ -1,7 +1,13 @@
 use anyhow::Context;
 use rusqlite::{Connection, OptionalExtension, params};
 
-use crate::{db::Database, last_generation::now_ms};
+use crate::{
+    db::Database,
+    last_generation::now_ms,
+    revision_store::{backfill_revisions, record_revision, rename_revisions},
+};
+
+const TABLE: &str = "presets";
 
 pub const DEFAULT_PRESET_NAME: &str = "默认";
 
@@ -58,12 +64,28 @@ impl PresetStore {
                         "INSERT INTO presets (model, name, updated_at_ms, preset_json) VALUES (?1, ?2, ?3, ?4)",
                         params![m, DEFAULT_PRESET_NAME, now_ms(), preset_json],
                     )?;
+                    record_revision(conn, TABLE, &m, DEFAULT_PRESET_NAME, Some(&preset_json))?;
                 }
                 Ok(())
             })
             .await
     }
 
+    /// Models with at least one preset.
+    pub async fn list_models(&self) -> anyhow::Result<Vec<String>> {
+        self.db
+            .with_conn_blocking("list preset models", move |conn| {
+                let mut stmt = conn.prepare("SELECT DISTINCT model FROM presets ORDER BY model")?;
+                let mut rows = stmt.query([])?;
+                let mut out = vec![];
+                while let Some(r) = rows.next()? {
+                    out.push(r.get::<_, String>(0)?);
+                }
+                Ok(out)
+            })
+            .await
+    }
+
     pub async fn list_names(&self, model: &str) -> anyhow::Result<Vec<String>> {
         let model = model.to_string();
         self.db
@@ -114,11 +136,9 @@ impl PresetStore {
 
         self.db
             .with_conn_blocking("upsert preset", move |conn| {
-                conn.execute(
-                    "INSERT INTO presets (model, name, updated_at_ms, preset_json) VALUES (?1, ?2, ?3, ?4)\
-                     ON CONFLICT(model, name) DO UPDATE SET updated_at_ms=excluded.updated_at_ms, preset_json=excluded.preset_json",
-                    params![model, name, now_ms(), preset_json],
-                )?;
+                let tx = conn.transaction()?;
+                write_preset_json(&tx, &model, &name, &preset_json)?;
+                tx.commit()?;
                 Ok(())
             })
             .await
@@ -129,10 +149,15 @@ impl PresetStore {
         let name = name.to_string();
         self.db
             .with_conn_blocking("delete preset", move |conn| {
-                let rows = conn.execute(
+                let tx = conn.transaction()?;
+                let rows = tx.execute(
                     "DELETE FROM presets WHERE model = ?1 AND name = ?2",
                     params![model, name],
                 )?;
+                if rows > 0 {
+                    record_revision(&tx, TABLE, &model, &name, None)?;
+                }
+                tx.commit()?;
                 Ok(rows > 0)
             })
             .await
@@ -173,6 +198,7 @@ impl PresetStore {
                     "UPDATE presets SET name = ?3, updated_at_ms = ?4 WHERE model = ?1 AND name = ?2",
                     params![model, from, to, now_ms()],
                 )?;
+                rename_revisions(&tx, TABLE, &model, &from, &to)?;
                 tx.commit()?;
                 Ok(())
             })
@@ -192,6 +218,11 @@ impl PresetStore {
             ",
         )
         .context("init presets schema")?;
+        backfill_revisions(
+            conn,
+            TABLE,
+            "SELECT model AS scope, name, updated_at_ms, preset_json AS value_json FROM presets",
+        )?;
         Ok(())
     }
 }
@@ -210,9 +241,26 @@ fn default_preset_for_model(model: &str) -> GeneratePreset {
         seed: -1,
         add_quality_tags: true,
         undesired_content_preset: "None".to_string(),
-        sm: is_v3 && false,
-        sm_dyn: is_v3 && false,
-        use_coords: !is_v3 && true,
-        legacy_uc: !is_v3 && false,
+        sm: false,
+        sm_dyn: false,
+        use_coords: !is_v3,
+        legacy_uc: false,
     }
 }
+
+/// Insert or replace a generation preset, recording a revision. Runs inside
+/// the caller's transaction.
+pub(crate) fn write_preset_json(
+    conn: &Connection,
+    model: &str,
+    name: &str,
+    preset_json: &str,
+) -> anyhow::Result<()> {
+    conn.execute(
+        "INSERT INTO presets (model, name, updated_at_ms, preset_json) VALUES (?1, ?2, ?3, ?4)\
+         ON CONFLICT(model, name) DO UPDATE SET updated_at_ms=excluded.updated_at_ms, preset_json=excluded.preset_json",
+        params![model, name, now_ms(), preset_json],
+    )?;
+    record_revision(conn, TABLE, model, name, Some(preset_json))?;
+    Ok(())
+}
@@ -0,0 +1,49 @@
+use std::collections::HashSet;
+
+use nai_core::{
+    config::AppConfig,
+    dto::CharacterPrompt,
+    prompt::{
+        lint::{LintIssue, lint},
+        snippet::snippet_refs,
+    },
+};
+
+use crate::prompt_snippet_store::PromptSnippetStore;
+
+/// Lint prompts as written, before snippets are expanded, so positions match
+/// the editor's text.
+pub async fn lint_prompts(
+    cfg: &AppConfig,
+    snippets: &PromptSnippetStore,
+    positive: &str,
+    negative: &str,
+    characters: &[CharacterPrompt],
+) -> anyhow::Result<Vec<LintIssue>> {
+    let texts = [positive, negative].into_iter().chain(
+        characters
+            .iter()
+            .flat_map(|c| [c.prompt.as_str(), c.uc.as_str()]),
+    );
+    let mut known = HashSet::new();
+    for text in texts {
+        for r in snippet_refs(text) {
+            if !r.name.is_empty()
+                && !known.contains(r.name)
+                && snippets
+                    .get(r.name.trim_start_matches('/'))
+                    .await?
+                    .is_some()
+            {
+                known.insert(r.name.to_string());
+            }
+        }
+    }
+    Ok(lint(
+        positive,
+        negative,
+        characters,
+        &cfg.lint_rules,
+        |name| known.contains(name),
+    ))
+}
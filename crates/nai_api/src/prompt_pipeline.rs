@@ -0,0 +1,30 @@
+//! The prompt steps after snippet expansion, shared by direct generation and jobs.
+//! Formatting (`format_str`) runs on the results when the payload is built.
+
+use tracing::warn;
+
+use nai_core::dto::BaseGenerateRequest;
+
+use crate::{routes::AppState, wildcard_expand::expand_wildcards};
+
+/// Prepare the prompts of image `index` of a job (0 outside jobs): expand
+/// `__wildcard__` tokens and `{a|b}` choices, normalise tag aliases when
+/// enabled, then warn about prompts over the text encoder's limit.
+pub(crate) async fn prepare_prompts(
+    state: &AppState,
+    base: &mut BaseGenerateRequest,
+    index: usize,
+) -> anyhow::Result<()> {
+    for w in expand_wildcards(&state.wildcards, base, index).await? {
+        warn!(warning = %w, "wildcard warning");
+    }
+    if state.config.normalize_tags {
+        state.tags.normalize_request(base).await?;
+    }
+    if let Some(report) = state.tokenizers.report(base) {
+        for w in report.warnings() {
+            warn!(warning = %w, "token limit warning");
+        }
+    }
+    Ok(())
+}
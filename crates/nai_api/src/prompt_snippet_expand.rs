@@ -1,14 +1,18 @@
 use std::collections::HashMap;
 
 use async_recursion::async_recursion;
-use regex::Regex;
-
-use nai_core::{config::AppConfig, prompt};
+use nai_core::{
+    config::AppConfig,
+    prompt::{
+        self,
+        snippet::{self, SnippetRef, snippet_refs},
+    },
+};
 
 use crate::prompt_snippet_store::PromptSnippetStore;
 
-const MAX_DEPTH: usize = 8;
-const MAX_TOTAL_EXPANSIONS: usize = 64;
+pub(crate) const MAX_DEPTH: usize = 8;
+pub(crate) const MAX_TOTAL_EXPANSIONS: usize = 64;
 
 #[derive(Debug, Clone, serde::Serialize)]
 pub struct SnippetExpansionResult {
@@ -27,8 +31,28 @@ pub async fn expand_prompts_pair(
     let mut warnings = Vec::new();
     let mut total = 0usize;
 
-    let pos = expand_text(cfg, store, positive, &mut cache, &mut warnings, &mut total).await?;
-    let neg = expand_text(cfg, store, negative, &mut cache, &mut warnings, &mut total).await?;
+    let pos = expand_text(
+        cfg,
+        store,
+        positive,
+        &mut cache,
+        &mut warnings,
+        &mut total,
+        &[],
+        0,
+    )
+    .await?;
+    let neg = expand_text(
+        cfg,
+        store,
+        negative,
+        &mut cache,
+        &mut warnings,
+        &mut total,
+        &[],
+        0,
+    )
+    .await?;
 
     Ok(SnippetExpansionResult {
         positive: pos,
@@ -37,6 +61,8 @@ pub async fn expand_prompts_pair(
     })
 }
 
+#[async_recursion]
+#[allow(clippy::too_many_arguments)]
 async fn expand_text(
     cfg: &AppConfig,
     store: &PromptSnippetStore,
@@ -44,18 +70,17 @@ async fn expand_text(
     cache: &mut HashMap<String, String>,
     warnings: &mut Vec<String>,
     total: &mut usize,
+    stack: &[String],
+    depth: usize,
 ) -> anyhow::Result<String> {
-    let token_re = Regex::new(r"<\s*snippet:([^>]+?)\s*>").expect("valid snippet regex");
-
     let mut out = String::new();
     let mut last_idx = 0;
 
-    for cap in token_re.captures_iter(text) {
-        let m = cap.get(0).expect("full match");
-        let name = cap.get(1).map(|m| m.as_str()).unwrap_or("").trim();
+    for token in snippet_refs(text) {
+        let name = token.name;
 
-        out.push_str(&text[last_idx..m.start()]);
-        last_idx = m.end();
+        out.push_str(&text[last_idx..token.span.start]);
+        last_idx = token.span.end;
 
         if name.is_empty() {
             warnings.push("空 snippet 名称已忽略".to_string());
@@ -70,8 +95,12 @@ async fn expand_text(
         }
         *total += 1;
 
+        for arg in &token.malformed {
+            warnings.push(format!("片段 {name} 的参数无法解析：{arg}"));
+        }
+
         let expanded =
-            resolve_snippet(cfg, store, name, cache, warnings, total, &mut Vec::new(), 0).await?;
+            resolve_snippet(cfg, store, &token, cache, warnings, total, stack, depth).await?;
 
         if expanded.is_empty() {
             warnings.push(format!("片段 {name} 无法展开，已移除"));
@@ -84,46 +113,92 @@ async fn expand_text(
 }
 
 #[async_recursion]
+#[allow(clippy::too_many_arguments)]
 async fn resolve_snippet(
     cfg: &AppConfig,
     store: &PromptSnippetStore,
-    name: &str,
+    token: &SnippetRef<'_>,
     cache: &mut HashMap<String, String>,
     warnings: &mut Vec<String>,
     total: &mut usize,
-    stack: &mut Vec<String>,
+    stack: &[String],
     depth: usize,
 ) -> anyhow::Result<String> {
     if depth >= MAX_DEPTH {
-        warnings.push(format!("递归深度超过 {MAX_DEPTH}，跳过 {name}"));
+        warnings.push(format!("递归深度超过 {MAX_DEPTH}，跳过 {}", token.name));
         return Ok(String::new());
     }
 
-    if stack.contains(&name.to_string()) {
-        let mut chain = stack.clone();
+    // Relative to the referring snippet's folder first, then from the root.
+    let folder = stack.last().map_or("", |s| snippet::snippet_folder(s));
+    let mut found = None;
+    for candidate in snippet::snippet_candidates(folder, token.name) {
+        if let Some(snippet) = store.get(&candidate).await? {
+            found = Some((candidate, snippet));
+            break;
+        }
+    }
+    let Some((name, snippet)) = found else {
+        warnings.push(format!("片段不存在：{}", token.name));
+        return Ok(String::new());
+    };
+    let name = name.as_str();
+
+    if stack.iter().any(|s| s == name) {
+        let mut chain = stack.to_vec();
         chain.push(name.to_string());
         let desc = chain.join(" -> ");
         warnings.push(format!("检测到循环引用：{desc}"));
         return Ok(String::new());
     }
 
-    if let Some(cached) = cache.get(name) {
+    // The same snippet expands differently per set of arguments.
+    let cache_key = if token.args.is_empty() {
+        name.to_string()
+    } else {
+        format!("{name} {:?}", token.args)
+    };
+    if let Some(cached) = cache.get(&cache_key) {
         return Ok(cached.clone());
     }
 
-    let Some(snippet) = store.get(name).await? else {
-        warnings.push(format!("片段不存在：{name}"));
-        return Ok(String::new());
-    };
+    let declared = |key: &str| snippet.params.iter().find(|p| p.name == key);
+    for (key, _) in &token.args {
+        if declared(key).is_none() {
+            warnings.push(format!("片段 {name} 没有参数 {key}，已忽略"));
+        }
+    }
+    for param in &snippet.params {
+        if param.default.is_none() && !token.args.iter().any(|(k, _)| *k == param.name) {
+            warnings.push(format!("片段 {name} 缺少参数 {}", param.name));
+        }
+    }
+    let (body, undeclared) = snippet::substitute(&snippet.body, |key| {
+        let param = declared(key)?;
+        let passed = token.args.iter().rev().find(|(k, _)| *k == key);
+        Some(passed.map_or_else(|| param.default.as_deref().unwrap_or(""), |(_, v)| *v))
+    });
+    for key in undeclared {
+        warnings.push(format!("片段 {name} 引用了未声明的参数 {key}，已移除"));
+    }
 
-    let mut new_stack = stack.clone();
+    let mut new_stack = stack.to_vec();
     new_stack.push(name.to_string());
 
     let mut local_total = *total;
-    let expanded_body =
-        expand_text(cfg, store, &snippet.body, cache, warnings, &mut local_total).await?;
+    let expanded_body = expand_text(
+        cfg,
+        store,
+        &body,
+        cache,
+        warnings,
+        &mut local_total,
+        &new_stack,
+        depth + 1,
+    )
+    .await?;
     *total = local_total;
 
-    cache.insert(name.to_string(), expanded_body.clone());
+    cache.insert(cache_key, expanded_body.clone());
     Ok(expanded_body)
 }
@@ -1,17 +1,39 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 
 use anyhow::Context;
-use rusqlite::{Connection, OptionalExtension, params};
+use nai_core::prompt::snippet::check_snippet_name;
+use rusqlite::{Connection, OptionalExtension, params, params_from_iter, types::Value};
+use tracing::warn;
 
-use crate::{db::Database, last_generation::now_ms};
+use crate::{
+    db::Database,
+    last_generation::now_ms,
+    revision_store::{backfill_revisions, record_revision, rename_revisions},
+    snippet_graph::{
+        SnippetGraph, SnippetReferrer, build_graph, rewrite_legacy_references, rewrite_references,
+    },
+};
 
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, Default)]
+const TABLE: &str = "prompt_snippets";
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize, Default)]
 pub struct PromptSnippet {
     pub body: String,
     #[serde(default)]
     pub tags: Vec<String>,
     #[serde(default)]
     pub description: Option<String>,
+    /// Arguments the body uses as `${name}`, passed as `<snippet:name key=value>`.
+    #[serde(default)]
+    pub params: Vec<SnippetParam>,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize, Default)]
+pub struct SnippetParam {
+    pub name: String,
+    /// Used when the argument isn't passed; the argument is required without one.
+    #[serde(default)]
+    pub default: Option<String>,
 }
 
 #[derive(Debug, Clone, serde::Serialize)]
@@ -19,6 +41,53 @@ pub struct PromptSnippetSummary {
     pub name: String,
     pub tags: Vec<String>,
     pub description: Option<String>,
+    /// The best matching part of the body, hits in `<mark>` tags (brackets
+    /// are prompt syntax); only for text searches.
+    pub highlight: Option<String>,
+}
+
+/// How a list of tags filters snippets.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TagMode {
+    /// Any of the tags.
+    #[default]
+    Any,
+    /// Every tag.
+    All,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SnippetQuery {
+    /// Words each found anywhere in the name, tags, description or body.
+    pub q: Option<String>,
+    pub tags: Vec<String>,
+    pub tag_mode: TagMode,
+    pub limit: usize,
+    pub offset: usize,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct SnippetFolder {
+    /// Empty for the root.
+    pub path: String,
+    pub folders: Vec<SnippetSubfolder>,
+    /// Full names of the snippets directly in the folder.
+    pub snippets: Vec<String>,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct SnippetSubfolder {
+    pub path: String,
+    /// Snippets anywhere below it.
+    pub count: usize,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct SnippetPage {
+    pub items: Vec<PromptSnippetSummary>,
+    pub next_offset: usize,
+    pub has_more: bool,
 }
 
 #[derive(Debug, Clone)]
@@ -32,61 +101,105 @@ impl PromptSnippetStore {
         Ok(Self { db })
     }
 
-    pub async fn list(
-        &self,
-        query: Option<&str>,
-        tags: &[String],
-    ) -> anyhow::Result<Vec<PromptSnippetSummary>> {
-        let q = query.map(|s| s.to_string());
-        let tags = normalize_tags(tags.iter().map(|s| s.as_str()));
+    /// Substring search over name, tags, description and body, best matches
+    /// first; without a query, most recently updated first.
+    pub async fn list(&self, query: SnippetQuery) -> anyhow::Result<SnippetPage> {
+        let words: Vec<&str> = query
+            .q
+            .as_deref()
+            .unwrap_or("")
+            .split_whitespace()
+            .collect();
+        // The trigram index only finds words of three characters or more;
+        // shorter ones (two CJK characters are a word) are matched with LIKE.
+        let (long, short): (Vec<&str>, Vec<&str>) =
+            words.into_iter().partition(|w| w.chars().count() >= 3);
+        let terms = fts_query(&long);
+        let likes: Vec<String> = short.iter().map(|w| like_pattern(w)).collect();
+        let tags = normalize_tags(query.tags.iter().map(|s| s.as_str()));
 
         self.db
             .with_conn_blocking("snippet list", move |conn| {
-                let mut stmt = conn.prepare(
-                    "SELECT name, preset_json FROM prompt_snippets ORDER BY updated_at_ms DESC",
-                )?;
-                let mut rows = stmt.query([])?;
-                let mut out = Vec::new();
+                let mut args: Vec<Value> = Vec::new();
+                let mut filters = Vec::new();
+                if !terms.is_empty() {
+                    args.push(Value::Text(terms.clone()));
+                    filters.push(format!("prompt_snippets_fts MATCH ?{}", args.len()));
+                }
+                for pattern in &likes {
+                    args.push(Value::Text(pattern.clone()));
+                    let n = args.len();
+                    filters.push(format!(
+                        "(prompt_snippets_fts.name LIKE ?{n} ESCAPE '\\' \
+                         OR prompt_snippets_fts.body LIKE ?{n} ESCAPE '\\' \
+                         OR prompt_snippets_fts.tags LIKE ?{n} ESCAPE '\\' \
+                         OR prompt_snippets_fts.description LIKE ?{n} ESCAPE '\\')"
+                    ));
+                }
+                if !tags.is_empty() {
+                    let joiner = match query.tag_mode {
+                        TagMode::Any => " OR ",
+                        TagMode::All => " AND ",
+                    };
+                    let clauses = tags
+                        .iter()
+                        .map(|t| {
+                            args.push(Value::Text(t.clone()));
+                            format!(
+                                "EXISTS (SELECT 1 FROM json_each(s.preset_json, '$.tags') \
+                                 WHERE lower(trim(json_each.value)) = ?{})",
+                                args.len()
+                            )
+                        })
+                        .collect::<Vec<_>>();
+                    filters.push(format!("({})", clauses.join(joiner)));
+                }
+                let where_sql = if filters.is_empty() {
+                    String::new()
+                } else {
+                    format!("WHERE {}", filters.join(" AND "))
+                };
+                let (highlight, order) = if terms.is_empty() {
+                    ("NULL", "s.updated_at_ms DESC")
+                } else {
+                    (
+                        "snippet(prompt_snippets_fts, 1, '<mark>', '</mark>', '…', 64)",
+                        "bm25(prompt_snippets_fts, 10.0, 1.0, 5.0, 2.0), s.updated_at_ms DESC",
+                    )
+                };
+                // One extra row tells whether there is another page.
+                args.push(Value::Integer((query.limit + 1) as i64));
+                args.push(Value::Integer(query.offset as i64));
+                let sql = format!(
+                    "SELECT s.name, s.preset_json, {highlight} \
+                     FROM prompt_snippets s JOIN prompt_snippets_fts ON prompt_snippets_fts.rowid = s.rowid \
+                     {where_sql} ORDER BY {order} LIMIT ?{} OFFSET ?{}",
+                    args.len() - 1,
+                    args.len()
+                );
+
+                let mut stmt = conn.prepare(&sql)?;
+                let mut rows = stmt.query(params_from_iter(args))?;
+                let mut items = Vec::new();
                 while let Some(r) = rows.next()? {
                     let name: String = r.get(0)?;
                     let json: String = r.get(1)?;
                     let snippet: PromptSnippet =
                         serde_json::from_str(&json).context("parse snippet")?;
-
-                    if let Some(q) = &q {
-                        let needle = q.to_lowercase();
-                        if !name.to_lowercase().contains(&needle)
-                            && !snippet
-                                .tags
-                                .iter()
-                                .any(|t| t.to_lowercase().contains(&needle))
-                            && snippet
-                                .description
-                                .as_ref()
-                                .map(|d| d.to_lowercase().contains(&needle))
-                                .unwrap_or(false)
-                        {
-                            continue;
-                        }
-                    }
-
-                    if !tags.is_empty() {
-                        let matched = snippet
-                            .tags
-                            .iter()
-                            .any(|t| tags.iter().any(|want| want == &t.to_lowercase()));
-                        if !matched {
-                            continue;
-                        }
-                    }
-
-                    out.push(PromptSnippetSummary {
+                    items.push(PromptSnippetSummary {
                         name,
                         tags: normalize_tags(snippet.tags.iter().map(|s| s.as_str())),
-                        description: snippet.description.clone(),
+                        description: snippet.description,
+                        highlight: r.get(2)?,
                     });
                 }
-                Ok(out)
+                let has_more = items.len() > query.limit;
+                items.truncate(query.limit);
+                Ok(SnippetPage {
+                    next_offset: query.offset + items.len(),
+                    items,
+                    has_more,
+                })
             })
             .await
     }
@@ -113,17 +226,19 @@ impl PromptSnippetStore {
             .await
     }
 
-    pub async fn upsert(&self, name: &str, mut snippet: PromptSnippet) -> anyhow::Result<()> {
-        snippet.tags = normalize_tags(snippet.tags.iter().map(|s| s.as_str()));
-        let name = name.to_string();
-        let json = serde_json::to_string(&snippet).context("serialize snippet")?;
+    pub async fn upsert(&self, name: &str, snippet: PromptSnippet) -> anyhow::Result<()> {
+        self.upsert_many(vec![(name.to_string(), snippet)]).await
+    }
+
+    /// Write `items` in one transaction.
+    pub async fn upsert_many(&self, items: Vec<(String, PromptSnippet)>) -> anyhow::Result<()> {
         self.db
             .with_conn_blocking("snippet upsert", move |conn| {
-                conn.execute(
-                    "INSERT INTO prompt_snippets (name, updated_at_ms, preset_json) VALUES (?1, ?2, ?3)\
-                     ON CONFLICT(name) DO UPDATE SET updated_at_ms=excluded.updated_at_ms, preset_json=excluded.preset_json",
-                    params![name, now_ms(), json],
-                )?;
+                let tx = conn.transaction()?;
+                for (name, snippet) in items {
+                    write_snippet(&tx, &name, snippet)?;
+                }
+                tx.commit()?;
                 Ok(())
             })
             .await
@@ -133,20 +248,32 @@ impl PromptSnippetStore {
         let name = name.to_string();
         self.db
             .with_conn_blocking("snippet delete", move |conn| {
+                let tx = conn.transaction()?;
                 let rows =
-                    conn.execute("DELETE FROM prompt_snippets WHERE name = ?1", params![name])?;
+                    tx.execute("DELETE FROM prompt_snippets WHERE name = ?1", params![name])?;
+                if rows > 0 {
+                    record_revision(&tx, TABLE, "", &name, None)?;
+                }
+                tx.commit()?;
                 Ok(rows > 0)
             })
             .await
     }
 
-    pub async fn rename(&self, from: &str, to: &str) -> anyhow::Result<()> {
+    /// With `update_references`, every snippet, preset and the last generation
+    /// referring to `from` is rewritten to `to` in the same transaction; the
+    /// items changed are returned. `None` when there is no snippet `from`.
+    pub async fn rename(
+        &self,
+        from: &str,
+        to: &str,
+        update_references: bool,
+    ) -> anyhow::Result<Option<Vec<SnippetReferrer>>> {
         let from = from.to_string();
         let to = to.to_string();
         self.db
             .with_conn_blocking("snippet rename", move |conn| {
                 let tx = conn.transaction()?;
-
                 let exists: Option<i64> = tx
                     .query_row(
                         "SELECT 1 FROM prompt_snippets WHERE name = ?1",
@@ -155,30 +282,137 @@ impl PromptSnippetStore {
                     )
                     .optional()?;
                 if exists.is_none() {
-                    return Ok(());
+                    return Ok(None);
                 }
+                let updated =
+                    rename_snippets(&tx, &HashMap::from([(from, to)]), update_references)?;
+                tx.commit()?;
+                Ok(Some(updated))
+            })
+            .await
+    }
 
-                let conflict: Option<i64> = tx
-                    .query_row(
-                        "SELECT 1 FROM prompt_snippets WHERE name = ?1",
-                        params![to],
-                        |r| r.get(0),
-                    )
-                    .optional()?;
-                if conflict.is_some() {
-                    anyhow::bail!("snippet already exists: {to}");
+    /// Every snippet under `folder` (all for the root), by name.
+    pub async fn snippets_in(&self, folder: &str) -> anyhow::Result<Vec<(String, PromptSnippet)>> {
+        let folder = folder.trim_matches('/').to_string();
+        self.db
+            .with_conn_blocking("snippet export", move |conn| {
+                let mut stmt =
+                    conn.prepare("SELECT name, preset_json FROM prompt_snippets ORDER BY name")?;
+                let rows = stmt
+                    .query_map([], |r| Ok((r.get::<_, String>(0)?, r.get::<_, String>(1)?)))?
+                    .collect::<Result<Vec<_>, _>>()?;
+                let mut out = Vec::new();
+                for (name, json) in rows {
+                    let in_folder = folder.is_empty()
+                        || name
+                            .strip_prefix(&folder)
+                            .is_some_and(|rest| rest.starts_with('/'));
+                    if !in_folder {
+                        continue;
+                    }
+                    let mut snippet: PromptSnippet =
+                        serde_json::from_str(&json).context("parse snippet")?;
+                    snippet.tags = normalize_tags(snippet.tags.iter().map(|s| s.as_str()));
+                    out.push((name, snippet));
+                }
+                Ok(out)
+            })
+            .await
+    }
+
+    /// Snippets and subfolders directly in `folder`; empty for the root.
+    pub async fn folder(&self, folder: &str) -> anyhow::Result<SnippetFolder> {
+        let path = folder.trim_matches('/').to_string();
+        self.db
+            .with_conn_blocking("snippet folder", move |conn| {
+                let mut stmt = conn.prepare("SELECT name FROM prompt_snippets ORDER BY name")?;
+                let names = stmt
+                    .query_map([], |r| r.get::<_, String>(0))?
+                    .collect::<Result<Vec<_>, _>>()?;
+                let mut folders = BTreeMap::<String, usize>::new();
+                let mut snippets = Vec::new();
+                for name in names {
+                    let rest = if path.is_empty() {
+                        name.as_str()
+                    } else {
+                        match name.strip_prefix(&path).and_then(|r| r.strip_prefix('/')) {
+                            Some(rest) => rest,
+                            None => continue,
+                        }
+                    };
+                    match rest.split_once('/') {
+                        Some((sub, _)) => *folders.entry(sub.to_string()).or_default() += 1,
+                        None => snippets.push(name.clone()),
+                    }
                 }
+                let folders = folders
+                    .into_iter()
+                    .map(|(sub, count)| SnippetSubfolder {
+                        path: if path.is_empty() {
+                            sub
+                        } else {
+                            format!("{path}/{sub}")
+                        },
+                        count,
+                    })
+                    .collect();
+                Ok(SnippetFolder {
+                    path,
+                    folders,
+                    snippets,
+                })
+            })
+            .await
+    }
 
-                tx.execute(
-                    "UPDATE prompt_snippets SET name = ?2, updated_at_ms = ?3 WHERE name = ?1",
-                    params![from, to, now_ms()],
-                )?;
+    /// Move every snippet under folder `from` to folder `to` (the root when
+    /// empty), keeping their paths below it. References between the moved
+    /// snippets keep working; with `update_references`, others are rewritten
+    /// as for [`Self::rename`]. Returns the number moved and the referrers
+    /// changed.
+    pub async fn move_folder(
+        &self,
+        from: &str,
+        to: &str,
+        update_references: bool,
+    ) -> anyhow::Result<(usize, Vec<SnippetReferrer>)> {
+        let from = from.trim_matches('/').to_string();
+        let to = to.trim_matches('/').to_string();
+        self.db
+            .with_conn_blocking("snippet folder move", move |conn| {
+                let tx = conn.transaction()?;
+                let names = {
+                    let mut stmt = tx.prepare("SELECT name FROM prompt_snippets")?;
+                    stmt.query_map([], |r| r.get::<_, String>(0))?
+                        .collect::<Result<Vec<_>, _>>()?
+                };
+                let renames: HashMap<String, String> = names
+                    .into_iter()
+                    .filter_map(|name| {
+                        let rest = name.strip_prefix(&from)?.strip_prefix('/')?.to_string();
+                        let new = if to.is_empty() {
+                            rest
+                        } else {
+                            format!("{to}/{rest}")
+                        };
+                        Some((name, new))
+                    })
+                    .collect();
+                let updated = rename_snippets(&tx, &renames, update_references)?;
                 tx.commit()?;
-                Ok(())
+                Ok((renames.len(), updated))
             })
             .await
     }
 
+    /// Who refers to which snippet, with missing snippets and cycles.
+    pub async fn graph(&self) -> anyhow::Result<SnippetGraph> {
+        self.db
+            .with_conn_blocking("snippet graph", |conn| build_graph(conn))
+            .await
+    }
+
     fn init_schema(conn: &mut Connection) -> anyhow::Result<()> {
         conn.execute_batch(
             "\
@@ -190,11 +424,217 @@ impl PromptSnippetStore {
             ",
         )
         .with_context(|| "init prompt_snippets schema")?;
+        // The index follows the table through triggers, so every write path
+        // (renames rewriting references included) keeps it current. It is
+        // rebuilt here to cover rows written before it existed, and recreated
+        // when it was made with the word tokenizer, which missed CJK text and
+        // matches inside words.
+        let fts_sql: Option<String> = conn
+            .query_row(
+                "SELECT sql FROM sqlite_master WHERE name = 'prompt_snippets_fts'",
+                [],
+                |r| r.get(0),
+            )
+            .optional()?;
+        if fts_sql.is_some_and(|sql| !sql.contains("trigram")) {
+            conn.execute_batch("DROP TABLE prompt_snippets_fts;")?;
+        }
+        conn.execute_batch(
+            "\
+            CREATE VIRTUAL TABLE IF NOT EXISTS prompt_snippets_fts USING fts5(\
+                name, body, tags, description,\
+                tokenize = 'trigram remove_diacritics 1'\
+            );\
+            CREATE TRIGGER IF NOT EXISTS prompt_snippets_fts_insert \
+            AFTER INSERT ON prompt_snippets BEGIN \
+                INSERT INTO prompt_snippets_fts (rowid, name, body, tags, description) VALUES (\
+                    new.rowid, new.name,\
+                    coalesce(json_extract(new.preset_json, '$.body'), ''),\
+                    (SELECT coalesce(group_concat(value, ' '), '') FROM json_each(new.preset_json, '$.tags')),\
+                    coalesce(json_extract(new.preset_json, '$.description'), '')\
+                );\
+            END;\
+            CREATE TRIGGER IF NOT EXISTS prompt_snippets_fts_delete \
+            AFTER DELETE ON prompt_snippets BEGIN \
+                DELETE FROM prompt_snippets_fts WHERE rowid = old.rowid;\
+            END;\
+            CREATE TRIGGER IF NOT EXISTS prompt_snippets_fts_update \
+            AFTER UPDATE ON prompt_snippets BEGIN \
+                DELETE FROM prompt_snippets_fts WHERE rowid = old.rowid;\
+                INSERT INTO prompt_snippets_fts (rowid, name, body, tags, description) VALUES (\
+                    new.rowid, new.name,\
+                    coalesce(json_extract(new.preset_json, '$.body'), ''),\
+                    (SELECT coalesce(group_concat(value, ' '), '') FROM json_each(new.preset_json, '$.tags')),\
+                    coalesce(json_extract(new.preset_json, '$.description'), '')\
+                );\
+            END;\
+            DELETE FROM prompt_snippets_fts;\
+            INSERT INTO prompt_snippets_fts (rowid, name, body, tags, description) \
+            SELECT rowid, name,\
+                coalesce(json_extract(preset_json, '$.body'), ''),\
+                (SELECT coalesce(group_concat(value, ' '), '') FROM json_each(preset_json, '$.tags')),\
+                coalesce(json_extract(preset_json, '$.description'), '') \
+            FROM prompt_snippets;\
+            ",
+        )
+        .context("init prompt_snippets_fts")?;
+        backfill_revisions(
+            conn,
+            TABLE,
+            "SELECT '' AS scope, name, updated_at_ms, preset_json AS value_json FROM prompt_snippets",
+        )?;
+        Self::migrate_invalid_names(conn)
+    }
+
+    /// Names saved before snippet arguments may have spaces or quotes, which
+    /// `<snippet:...>` can no longer spell. Rename them to valid names and
+    /// rewrite the references to them. Needs the referrer tables, so it runs
+    /// after the preset and last generation stores were opened.
+    fn migrate_invalid_names(conn: &mut Connection) -> anyhow::Result<()> {
+        let names = {
+            let mut stmt = conn.prepare("SELECT name FROM prompt_snippets")?;
+            stmt.query_map([], |r| r.get::<_, String>(0))?
+                .collect::<Result<BTreeSet<_>, _>>()?
+        };
+        let mut taken: HashSet<String> = names
+            .iter()
+            .filter(|n| check_snippet_name(n).is_ok())
+            .cloned()
+            .collect();
+        let mut renames = HashMap::new();
+        for name in names.iter().filter(|n| check_snippet_name(n).is_err()) {
+            let to = valid_snippet_name(name, &taken);
+            warn!(from = %name, to = %to, "renaming snippet with an invalid name");
+            taken.insert(to.clone());
+            renames.insert(name.clone(), to);
+        }
+        if renames.is_empty() {
+            return Ok(());
+        }
+
+        let tx = conn.transaction()?;
+        rewrite_legacy_references(&tx, &renames)?;
+        rename_snippets(&tx, &renames, true)?;
+        tx.commit()?;
         Ok(())
     }
 }
 
-fn normalize_tags<'a>(tags: impl IntoIterator<Item = &'a str>) -> Vec<String> {
+/// A valid name close to `name`, not in `taken`: whitespace becomes `_`,
+/// quotes and brackets and empty or dot segments are dropped.
+fn valid_snippet_name(name: &str, taken: &HashSet<String>) -> String {
+    let cleaned: String = name
+        .chars()
+        .filter(|c| !matches!(c, '<' | '>' | '"' | '\''))
+        .map(|c| if c.is_whitespace() { '_' } else { c })
+        .collect();
+    let segments: Vec<&str> = cleaned
+        .split('/')
+        .map(|seg| seg.trim_matches('_'))
+        .filter(|seg| !matches!(*seg, "" | "." | ".."))
+        .collect();
+    let base = if segments.is_empty() {
+        "snippet".to_string()
+    } else {
+        segments.join("/")
+    };
+    let mut candidate = base.clone();
+    let mut n = 2;
+    while taken.contains(&candidate) {
+        candidate = format!("{base}_{n}");
+        n += 1;
+    }
+    candidate
+}
+
+/// Insert or replace a snippet, recording a revision. Runs inside the
+/// caller's transaction.
+pub(crate) fn write_snippet(
+    conn: &Connection,
+    name: &str,
+    mut snippet: PromptSnippet,
+) -> anyhow::Result<()> {
+    snippet.tags = normalize_tags(snippet.tags.iter().map(|s| s.as_str()));
+    let json = serde_json::to_string(&snippet).context("serialize snippet")?;
+    conn.execute(
+        "INSERT INTO prompt_snippets (name, updated_at_ms, preset_json) VALUES (?1, ?2, ?3)\
+         ON CONFLICT(name) DO UPDATE SET updated_at_ms=excluded.updated_at_ms, preset_json=excluded.preset_json",
+        params![name, now_ms(), json],
+    )?;
+    record_revision(conn, TABLE, "", name, Some(&json))?;
+    Ok(())
+}
+
+/// Rename snippets along with their revisions. Targets may be names being
+/// renamed away, so rows pass through temporary names.
+fn rename_snippets(
+    conn: &Connection,
+    renames: &HashMap<String, String>,
+    update_references: bool,
+) -> anyhow::Result<Vec<SnippetReferrer>> {
+    let renames: HashMap<String, String> = renames
+        .iter()
+        .filter(|(from, to)| from != to)
+        .map(|(from, to)| (from.clone(), to.clone()))
+        .collect();
+    if renames.is_empty() {
+        return Ok(Vec::new());
+    }
+    let before = {
+        let mut stmt = conn.prepare("SELECT name FROM prompt_snippets")?;
+        stmt.query_map([], |r| r.get::<_, String>(0))?
+            .collect::<Result<HashSet<_>, _>>()?
+    };
+    let mut targets = HashSet::new();
+    for to in renames.values() {
+        if !targets.insert(to) || (before.contains(to) && !renames.contains_key(to)) {
+            anyhow::bail!("snippet already exists: {to}");
+        }
+    }
+
+    let now = now_ms();
+    let temp = |to: &str| format!("\u{1}{to}");
+    for (from, to) in &renames {
+        conn.execute(
+            "UPDATE prompt_snippets SET name = ?2, updated_at_ms = ?3 WHERE name = ?1",
+            params![from, temp(to), now],
+        )?;
+        rename_revisions(conn, TABLE, "", from, &temp(to))?;
+    }
+    for to in renames.values() {
+        conn.execute(
+            "UPDATE prompt_snippets SET name = ?2 WHERE name = ?1",
+            params![temp(to), to],
+        )?;
+        rename_revisions(conn, TABLE, "", &temp(to), to)?;
+    }
+
+    if update_references {
+        rewrite_references(conn, &before, &renames)
+    } else {
+        Ok(Vec::new())
+    }
+}
+
+/// FTS5 query for the words: each a quoted string found anywhere, all required.
+fn fts_query(words: &[&str]) -> String {
+    words
+        .iter()
+        .map(|w| format!("\"{}\"", w.replace('"', "\"\"")))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// LIKE pattern finding `word` anywhere, with `\` as the escape.
+fn like_pattern(word: &str) -> String {
+    let escaped = word
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_");
+    format!("%{escaped}%")
+}
+
+pub(crate) fn normalize_tags<'a>(tags: impl IntoIterator<Item = &'a str>) -> Vec<String> {
     let mut map = HashMap::<String, ()>::new();
     for t in tags {
         let cleaned = t.trim();
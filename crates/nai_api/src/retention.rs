@@ -0,0 +1,110 @@
+use std::collections::HashSet;
+
+use tracing::info;
+
+use crate::{last_generation::now_ms, routes::AppState};
+
+const DAY_MS: i64 = 24 * 60 * 60 * 1000;
+const MIB: u64 = 1024 * 1024;
+
+#[derive(Debug, Clone, Default, serde::Serialize)]
+pub struct RetentionReport {
+    pub dry_run: bool,
+    /// Outputs older than their kind's max age.
+    pub expired: Vec<String>,
+    /// Oldest outputs pruned to get under the total size limit.
+    pub over_quota: Vec<String>,
+    pub freed_bytes: u64,
+}
+
+struct Candidate {
+    path: String,
+    op_type: String,
+    favorite: bool,
+    size: u64,
+    modified_ms: i64,
+}
+
+/// Apply `AppConfig::retention`: per-kind max age first, then the total size cap
+/// (oldest first). Favourites are never pruned. Deletion is permanent, not via trash.
+pub async fn run_retention(state: &AppState, dry_run: bool) -> anyhow::Result<RetentionReport> {
+    let rules = &state.config.retention;
+    let mut report = RetentionReport {
+        dry_run,
+        ..Default::default()
+    };
+    if !rules.is_enabled() {
+        return Ok(report);
+    }
+
+    let mut items = state.outputs.list_items().await?;
+    state
+        .output_annotations
+        .annotate_items(&state.outputs, &mut items)
+        .await?;
+
+    let mut candidates: Vec<Candidate> = items
+        .into_iter()
+        .map(|item| Candidate {
+            path: item.path,
+            op_type: item.op_type,
+            favorite: item.annotation.favorite,
+            size: item.size,
+            modified_ms: item.modified_ms,
+        })
+        .collect();
+
+    let now = now_ms();
+    let mut pruned = HashSet::new();
+    for c in candidates.iter().filter(|c| !c.favorite) {
+        let Some(days) = rules.max_age_for(&c.op_type) else {
+            continue;
+        };
+        if now - c.modified_ms > days as i64 * DAY_MS {
+            report.expired.push(c.path.clone());
+            report.freed_bytes += c.size;
+            pruned.insert(c.path.clone());
+        }
+    }
+
+    if let Some(max_mb) = rules.max_total_mb {
+        let limit = max_mb * MIB;
+        let mut total: u64 = candidates
+            .iter()
+            .filter(|c| !pruned.contains(&c.path))
+            .map(|c| c.size)
+            .sum();
+        candidates.sort_by_key(|c| c.modified_ms);
+        for c in candidates
+            .iter()
+            .filter(|c| !c.favorite && !pruned.contains(&c.path))
+        {
+            if total <= limit {
+                break;
+            }
+            total = total.saturating_sub(c.size);
+            report.over_quota.push(c.path.clone());
+            report.freed_bytes += c.size;
+        }
+    }
+
+    if !dry_run {
+        let doomed: Vec<String> = report
+            .expired
+            .iter()
+            .chain(report.over_quota.iter())
+            .cloned()
+            .collect();
+        if !doomed.is_empty() {
+            state.outputs.purge_rel_files(&doomed).await?;
+            state.output_annotations.forget_paths(&doomed).await?;
+            info!(
+                expired = report.expired.len(),
+                over_quota = report.over_quota.len(),
+                freed_mb = report.freed_bytes / MIB,
+                "retention pruned outputs"
+            );
+        }
+    }
+    Ok(report)
+}
@@ -0,0 +1,255 @@
+use anyhow::Context;
+use rusqlite::{Connection, OptionalExtension, params};
+use similar::TextDiff;
+
+use crate::{db::Database, last_generation::now_ms};
+
+/// What a revision is of. Each is stored under its table's name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RevisionKind {
+    PromptSnippet,
+    PromptPreset,
+    CharacterPreset,
+    /// Generation presets, one set per model.
+    Preset,
+}
+
+impl RevisionKind {
+    pub(crate) fn table(self) -> &'static str {
+        match self {
+            RevisionKind::PromptSnippet => "prompt_snippets",
+            RevisionKind::PromptPreset => "prompt_presets",
+            RevisionKind::CharacterPreset => "character_presets",
+            RevisionKind::Preset => "presets",
+        }
+    }
+
+    fn from_table(table: &str) -> Option<Self> {
+        [
+            RevisionKind::PromptSnippet,
+            RevisionKind::PromptPreset,
+            RevisionKind::CharacterPreset,
+            RevisionKind::Preset,
+        ]
+        .into_iter()
+        .find(|k| k.table() == table)
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct RevisionSummary {
+    pub id: i64,
+    pub created_at_ms: i64,
+    /// The write was a delete.
+    pub deleted: bool,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct Revision {
+    pub id: i64,
+    pub kind: RevisionKind,
+    /// The model of generation presets; empty for the rest.
+    pub model: String,
+    pub name: String,
+    pub created_at_ms: i64,
+    /// The item as written; `None` for deletes.
+    pub value: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct RevisionDiff {
+    pub from: i64,
+    pub to: i64,
+    /// Unified diff of the two revisions as pretty-printed JSON.
+    pub diff: String,
+}
+
+/// Every write to snippets and presets, kept so a bad edit can be undone.
+/// Stores record revisions themselves in the transaction of the write.
+#[derive(Debug, Clone)]
+pub struct RevisionStore {
+    db: Database,
+}
+
+impl RevisionStore {
+    pub fn new(db: Database) -> anyhow::Result<Self> {
+        db.with_conn(|conn| init_revisions_schema(conn))?;
+        Ok(Self { db })
+    }
+
+    /// Revisions of an item, newest first.
+    pub async fn list(
+        &self,
+        kind: RevisionKind,
+        model: &str,
+        name: &str,
+    ) -> anyhow::Result<Vec<RevisionSummary>> {
+        let model = model.to_string();
+        let name = name.to_string();
+        self.db
+            .with_conn_blocking("revision list", move |conn| {
+                let mut stmt = conn.prepare(
+                    "SELECT id, created_at_ms, value_json IS NULL FROM revisions \
+                     WHERE kind = ?1 AND scope = ?2 AND name = ?3 ORDER BY id DESC",
+                )?;
+                let rows = stmt
+                    .query_map(params![kind.table(), model, name], |r| {
+                        Ok(RevisionSummary {
+                            id: r.get(0)?,
+                            created_at_ms: r.get(1)?,
+                            deleted: r.get(2)?,
+                        })
+                    })?
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(rows)
+            })
+            .await
+    }
+
+    pub async fn get(&self, id: i64) -> anyhow::Result<Option<Revision>> {
+        self.db
+            .with_conn_blocking("revision get", move |conn| get_revision(conn, id))
+            .await
+    }
+
+    /// Diff two revisions of the same item.
+    pub async fn diff(&self, from: i64, to: i64) -> anyhow::Result<RevisionDiff> {
+        let (a, b) = self
+            .db
+            .with_conn_blocking("revision diff", move |conn| {
+                Ok((get_revision(conn, from)?, get_revision(conn, to)?))
+            })
+            .await?;
+        let (Some(a), Some(b)) = (a, b) else {
+            anyhow::bail!("revision not found");
+        };
+        if (a.kind, &a.model, &a.name) != (b.kind, &b.model, &b.name) {
+            anyhow::bail!("revisions {from} and {to} are of different items");
+        }
+        let pretty = |v: &Option<serde_json::Value>| -> anyhow::Result<String> {
+            Ok(match v {
+                Some(v) => serde_json::to_string_pretty(v)? + "\n",
+                None => String::new(),
+            })
+        };
+        let (old, new) = (pretty(&a.value)?, pretty(&b.value)?);
+        let diff = TextDiff::from_lines(&old, &new)
+            .unified_diff()
+            .header(&format!("#{from}"), &format!("#{to}"))
+            .to_string();
+        Ok(RevisionDiff { from, to, diff })
+    }
+}
+
+pub(crate) fn init_revisions_schema(conn: &Connection) -> anyhow::Result<()> {
+    conn.execute_batch(
+        "\
+        CREATE TABLE IF NOT EXISTS revisions (\
+            id INTEGER PRIMARY KEY AUTOINCREMENT,\
+            kind TEXT NOT NULL,\
+            scope TEXT NOT NULL,\
+            name TEXT NOT NULL,\
+            created_at_ms INTEGER NOT NULL,\
+            value_json TEXT\
+        );\
+        CREATE INDEX IF NOT EXISTS revisions_item ON revisions(kind, scope, name, id);\
+        ",
+    )
+    .context("init revisions schema")?;
+    Ok(())
+}
+
+/// Give rows written before revisions were kept a first revision, so their
+/// original content survives the next edit. `rows_sql` selects
+/// `scope`, `name`, `updated_at_ms` and `value_json` columns.
+pub(crate) fn backfill_revisions(
+    conn: &Connection,
+    kind: &str,
+    rows_sql: &str,
+) -> anyhow::Result<()> {
+    init_revisions_schema(conn)?;
+    let sql = format!(
+        "INSERT INTO revisions (kind, scope, name, created_at_ms, value_json) \
+         SELECT ?1, t.scope, t.name, t.updated_at_ms, t.value_json FROM ({rows_sql}) AS t \
+         WHERE NOT EXISTS (SELECT 1 FROM revisions r \
+             WHERE r.kind = ?1 AND r.scope = t.scope AND r.name = t.name)"
+    );
+    conn.execute(&sql, params![kind])
+        .with_context(|| format!("backfill {kind} revisions"))?;
+    Ok(())
+}
+
+/// Record a write; `json` is `None` for a delete.
+pub(crate) fn record_revision(
+    conn: &Connection,
+    kind: &str,
+    scope: &str,
+    name: &str,
+    json: Option<&str>,
+) -> anyhow::Result<()> {
+    conn.execute(
+        "INSERT INTO revisions (kind, scope, name, created_at_ms, value_json) \
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![kind, scope, name, now_ms(), json],
+    )?;
+    Ok(())
+}
+
+/// Keep an item's history when it is renamed. Whatever history `to` has is
+/// of an item deleted earlier, and is dropped so the two don't interleave.
+pub(crate) fn rename_revisions(
+    conn: &Connection,
+    kind: &str,
+    scope: &str,
+    from: &str,
+    to: &str,
+) -> anyhow::Result<()> {
+    if from == to {
+        return Ok(());
+    }
+    conn.execute(
+        "DELETE FROM revisions WHERE kind = ?1 AND scope = ?2 AND name = ?3",
+        params![kind, scope, to],
+    )?;
+    conn.execute(
+        "UPDATE revisions SET name = ?4 WHERE kind = ?1 AND scope = ?2 AND name = ?3",
+        params![kind, scope, from, to],
+    )?;
+    Ok(())
+}
+
+fn get_revision(conn: &Connection, id: i64) -> anyhow::Result<Option<Revision>> {
+    let row = conn
+        .query_row(
+            "SELECT kind, scope, name, created_at_ms, value_json FROM revisions WHERE id = ?1",
+            params![id],
+            |r| {
+                Ok((
+                    r.get::<_, String>(0)?,
+                    r.get::<_, String>(1)?,
+                    r.get::<_, String>(2)?,
+                    r.get::<_, i64>(3)?,
+                    r.get::<_, Option<String>>(4)?,
+                ))
+            },
+        )
+        .optional()?;
+    let Some((kind, model, name, created_at_ms, json)) = row else {
+        return Ok(None);
+    };
+    let kind = RevisionKind::from_table(&kind)
+        .with_context(|| format!("unknown revision kind: {kind}"))?;
+    let value = json
+        .map(|j| serde_json::from_str(&j))
+        .transpose()
+        .context("parse revision")?;
+    Ok(Some(Revision {
+        id,
+        kind,
+        model,
+        name,
+        created_at_ms,
+        value,
+    }))
+}
@@ -8,6 +8,7 @@ pub type ApiResult<T> = Result<Json<T>, ApiError>;
 pub enum ApiError {
     BadRequest(anyhow::Error),
     NotFound(String),
+    Unavailable(anyhow::Error),
     Internal(anyhow::Error),
 }
 
@@ -28,6 +29,10 @@ impl ApiError {
     pub fn not_found(msg: impl Into<String>) -> Self {
         Self::NotFound(msg.into())
     }
+
+    pub fn unavailable<E: Into<anyhow::Error>>(err: E) -> Self {
+        Self::Unavailable(err.into())
+    }
 }
 
 impl IntoResponse for ApiError {
@@ -35,6 +40,7 @@ impl IntoResponse for ApiError {
         let (status, msg) = match self {
             ApiError::BadRequest(err) => (StatusCode::BAD_REQUEST, err.to_string()),
             ApiError::NotFound(msg) => (StatusCode::NOT_FOUND, msg),
+            ApiError::Unavailable(err) => (StatusCode::SERVICE_UNAVAILABLE, err.to_string()),
             ApiError::Internal(err) => (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()),
         };
 
@@ -8,6 +8,8 @@ use nai_core::dto::{
 };
 use nai_core::services;
 
+use crate::prompt_pipeline::prepare_prompts;
+
 use super::{ApiError, ApiResult, AppState};
 
 async fn apply_snippets_to_base(
@@ -68,6 +70,11 @@ async fn t2i(
     let mut req = req;
     let raw_req = req.clone();
     apply_snippets_to_base(&state, &mut req).await?;
+    req.resolve_seeds(None).map_err(ApiError::bad_request)?;
+    req.seed = req.seed_at(0);
+    prepare_prompts(&state, &mut req, 0)
+        .await
+        .map_err(ApiError::bad_request)?;
     info!(
         model = %req.model,
         width = req.width,
@@ -81,7 +88,8 @@ async fn t2i(
         warn!(error = %e, "failed to cache last_generation");
     }
 
-    let resp = services::generate_t2i(&state.config, &state.outputs, &state.nai, req)
+    state.disk_guard.check().map_err(ApiError::unavailable)?;
+    let resp = services::generate_t2i(&state.config, &state.outputs, &state.nai, req, None)
         .await
         .map_err(ApiError::bad_request)?;
     Ok(Json(resp))
@@ -94,6 +102,13 @@ async fn i2i(
     let mut req = req;
     let raw_base = req.base.clone();
     apply_snippets_to_base(&state, &mut req.base).await?;
+    req.base
+        .resolve_seeds(Some(&mut req.extra_noise_seed))
+        .map_err(ApiError::bad_request)?;
+    req.base.seed = req.base.seed_at(0);
+    prepare_prompts(&state, &mut req.base, 0)
+        .await
+        .map_err(ApiError::bad_request)?;
     info!(
         model = %req.base.model,
         width = req.base.width,
@@ -107,7 +122,8 @@ async fn i2i(
         warn!(error = %e, "failed to cache last_generation");
     }
 
-    let resp = services::generate_i2i(&state.config, &state.outputs, &state.nai, req)
+    state.disk_guard.check().map_err(ApiError::unavailable)?;
+    let resp = services::generate_i2i(&state.config, &state.outputs, &state.nai, req, None)
         .await
         .map_err(ApiError::bad_request)?;
     Ok(Json(resp))
@@ -120,6 +136,13 @@ async fn inpaint(
     let mut req = req;
     let raw_base = req.base.clone();
     apply_snippets_to_base(&state, &mut req.base).await?;
+    req.base
+        .resolve_seeds(Some(&mut req.extra_noise_seed))
+        .map_err(ApiError::bad_request)?;
+    req.base.seed = req.base.seed_at(0);
+    prepare_prompts(&state, &mut req.base, 0)
+        .await
+        .map_err(ApiError::bad_request)?;
     info!(
         model = %req.base.model,
         width = req.base.width,
@@ -133,7 +156,8 @@ async fn inpaint(
         warn!(error = %e, "failed to cache last_generation");
     }
 
-    let resp = services::generate_inpaint(&state.config, &state.outputs, &state.nai, req)
+    state.disk_guard.check().map_err(ApiError::unavailable)?;
+    let resp = services::generate_inpaint(&state.config, &state.outputs, &state.nai, req, None)
         .await
         .map_err(ApiError::bad_request)?;
     Ok(Json(resp))
@@ -146,6 +170,13 @@ async fn character(
     let mut req = req;
     let raw_base = req.base.clone();
     apply_snippets_to_base(&state, &mut req.base).await?;
+    req.base
+        .resolve_seeds(None)
+        .map_err(ApiError::bad_request)?;
+    req.base.seed = req.base.seed_at(0);
+    prepare_prompts(&state, &mut req.base, 0)
+        .await
+        .map_err(ApiError::bad_request)?;
     info!(
         model = %req.base.model,
         width = req.base.width,
@@ -160,7 +191,8 @@ async fn character(
         warn!(error = %e, "failed to cache last_generation");
     }
 
-    let resp = services::generate_character(&state.config, &state.outputs, &state.nai, req)
+    state.disk_guard.check().map_err(ApiError::unavailable)?;
+    let resp = services::generate_character(&state.config, &state.outputs, &state.nai, req, None)
         .await
         .map_err(ApiError::bad_request)?;
     Ok(Json(resp))
@@ -16,15 +16,53 @@ use uuid::Uuid;
 use nai_core::{
     config::AppConfig,
     dto::{
-        BaseGenerateRequest, CharacterRequest, Img2ImgRequest, InpaintRequest, JobSubmitResponse,
+        BaseGenerateRequest, CharacterRequest, GenerateResponse, Img2ImgRequest, InpaintRequest,
+        JobSubmitResponse,
     },
+    grid::{GridItem, GridOptions, GridResponse},
     job::{JobStatus, JobSummary},
+    outputs::{OutputError, OutputStore},
+    prompt::lint::Severity,
     services,
+    sweep::{SweepPlan, SweepRequest},
 };
 use nai_nai::NaiError;
 
+use crate::prompt_pipeline::prepare_prompts;
+
 use super::{ApiError, ApiResult, AppState};
 
+/// With `lint_on_submit`, refuse prompts the linter finds errors in.
+async fn lint_before_submit(state: &AppState, base: &BaseGenerateRequest) -> Result<(), ApiError> {
+    if !state.config.lint_on_submit {
+        return Ok(());
+    }
+    let issues = crate::lint_prompts(
+        &state.config,
+        &state.prompt_snippets,
+        &base.positive,
+        &base.negative,
+        base.character_prompts.as_deref().unwrap_or_default(),
+    )
+    .await
+    .map_err(ApiError::internal)?;
+    let errors: Vec<String> = issues
+        .iter()
+        .filter(|i| i.severity == Severity::Error)
+        .map(|i| format!("{} {}..{}: {}", i.field, i.start, i.end, i.message))
+        .collect();
+    if !errors.is_empty() {
+        return Err(ApiError::bad_request(anyhow::anyhow!(
+            "prompt lint failed: {}",
+            errors.join("; ")
+        )));
+    }
+    if !issues.is_empty() {
+        debug!(warnings = issues.len(), "prompt lint warnings");
+    }
+    Ok(())
+}
+
 async fn apply_snippets_to_base(
     state: &AppState,
     base: &mut BaseGenerateRequest,
@@ -79,6 +117,7 @@ enum JobKind {
     I2i,
     Inpaint,
     Character,
+    Sweep,
 }
 
 impl JobKind {
@@ -88,6 +127,7 @@ impl JobKind {
             JobKind::I2i => "i2i",
             JobKind::Inpaint => "inpaint",
             JobKind::Character => "character",
+            JobKind::Sweep => "sweep",
         }
     }
 }
@@ -97,10 +137,12 @@ pub fn routes() -> Router<Arc<AppState>> {
         .route("/api/jobs", get(jobs_list))
         .route("/api/jobs/{id}", get(job_status))
         .route("/api/jobs/{id}/cancel", post(job_cancel))
+        .route("/api/jobs/{id}/grid", post(job_grid))
         .route("/api/jobs/t2i", post(job_t2i))
         .route("/api/jobs/i2i", post(job_i2i))
         .route("/api/jobs/inpaint", post(job_inpaint))
         .route("/api/jobs/character", post(job_character))
+        .route("/api/jobs/sweep", post(job_sweep))
 }
 
 async fn jobs_list(State(state): State<Arc<AppState>>) -> ApiResult<JobsListResponse> {
@@ -132,13 +174,61 @@ async fn job_cancel(
     }
 }
 
+/// Compose a finished job's outputs into a contact sheet.
+async fn job_grid(
+    State(state): State<Arc<AppState>>,
+    axum::extract::Path(id): axum::extract::Path<Uuid>,
+    Json(opts): Json<GridOptions>,
+) -> ApiResult<GridResponse> {
+    info!(job_id = %id, columns = ?opts.columns, "job_grid request");
+    let outputs = match state.jobs.get_status(id).await {
+        Some(JobStatus::Succeeded { outputs, .. }) => outputs,
+        Some(_) => {
+            return Err(ApiError::bad_request(anyhow::anyhow!(
+                "job has not succeeded"
+            )));
+        }
+        None => return Err(ApiError::not_found("job not found")),
+    };
+    let grid = compose_job_grid(&state.outputs, id, &outputs, &opts)
+        .await
+        .map_err(|e| match e {
+            OutputError::NotFound => ApiError::not_found("job outputs not found"),
+            OutputError::TooManyGridItems(_) => ApiError::bad_request(e),
+            other => ApiError::internal(other),
+        })?;
+    Ok(Json(grid))
+}
+
+async fn compose_job_grid(
+    outputs: &OutputStore,
+    job_id: Uuid,
+    generated: &[GenerateResponse],
+    opts: &GridOptions,
+) -> Result<GridResponse, OutputError> {
+    let items: Vec<GridItem> = generated
+        .iter()
+        .map(|o| GridItem {
+            path: o.output_path.clone(),
+            caption: o.seed.to_string(),
+        })
+        .collect();
+    outputs.compose_grid(&items, opts, Some(job_id)).await
+}
+
 async fn job_t2i(
     State(state): State<Arc<AppState>>,
     Json(req): Json<BaseGenerateRequest>,
 ) -> ApiResult<JobSubmitResponse> {
     let mut req = req;
     let raw_req = req.clone();
+    lint_before_submit(&state, &req).await?;
     apply_snippets_to_base(&state, &mut req).await?;
+    req.resolve_seeds(None).map_err(ApiError::bad_request)?;
+    let wildcard_seed = crate::resolve_wildcard_seed(&mut req);
+    crate::count_combinations(&state.wildcards, &req)
+        .await
+        .map_err(ApiError::bad_request)?;
     if let Err(e) = state.last_generation.set_from_base(&raw_req).await {
         warn!(error = %e, "failed to cache last_generation");
     }
@@ -146,6 +236,7 @@ async fn job_t2i(
         state,
         JobKind::T2i,
         serde_json::to_value(req).map_err(ApiError::bad_request)?,
+        wildcard_seed,
     )
     .await
 }
@@ -156,7 +247,15 @@ async fn job_i2i(
 ) -> ApiResult<JobSubmitResponse> {
     let mut req = req;
     let raw_base = req.base.clone();
+    lint_before_submit(&state, &req.base).await?;
     apply_snippets_to_base(&state, &mut req.base).await?;
+    req.base
+        .resolve_seeds(Some(&mut req.extra_noise_seed))
+        .map_err(ApiError::bad_request)?;
+    let wildcard_seed = crate::resolve_wildcard_seed(&mut req.base);
+    crate::count_combinations(&state.wildcards, &req.base)
+        .await
+        .map_err(ApiError::bad_request)?;
     if let Err(e) = state.last_generation.set_from_base(&raw_base).await {
         warn!(error = %e, "failed to cache last_generation");
     }
@@ -164,6 +263,7 @@ async fn job_i2i(
         state,
         JobKind::I2i,
         serde_json::to_value(req).map_err(ApiError::bad_request)?,
+        wildcard_seed,
     )
     .await
 }
@@ -174,7 +274,15 @@ async fn job_inpaint(
 ) -> ApiResult<JobSubmitResponse> {
     let mut req = req;
     let raw_base = req.base.clone();
+    lint_before_submit(&state, &req.base).await?;
     apply_snippets_to_base(&state, &mut req.base).await?;
+    req.base
+        .resolve_seeds(Some(&mut req.extra_noise_seed))
+        .map_err(ApiError::bad_request)?;
+    let wildcard_seed = crate::resolve_wildcard_seed(&mut req.base);
+    crate::count_combinations(&state.wildcards, &req.base)
+        .await
+        .map_err(ApiError::bad_request)?;
     if let Err(e) = state.last_generation.set_from_base(&raw_base).await {
         warn!(error = %e, "failed to cache last_generation");
     }
@@ -182,6 +290,7 @@ async fn job_inpaint(
         state,
         JobKind::Inpaint,
         serde_json::to_value(req).map_err(ApiError::bad_request)?,
+        wildcard_seed,
     )
     .await
 }
@@ -192,7 +301,15 @@ async fn job_character(
 ) -> ApiResult<JobSubmitResponse> {
     let mut req = req;
     let raw_base = req.base.clone();
+    lint_before_submit(&state, &req.base).await?;
     apply_snippets_to_base(&state, &mut req.base).await?;
+    req.base
+        .resolve_seeds(None)
+        .map_err(ApiError::bad_request)?;
+    let wildcard_seed = crate::resolve_wildcard_seed(&mut req.base);
+    crate::count_combinations(&state.wildcards, &req.base)
+        .await
+        .map_err(ApiError::bad_request)?;
     if let Err(e) = state.last_generation.set_from_base(&raw_base).await {
         warn!(error = %e, "failed to cache last_generation");
     }
@@ -200,6 +317,38 @@ async fn job_character(
         state,
         JobKind::Character,
         serde_json::to_value(req).map_err(ApiError::bad_request)?,
+        wildcard_seed,
+    )
+    .await
+}
+
+async fn job_sweep(
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<SweepRequest>,
+) -> ApiResult<JobSubmitResponse> {
+    lint_before_submit(&state, &req.base).await?;
+    if let Err(e) = state.last_generation.set_from_base(&req.base).await {
+        warn!(error = %e, "failed to cache last_generation");
+    }
+    // Axes apply to the raw prompts, so search/replace can target snippet references.
+    let mut plan = req.expand().map_err(ApiError::bad_request)?;
+    for cell in plan.cells.iter_mut() {
+        apply_snippets_to_base(&state, &mut cell.req).await?;
+    }
+    // Every cell draws the same wildcard picks so only the axes differ.
+    let wildcard_seed = plan
+        .cells
+        .iter()
+        .any(|c| crate::has_wildcards(&c.req))
+        .then(|| req.base.wildcard_seed.unwrap_or_else(rand::random));
+    for cell in plan.cells.iter_mut() {
+        cell.req.wildcard_seed = wildcard_seed;
+    }
+    submit_job(
+        state,
+        JobKind::Sweep,
+        serde_json::to_value(plan).map_err(ApiError::bad_request)?,
+        wildcard_seed,
     )
     .await
 }
@@ -208,14 +357,27 @@ async fn submit_job(
     state: Arc<AppState>,
     kind: JobKind,
     payload: Value,
+    wildcard_seed: Option<u64>,
 ) -> ApiResult<JobSubmitResponse> {
+    let grid_opts: Option<GridOptions> = match payload.get("grid") {
+        Some(v) => serde_json::from_value(v.clone())
+            .map_err(|e| ApiError::bad_request(anyhow::anyhow!("invalid grid options: {e}")))?,
+        None => None,
+    };
+    state.disk_guard.check().map_err(ApiError::unavailable)?;
     let (id, cancel) = state.jobs.create(kind.as_str()).await;
 
     let qty = payload
         .get("quantity")
         .and_then(|v| v.as_u64())
+        .or_else(|| {
+            payload
+                .get("cells")
+                .and_then(|v| v.as_array())
+                .map(|cells| cells.len() as u64)
+        })
         .unwrap_or(1);
-    info!(job_id = %id, kind = kind.as_str(), quantity = qty, "job submitted");
+    info!(job_id = %id, kind = kind.as_str(), quantity = qty, grid = grid_opts.is_some(), "job submitted");
 
     let state2 = state.clone();
     tokio::spawn(async move {
@@ -242,99 +404,162 @@ async fn submit_job(
         info!(job_id = %id, kind = kind.as_str(), queued_ms = queued_at.elapsed().as_millis() as u64, "job dequeued");
         state2.jobs.set_status(id, JobStatus::Running).await;
 
-        let result: anyhow::Result<Vec<nai_core::dto::GenerateResponse>> = (async {
+        // Outputs, plus the comparison grid for sweeps.
+        let result: anyhow::Result<(Vec<GenerateResponse>, Option<GridResponse>)> = (async {
             match kind {
                 JobKind::T2i => {
                     let req: BaseGenerateRequest = serde_json::from_value(payload.clone())?;
-                    let qty = req.quantity.unwrap_or(1).max(1) as usize;
+                    let plan = ImagePlan::new(&state2, &req).await?;
+                    let qty = plan.total();
                     let mut outs = Vec::with_capacity(qty);
                     for idx in 0..qty {
                         if cancel.is_cancelled() {
                             info!(job_id = %id, kind = kind.as_str(), done = idx, total = qty, "job cancelled during run");
                             break;
                         }
+                        state2.disk_guard.check()?;
                         info!(job_id = %id, kind = kind.as_str(), index = idx + 1, total = qty, "generate t2i");
                         let out = with_429_retry(&cancel, id, || {
-                            let req2 = req.clone();
+                            let mut req2 = req.clone();
+                            req2.seed = req.seed_at(plan.seed_index(idx));
                             let st = state2.clone();
                             async move {
-                                services::generate_t2i(&st.config, &st.outputs, &st.nai, req2).await
+                                prepare_prompts(&st, &mut req2, plan.prompt_index(idx)).await?;
+                                services::generate_t2i(&st.config, &st.outputs, &st.nai, req2, Some(id)).await
                             }
                         })
                         .await?;
                         outs.push(out);
                         cooldown_sleep(&state2.config, &cancel, id).await;
                     }
-                    Ok(outs)
+                    Ok((outs, None))
                 }
                 JobKind::I2i => {
                     let req: Img2ImgRequest = serde_json::from_value(payload.clone())?;
-                    let qty = req.base.quantity.unwrap_or(1).max(1) as usize;
+                    let plan = ImagePlan::new(&state2, &req.base).await?;
+                    let qty = plan.total();
                     let mut outs = Vec::with_capacity(qty);
                     for idx in 0..qty {
                         if cancel.is_cancelled() {
                             info!(job_id = %id, kind = kind.as_str(), done = idx, total = qty, "job cancelled during run");
                             break;
                         }
+                        state2.disk_guard.check()?;
                         info!(job_id = %id, kind = kind.as_str(), index = idx + 1, total = qty, "generate i2i");
                         let out = with_429_retry(&cancel, id, || {
-                            let req2 = req.clone();
+                            let mut req2 = req.clone();
+                            req2.base.seed = req.base.seed_at(plan.seed_index(idx));
+                            req2.extra_noise_seed = req.base.extra_noise_seed_at(req.extra_noise_seed, plan.seed_index(idx));
                             let st = state2.clone();
                             async move {
-                                services::generate_i2i(&st.config, &st.outputs, &st.nai, req2).await
+                                prepare_prompts(&st, &mut req2.base, plan.prompt_index(idx)).await?;
+                                services::generate_i2i(&st.config, &st.outputs, &st.nai, req2, Some(id)).await
                             }
                         })
                         .await?;
                         outs.push(out);
                         cooldown_sleep(&state2.config, &cancel, id).await;
                     }
-                    Ok(outs)
+                    Ok((outs, None))
                 }
                 JobKind::Inpaint => {
                     let req: InpaintRequest = serde_json::from_value(payload.clone())?;
-                    let qty = req.base.quantity.unwrap_or(1).max(1) as usize;
+                    let plan = ImagePlan::new(&state2, &req.base).await?;
+                    let qty = plan.total();
                     let mut outs = Vec::with_capacity(qty);
                     for idx in 0..qty {
                         if cancel.is_cancelled() {
                             info!(job_id = %id, kind = kind.as_str(), done = idx, total = qty, "job cancelled during run");
                             break;
                         }
+                        state2.disk_guard.check()?;
                         info!(job_id = %id, kind = kind.as_str(), index = idx + 1, total = qty, "generate inpaint");
                         let out = with_429_retry(&cancel, id, || {
-                            let req2 = req.clone();
+                            let mut req2 = req.clone();
+                            req2.base.seed = req.base.seed_at(plan.seed_index(idx));
+                            req2.extra_noise_seed = req.base.extra_noise_seed_at(req.extra_noise_seed, plan.seed_index(idx));
                             let st = state2.clone();
                             async move {
-                                services::generate_inpaint(&st.config, &st.outputs, &st.nai, req2).await
+                                prepare_prompts(&st, &mut req2.base, plan.prompt_index(idx)).await?;
+                                services::generate_inpaint(&st.config, &st.outputs, &st.nai, req2, Some(id)).await
                             }
                         })
                         .await?;
                         outs.push(out);
                         cooldown_sleep(&state2.config, &cancel, id).await;
                     }
-                    Ok(outs)
+                    Ok((outs, None))
                 }
                 JobKind::Character => {
                     let req: CharacterRequest = serde_json::from_value(payload.clone())?;
-                    let qty = req.base.quantity.unwrap_or(1).max(1) as usize;
+                    let plan = ImagePlan::new(&state2, &req.base).await?;
+                    let qty = plan.total();
                     let mut outs = Vec::with_capacity(qty);
                     for idx in 0..qty {
                         if cancel.is_cancelled() {
                             info!(job_id = %id, kind = kind.as_str(), done = idx, total = qty, "job cancelled during run");
                             break;
                         }
+                        state2.disk_guard.check()?;
                         info!(job_id = %id, kind = kind.as_str(), index = idx + 1, total = qty, "generate character");
                         let out = with_429_retry(&cancel, id, || {
-                            let req2 = req.clone();
+                            let mut req2 = req.clone();
+                            req2.base.seed = req.base.seed_at(plan.seed_index(idx));
+                            let st = state2.clone();
+                            async move {
+                                prepare_prompts(&st, &mut req2.base, plan.prompt_index(idx)).await?;
+                                services::generate_character(&st.config, &st.outputs, &st.nai, req2, Some(id)).await
+                            }
+                        })
+                        .await?;
+                        outs.push(out);
+                        cooldown_sleep(&state2.config, &cancel, id).await;
+                    }
+                    Ok((outs, None))
+                }
+                JobKind::Sweep => {
+                    let plan: SweepPlan = serde_json::from_value(payload.clone())?;
+                    let total = plan.cells.len();
+                    let mut outs = Vec::with_capacity(total);
+                    let mut placed = vec![None; total];
+                    for (idx, cell) in plan.cells.iter().enumerate() {
+                        if cancel.is_cancelled() {
+                            info!(job_id = %id, kind = kind.as_str(), done = idx, total, "job cancelled during run");
+                            break;
+                        }
+                        state2.disk_guard.check()?;
+                        info!(job_id = %id, kind = kind.as_str(), index = idx + 1, total, x = cell.x, y = cell.y, z = cell.z, "generate sweep cell");
+                        let out = with_429_retry(&cancel, id, || {
+                            let mut req2 = cell.req.clone();
                             let st = state2.clone();
                             async move {
-                                services::generate_character(&st.config, &st.outputs, &st.nai, req2).await
+                                prepare_prompts(&st, &mut req2, 0).await?;
+                                services::generate_t2i(&st.config, &st.outputs, &st.nai, req2, Some(id)).await
                             }
                         })
                         .await?;
+                        placed[idx] = Some(GridItem {
+                            path: out.output_path.clone(),
+                            caption: out.seed.to_string(),
+                        });
                         outs.push(out);
                         cooldown_sleep(&state2.config, &cancel, id).await;
                     }
-                    Ok(outs)
+                    if cancel.is_cancelled() {
+                        return Ok((outs, None));
+                    }
+                    let grid = match state2
+                        .outputs
+                        .compose_sweep_grid(&placed, &plan.axes, plan.cell_width, Some(id))
+                        .await
+                    {
+                        Ok(grid) => Some(grid),
+                        Err(e) => {
+                            warn!(job_id = %id, error = %e, "sweep grid failed");
+                            None
+                        }
+                    };
+                    Ok((outs, grid))
                 }
             }
         })
@@ -349,11 +574,25 @@ async fn submit_job(
         }
 
         match result {
-            Ok(outputs) => {
+            Ok((outputs, sweep_grid)) => {
                 info!(job_id = %id, kind = kind.as_str(), outputs = outputs.len(), "job succeeded");
+                // A failed grid doesn't fail the job; it can be composed again later.
+                let grid = match &grid_opts {
+                    _ if sweep_grid.is_some() => sweep_grid,
+                    Some(opts) if !outputs.is_empty() => {
+                        match compose_job_grid(&state2.outputs, id, &outputs, opts).await {
+                            Ok(grid) => Some(grid),
+                            Err(e) => {
+                                warn!(job_id = %id, error = %e, "job grid failed");
+                                None
+                            }
+                        }
+                    }
+                    _ => None,
+                };
                 state2
                     .jobs
-                    .set_status(id, JobStatus::Succeeded { outputs })
+                    .set_status(id, JobStatus::Succeeded { outputs, grid })
                     .await;
             }
             Err(e) => {
@@ -371,7 +610,47 @@ async fn submit_job(
         }
     });
 
-    Ok(Json(JobSubmitResponse { job_id: id }))
+    Ok(Json(JobSubmitResponse {
+        job_id: id,
+        wildcard_seed,
+    }))
+}
+
+/// The images of a submission: `iterations` seeds for each prompt combination,
+/// one combination after another.
+#[derive(Clone, Copy)]
+struct ImagePlan {
+    per_prompt: usize,
+    combinations: usize,
+    combinatorial: bool,
+}
+
+impl ImagePlan {
+    async fn new(state: &AppState, base: &BaseGenerateRequest) -> anyhow::Result<Self> {
+        Ok(Self {
+            per_prompt: base.iterations(),
+            combinations: crate::count_combinations(&state.wildcards, base).await?,
+            combinatorial: base.combinatorial == Some(true),
+        })
+    }
+
+    fn total(&self) -> usize {
+        self.per_prompt * self.combinations
+    }
+
+    /// Index for `seed_at`; restarts with each combination.
+    fn seed_index(&self, idx: usize) -> usize {
+        idx % self.per_prompt
+    }
+
+    /// Index for `expand_wildcards`: the combination, or the image for random picks.
+    fn prompt_index(&self, idx: usize) -> usize {
+        if self.combinatorial {
+            idx / self.per_prompt
+        } else {
+            idx
+        }
+    }
 }
 
 async fn cooldown_sleep(cfg: &AppConfig, cancel: &CancellationToken, job_id: Uuid) {
@@ -399,12 +678,8 @@ async fn cooldown_sleep(cfg: &AppConfig, cancel: &CancellationToken, job_id: Uui
 
 fn is_http_429(err: &anyhow::Error) -> bool {
     for cause in err.chain() {
-        if let Some(ne) = cause.downcast_ref::<NaiError>() {
-            if let NaiError::BadStatus { status, .. } = ne {
-                if *status == 429 {
-                    return true;
-                }
-            }
+        if let Some(NaiError::BadStatus { status: 429, .. }) = cause.downcast_ref::<NaiError>() {
+            return true;
         }
     }
     false
@@ -4,12 +4,12 @@ use axum::Router;
 use tokio::sync::Semaphore;
 use tower_http::services::{ServeDir, ServeFile};
 
-use nai_core::{config::AppConfig, job::JobStore, outputs::OutputStore};
+use nai_core::{config::AppConfig, job::JobStore, outputs::OutputStore, tokenizer::Tokenizers};
 use nai_nai::NaiClient;
 
 use crate::{
-    CharacterPresetStore, Database, LastGenerationStore, PresetStore, PromptPresetStore,
-    PromptSnippetStore,
+    CharacterPresetStore, Database, DiskGuard, LastGenerationStore, OutputAnnotationStore,
+    PresetStore, PromptPresetStore, PromptSnippetStore, RevisionStore, TagStore, WildcardStore,
 };
 
 mod character_presets;
@@ -19,10 +19,19 @@ mod generate;
 mod jobs;
 mod last_generation;
 mod meta;
+mod output_archive;
+mod output_files;
 mod outputs;
 mod presets;
+mod prompt;
 mod prompt_presets;
 mod prompt_snippets;
+mod revisions;
+mod settings_bundle;
+mod snippet_transfer;
+mod tags;
+mod thumbs;
+mod wildcards;
 
 pub use error::{ApiError, ApiResult};
 
@@ -32,26 +41,38 @@ pub struct AppState {
     pub db: Database,
     pub nai: NaiClient,
     pub outputs: OutputStore,
+    pub output_annotations: OutputAnnotationStore,
     pub jobs: JobStore,
     pub job_sem: Arc<Semaphore>,
+    pub disk_guard: DiskGuard,
     pub last_generation: LastGenerationStore,
     pub presets: PresetStore,
     pub prompt_presets: PromptPresetStore,
     pub character_presets: CharacterPresetStore,
     pub prompt_snippets: PromptSnippetStore,
+    pub revisions: RevisionStore,
+    pub wildcards: WildcardStore,
+    pub tags: TagStore,
+    pub tokenizers: Arc<Tokenizers>,
 }
 
 pub fn router(state: Arc<AppState>) -> Router {
-    let outputs_root = state.outputs.root().to_path_buf();
-
     let mut router = Router::<Arc<AppState>>::new()
-        .nest_service("/outputs", ServeDir::new(outputs_root))
+        .merge(output_files::routes())
         .merge(meta::routes())
         .merge(outputs::routes())
+        .merge(output_archive::routes())
+        .merge(thumbs::routes())
         .merge(last_generation::routes())
         .merge(presets::routes())
+        .merge(prompt::routes())
         .merge(prompt_presets::routes())
         .merge(prompt_snippets::routes())
+        .merge(snippet_transfer::routes())
+        .merge(revisions::routes())
+        .merge(settings_bundle::routes())
+        .merge(wildcards::routes())
+        .merge(tags::routes())
         .merge(character_presets::routes())
         .merge(generate::routes())
         .merge(jobs::routes())
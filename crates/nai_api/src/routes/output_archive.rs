@@ -0,0 +1,252 @@
+use std::{
+    collections::HashMap,
+    future::Future,
+    io::{self, Write},
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use anyhow::anyhow;
+use axum::{
+    Json, Router,
+    body::Body,
+    extract::State,
+    http::header,
+    response::{IntoResponse, Response},
+    routing::post,
+};
+use chrono::Local;
+use serde::Deserialize;
+use serde_json::{Value, json};
+use tokio::{
+    io::{AsyncRead, DuplexStream, ReadBuf},
+    sync::{mpsc, oneshot},
+};
+use tokio_util::io::{ReaderStream, SyncIoBridge};
+use tracing::{debug, info, warn};
+use zip::{CompressionMethod, ZipWriter, write::SimpleFileOptions};
+
+use nai_core::{
+    metadata::png_generation_metadata,
+    outputs::{OutputError, is_internal, is_safe_rel_path, normalize_rel_path},
+};
+
+use crate::OutputFilter;
+
+use super::{ApiError, AppState};
+
+/// Pipe buffer between the blocking zip writer and the response body.
+const PIPE_CAPACITY: usize = 256 * 1024;
+/// Images read ahead of the zip writer.
+const READ_AHEAD: usize = 2;
+
+enum ArchiveEntry {
+    Image(String, Vec<u8>),
+    /// Selected but no longer in storage; listed in the manifest.
+    Missing(String),
+}
+
+pub fn routes() -> Router<Arc<AppState>> {
+    Router::new().route("/api/outputs/archive", post(outputs_archive))
+}
+
+#[derive(Deserialize)]
+struct ArchiveRequest {
+    /// Relative paths to include. When empty, `filter` selects the outputs instead.
+    #[serde(default)]
+    items: Vec<String>,
+    filter: Option<OutputFilter>,
+    /// Add `manifest.json` with each image's generation parameters.
+    #[serde(default)]
+    include_manifest: bool,
+}
+
+async fn outputs_archive(
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<ArchiveRequest>,
+) -> Result<Response, ApiError> {
+    debug!(
+        items = req.items.len(),
+        filtered = req.filter.is_some(),
+        include_manifest = req.include_manifest,
+        "outputs_archive"
+    );
+
+    let paths = if !req.items.is_empty() {
+        let mut paths = Vec::with_capacity(req.items.len());
+        for rel in &req.items {
+            let rel = normalize_rel_path(rel);
+            if !is_safe_rel_path(&rel) || is_internal(&rel) {
+                return Err(ApiError::bad_request(anyhow!("invalid output path: {rel}")));
+            }
+            paths.push(rel);
+        }
+        paths
+    } else if let Some(filter) = req.filter {
+        let mut items = state
+            .outputs
+            .list_items()
+            .await
+            .map_err(ApiError::internal)?;
+        if filter.needs_annotations() {
+            state
+                .output_annotations
+                .annotate_items(&state.outputs, &mut items)
+                .await
+                .map_err(ApiError::internal)?;
+        }
+        items
+            .into_iter()
+            .filter(|it| filter.matches(it))
+            .map(|it| it.path)
+            .collect()
+    } else {
+        return Err(ApiError::bad_request(anyhow!("no outputs selected")));
+    };
+
+    if paths.is_empty() {
+        return Err(ApiError::bad_request(anyhow!("no outputs matched")));
+    }
+
+    // Non-PNG outputs keep their metadata in the database; fetch it up front since
+    // the archive is written on a blocking thread.
+    let mut stored_meta = HashMap::new();
+    if req.include_manifest {
+        for rel in paths
+            .iter()
+            .filter(|p| !p.to_ascii_lowercase().ends_with(".png"))
+        {
+            match state.outputs.stored_metadata(rel).await {
+                Ok(Some(meta)) => {
+                    stored_meta.insert(rel.clone(), meta);
+                }
+                Ok(None) => {}
+                Err(e) => warn!(path = %rel, error = %e, "archive: failed to load metadata"),
+            }
+        }
+    }
+
+    // Images are read from storage here and handed to the blocking zip writer, so
+    // only a few are held in memory at a time.
+    let (tx, rx) = mpsc::channel(READ_AHEAD);
+    let outputs = state.outputs.clone();
+    tokio::spawn(async move {
+        for rel in paths {
+            let entry = match outputs.read(&rel).await {
+                Ok(bytes) => Ok(ArchiveEntry::Image(rel, bytes)),
+                Err(OutputError::NotFound) => {
+                    warn!(path = %rel, "archive: output missing, skipped");
+                    Ok(ArchiveEntry::Missing(rel))
+                }
+                Err(e) => Err(anyhow!("failed to read {rel}: {e}")),
+            };
+            let failed = entry.is_err();
+            if tx.send(entry).await.is_err() || failed {
+                break;
+            }
+        }
+    });
+
+    let (reader, writer) = tokio::io::duplex(PIPE_CAPACITY);
+    let writer = SyncIoBridge::new(writer);
+    let include_manifest = req.include_manifest;
+    let (done_tx, done_rx) = oneshot::channel();
+    tokio::task::spawn_blocking(move || {
+        let result = write_archive(rx, include_manifest, stored_meta, writer);
+        match &result {
+            Ok(count) => info!(count, "outputs archive streamed"),
+            Err(e) => warn!(error = %e, "outputs archive aborted"),
+        }
+        let _ = done_tx.send(result.is_ok());
+    });
+    let reader = ArchiveReader {
+        inner: reader,
+        done: done_rx,
+    };
+
+    let filename = format!("nai-outputs-{}.zip", Local::now().format("%Y%m%d-%H%M%S"));
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/zip".to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{filename}\""),
+            ),
+        ],
+        Body::from_stream(ReaderStream::new(reader)),
+    )
+        .into_response())
+}
+
+/// The archive body. Ends with an error instead of a clean EOF when the writer
+/// gave up, so the response is aborted rather than a valid but truncated zip.
+struct ArchiveReader {
+    inner: DuplexStream,
+    /// Whether the archive was finished.
+    done: oneshot::Receiver<bool>,
+}
+
+impl AsyncRead for ArchiveReader {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let before = buf.filled().len();
+        match Pin::new(&mut self.inner).poll_read(cx, buf) {
+            Poll::Ready(Ok(())) if buf.filled().len() == before => {}
+            other => return other,
+        }
+        match Pin::new(&mut self.done).poll(cx) {
+            Poll::Ready(Ok(true)) => Poll::Ready(Ok(())),
+            Poll::Ready(_) => Poll::Ready(Err(io::Error::other("outputs archive aborted"))),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+/// Returns the number of images written. Fails, leaving the zip unfinished,
+/// when an image couldn't be read.
+fn write_archive(
+    mut images: mpsc::Receiver<anyhow::Result<ArchiveEntry>>,
+    include_manifest: bool,
+    mut stored_meta: HashMap<String, Value>,
+    out: impl Write,
+) -> anyhow::Result<usize> {
+    let mut zip = ZipWriter::new_stream(out);
+    // Images are already compressed; only the manifest benefits from deflate.
+    let stored = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+    let deflated = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+
+    let mut manifest = Vec::new();
+    let mut missing = Vec::new();
+    let mut count = 0;
+    while let Some(entry) = images.blocking_recv() {
+        let (rel, bytes) = match entry? {
+            ArchiveEntry::Image(rel, bytes) => (rel, bytes),
+            ArchiveEntry::Missing(rel) => {
+                missing.push(rel);
+                continue;
+            }
+        };
+        zip.start_file(rel.as_str(), stored)?;
+        zip.write_all(&bytes)?;
+        count += 1;
+        if include_manifest {
+            manifest.push(json!({
+                "path": rel,
+                "metadata": png_generation_metadata(&bytes).or_else(|| stored_meta.remove(&rel)),
+            }));
+        }
+    }
+
+    if include_manifest {
+        zip.start_file("manifest.json", deflated)?;
+        serde_json::to_writer_pretty(&mut zip, &json!({ "items": manifest, "missing": missing }))?;
+    }
+
+    let mut out = zip.finish()?.into_inner();
+    out.flush()?;
+    Ok(count)
+}
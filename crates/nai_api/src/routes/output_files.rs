@@ -0,0 +1,43 @@
+use std::sync::Arc;
+
+use axum::{
+    Router,
+    extract::{Path, State},
+    http::header,
+    response::IntoResponse,
+    routing::get,
+};
+use tracing::debug;
+
+use nai_core::{output_format::content_type, outputs::OutputError};
+
+use super::{ApiError, AppState};
+
+pub fn routes() -> Router<Arc<AppState>> {
+    Router::new().route("/outputs/{*path}", get(output_file))
+}
+
+/// Serve an output image from the configured storage backend.
+async fn output_file(
+    State(state): State<Arc<AppState>>,
+    Path(path): Path<String>,
+) -> Result<impl IntoResponse, ApiError> {
+    debug!(path = %path, "output_file");
+    let Some(content_type) = content_type(&path) else {
+        return Err(ApiError::not_found("output not found"));
+    };
+    let bytes = state.outputs.read(&path).await.map_err(|e| match e {
+        OutputError::NotFound => ApiError::not_found("output not found"),
+        OutputError::InvalidPath => ApiError::bad_request(e),
+        other => ApiError::internal(other),
+    })?;
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, content_type),
+            // Output paths are never reused, so the content behind a URL doesn't change.
+            (header::CACHE_CONTROL, "public, max-age=86400"),
+        ],
+        bytes,
+    ))
+}
@@ -1,10 +1,27 @@
 use std::sync::Arc;
 
-use axum::{extract::State, routing::{get, post}, Json, Router};
-use serde::Deserialize;
-use tracing::debug;
+use anyhow::anyhow;
+use axum::{
+    Json, Router,
+    extract::State,
+    routing::{get, post},
+};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, warn};
 
-use nai_core::dto::{OutputsDeleteRequest, OutputsDeleteResponse, OutputsListResponse};
+use nai_core::{
+    dto::{
+        OutputsAnnotateRequest, OutputsAnnotateResponse, OutputsDeleteRequest,
+        OutputsDeleteResponse, OutputsListResponse, TrashEmptyRequest, TrashEmptyResponse,
+        TrashListResponse, TrashRestoreRequest, TrashRestoreResponse,
+    },
+    outputs::{OutputError, paginate},
+};
+
+use crate::{
+    AnnotationPatch, DiskStatus, OutputFilter, RetentionReport,
+    output_annotation_store::OutputTagCount, run_retention,
+};
 
 use super::{ApiError, ApiResult, AppState};
 
@@ -12,12 +29,52 @@ pub fn routes() -> Router<Arc<AppState>> {
     Router::new()
         .route("/api/outputs", get(list_outputs))
         .route("/api/outputs/delete", post(outputs_delete))
+        .route("/api/outputs/annotate", post(outputs_annotate))
+        .route("/api/outputs/tags", get(outputs_tags))
+        .route("/api/outputs/trash", get(trash_list))
+        .route("/api/outputs/trash/restore", post(trash_restore))
+        .route("/api/outputs/trash/empty", post(trash_empty))
+        .route("/api/outputs/metadata", get(outputs_metadata))
+        .route("/api/outputs/storage", get(outputs_storage))
+        .route("/api/outputs/retention", post(outputs_retention))
 }
 
 #[derive(Deserialize)]
 struct OutputsListQuery {
     limit: Option<usize>,
     offset: Option<usize>,
+    favorite: Option<bool>,
+    min_rating: Option<u8>,
+    /// Comma-separated; all must match.
+    tags: Option<String>,
+    op_type: Option<String>,
+}
+
+impl OutputsListQuery {
+    fn filter(&self) -> OutputFilter {
+        OutputFilter {
+            favorite: self.favorite,
+            min_rating: self.min_rating,
+            tags: parse_tags(self.tags.as_deref()),
+            op_type: self.op_type.clone().filter(|s| !s.is_empty()),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct OutputTagsResponse {
+    items: Vec<OutputTagCount>,
+}
+
+fn parse_tags(tags: Option<&str>) -> Vec<String> {
+    tags.map(|s| {
+        s.split(',')
+            .map(|t| t.trim())
+            .filter(|t| !t.is_empty())
+            .map(|t| t.to_string())
+            .collect::<Vec<_>>()
+    })
+    .unwrap_or_default()
 }
 
 async fn list_outputs(
@@ -27,12 +84,34 @@ async fn list_outputs(
     debug!("outputs_list");
     let limit = query.limit.unwrap_or(60).clamp(1, 200);
     let offset = query.offset.unwrap_or(0);
+    let filter = query.filter();
 
-    let (items, has_more, next_offset) = state
+    let mut items = state
         .outputs
-        .list_items_paginated(limit, offset)
+        .list_items()
         .await
         .map_err(ApiError::internal)?;
+
+    // Annotation filters need every item resolved; otherwise only the requested page.
+    let (items, has_more, next_offset) = if filter.needs_annotations() {
+        state
+            .output_annotations
+            .annotate_items(&state.outputs, &mut items)
+            .await
+            .map_err(ApiError::internal)?;
+        items.retain(|it| filter.matches(it));
+        paginate(items, limit, offset)
+    } else {
+        items.retain(|it| filter.matches(it));
+        let (mut page, has_more, next_offset) = paginate(items, limit, offset);
+        state
+            .output_annotations
+            .annotate_items(&state.outputs, &mut page)
+            .await
+            .map_err(ApiError::internal)?;
+        (page, has_more, next_offset)
+    };
+
     Ok(Json(OutputsListResponse {
         items,
         next_offset,
@@ -45,10 +124,142 @@ async fn outputs_delete(
     Json(req): Json<OutputsDeleteRequest>,
 ) -> ApiResult<OutputsDeleteResponse> {
     debug!(count = req.items.len(), "outputs_delete");
-    let deleted = state
+    let trashed = state
+        .outputs
+        .trash_rel_files(&req.items)
+        .await
+        .map_err(ApiError::internal)?;
+    if let Err(e) = state.output_annotations.forget_paths(&req.items).await {
+        warn!(error = %e, "failed to prune output index");
+    }
+    Ok(Json(OutputsDeleteResponse {
+        deleted: trashed.len(),
+        trash_ids: trashed.into_iter().map(|e| e.id).collect(),
+    }))
+}
+
+async fn trash_list(State(state): State<Arc<AppState>>) -> ApiResult<TrashListResponse> {
+    debug!("trash_list");
+    let items = state
         .outputs
-        .delete_rel_files(&req.items)
+        .list_trash()
         .await
         .map_err(ApiError::internal)?;
-    Ok(Json(OutputsDeleteResponse { deleted }))
+    Ok(Json(TrashListResponse { items }))
+}
+
+async fn trash_restore(
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<TrashRestoreRequest>,
+) -> ApiResult<TrashRestoreResponse> {
+    debug!(count = req.ids.len(), "trash_restore");
+    let report = state
+        .outputs
+        .restore_trash(&req.ids)
+        .await
+        .map_err(ApiError::internal)?;
+    Ok(Json(report))
+}
+
+async fn trash_empty(
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<TrashEmptyRequest>,
+) -> ApiResult<TrashEmptyResponse> {
+    debug!(ids = ?req.ids.as_ref().map(|ids| ids.len()), "trash_empty");
+    let purged = state
+        .outputs
+        .empty_trash(req.ids.as_deref())
+        .await
+        .map_err(ApiError::internal)?;
+    Ok(Json(TrashEmptyResponse { purged }))
+}
+
+async fn outputs_annotate(
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<OutputsAnnotateRequest>,
+) -> ApiResult<OutputsAnnotateResponse> {
+    debug!(count = req.items.len(), "outputs_annotate");
+    if req.rating.is_some_and(|r| r > 5) {
+        return Err(ApiError::bad_request(anyhow!(
+            "rating must be 1-5, or 0 to clear"
+        )));
+    }
+
+    let patch = AnnotationPatch {
+        favorite: req.favorite,
+        rating: req.rating,
+        tags: req.tags,
+        add_tags: req.add_tags,
+        remove_tags: req.remove_tags,
+    };
+    let updated = state
+        .output_annotations
+        .update(&state.outputs, &req.items, patch)
+        .await
+        .map_err(ApiError::internal)?;
+    Ok(Json(OutputsAnnotateResponse { updated }))
+}
+
+async fn outputs_tags(State(state): State<Arc<AppState>>) -> ApiResult<OutputTagsResponse> {
+    debug!("outputs_tags");
+    let items = state
+        .output_annotations
+        .tag_counts()
+        .await
+        .map_err(ApiError::internal)?;
+    Ok(Json(OutputTagsResponse { items }))
+}
+
+#[derive(Deserialize, Default)]
+struct RetentionRequest {
+    /// Report what would be pruned without deleting anything.
+    #[serde(default)]
+    dry_run: bool,
+}
+
+async fn outputs_storage(State(state): State<Arc<AppState>>) -> ApiResult<DiskStatus> {
+    debug!("outputs_storage");
+    Ok(Json(state.disk_guard.refresh()))
+}
+
+async fn outputs_retention(
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<RetentionRequest>,
+) -> ApiResult<RetentionReport> {
+    debug!(dry_run = req.dry_run, "outputs_retention");
+    let report = run_retention(&state, req.dry_run)
+        .await
+        .map_err(ApiError::internal)?;
+    Ok(Json(report))
+}
+
+#[derive(Deserialize)]
+struct OutputMetadataQuery {
+    path: String,
+}
+
+#[derive(Serialize)]
+struct OutputMetadataResponse {
+    path: String,
+    metadata: Option<serde_json::Value>,
+}
+
+async fn outputs_metadata(
+    State(state): State<Arc<AppState>>,
+    axum::extract::Query(query): axum::extract::Query<OutputMetadataQuery>,
+) -> ApiResult<OutputMetadataResponse> {
+    debug!(path = %query.path, "outputs_metadata");
+    let metadata = state
+        .outputs
+        .generation_metadata(&query.path)
+        .await
+        .map_err(|e| match e {
+            OutputError::NotFound => ApiError::not_found("output not found"),
+            OutputError::InvalidPath => ApiError::bad_request(e),
+            other => ApiError::internal(other),
+        })?;
+    Ok(Json(OutputMetadataResponse {
+        path: query.path,
+        metadata,
+    }))
 }
@@ -0,0 +1,151 @@
+use std::sync::Arc;
+
+use axum::{Json, Router, extract::State, routing::post};
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+use nai_core::{
+    dto::CharacterPrompt,
+    prompt::{ast::Prompt, lint::LintIssue},
+    tokenizer::{Encoder, TokenReport},
+};
+
+use crate::{expand_prompts_pair, lint_prompts};
+
+use super::{ApiError, ApiResult, AppState};
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum WeightStyle {
+    /// `{{tag}}`, `[tag]`
+    Braces,
+    /// `1.1::tag::`
+    Numeric,
+}
+
+#[derive(Deserialize)]
+struct PromptFormatRequest {
+    text: String,
+    /// Rewrite weights in this style; only normalized when omitted.
+    weights: Option<WeightStyle>,
+}
+
+#[derive(Serialize)]
+struct PromptFormatResponse {
+    text: String,
+}
+
+#[derive(Deserialize)]
+struct PromptLintRequest {
+    positive: String,
+    negative: String,
+    #[serde(default)]
+    character_prompts: Vec<CharacterPrompt>,
+}
+
+#[derive(Serialize)]
+struct PromptLintResponse {
+    issues: Vec<LintIssue>,
+}
+
+#[derive(Deserialize)]
+struct PromptTokensRequest {
+    model: String,
+    positive: String,
+    negative: String,
+    #[serde(default)]
+    character_prompts: Vec<CharacterPrompt>,
+    #[serde(default)]
+    add_quality_tags: bool,
+}
+
+#[derive(Serialize)]
+struct PromptTokensResponse {
+    #[serde(flatten)]
+    report: TokenReport,
+    /// Snippet warnings, then prompts over the limit.
+    warnings: Vec<String>,
+}
+
+pub fn routes() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/api/prompt/format", post(prompt_format))
+        .route("/api/prompt/lint", post(prompt_lint))
+        .route("/api/prompt/tokens", post(prompt_tokens))
+}
+
+async fn prompt_format(Json(req): Json<PromptFormatRequest>) -> ApiResult<PromptFormatResponse> {
+    debug!(weights = ?req.weights, "prompt_format");
+    let prompt = Prompt::parse(&req.text);
+    let formatted = match req.weights {
+        None => prompt.normalized(),
+        Some(WeightStyle::Braces) => prompt.to_brace_weights(),
+        Some(WeightStyle::Numeric) => prompt.to_numeric_weights(),
+    };
+    Ok(Json(PromptFormatResponse {
+        text: formatted.to_string(),
+    }))
+}
+
+async fn prompt_lint(
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<PromptLintRequest>,
+) -> ApiResult<PromptLintResponse> {
+    debug!("prompt_lint");
+    let issues = lint_prompts(
+        &state.config,
+        &state.prompt_snippets,
+        &req.positive,
+        &req.negative,
+        &req.character_prompts,
+    )
+    .await
+    .map_err(ApiError::internal)?;
+    Ok(Json(PromptLintResponse { issues }))
+}
+
+/// Token counts after snippet expansion. Wildcards and choices are counted as
+/// written, since each image picks differently.
+async fn prompt_tokens(
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<PromptTokensRequest>,
+) -> ApiResult<PromptTokensResponse> {
+    debug!(model = %req.model, "prompt_tokens");
+    let encoder = Encoder::for_model(&req.model);
+    if !state.tokenizers.has(encoder) {
+        return Err(ApiError::unavailable(anyhow::anyhow!(
+            "{} tokenizer not found in {}",
+            encoder.as_str(),
+            state.config.tokenizer_dir.display()
+        )));
+    }
+
+    let cfg = &state.config;
+    let snippets = &state.prompt_snippets;
+    let base = expand_prompts_pair(cfg, snippets, &req.positive, &req.negative)
+        .await
+        .map_err(ApiError::bad_request)?;
+    let mut warnings = base.warnings;
+    let mut characters = req.character_prompts;
+    for c in characters.iter_mut() {
+        let expanded = expand_prompts_pair(cfg, snippets, &c.prompt, &c.uc)
+            .await
+            .map_err(ApiError::bad_request)?;
+        warnings.extend(expanded.warnings);
+        c.prompt = expanded.positive;
+        c.uc = expanded.negative;
+    }
+
+    let report = state
+        .tokenizers
+        .report_prompts(
+            &req.model,
+            &base.positive,
+            &base.negative,
+            &characters,
+            req.add_quality_tags,
+        )
+        .ok_or_else(|| ApiError::internal(anyhow::anyhow!("tokenizer missing")))?;
+    warnings.extend(report.warnings());
+    Ok(Json(PromptTokensResponse { report, warnings }))
+}
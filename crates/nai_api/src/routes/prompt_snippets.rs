@@ -6,21 +6,18 @@ use axum::{
     extract::State,
     routing::{get, post},
 };
+use nai_core::prompt::snippet::check_snippet_name;
 use serde::{Deserialize, Serialize};
 use tracing::debug;
 
 use crate::{
     expand_prompts_pair,
-    prompt_snippet_store::{PromptSnippet, PromptSnippetSummary},
+    prompt_snippet_store::{PromptSnippet, SnippetFolder, SnippetPage, SnippetQuery, TagMode},
+    snippet_graph::{SnippetGraph, SnippetReferrer},
 };
 
 use super::{ApiError, ApiResult, AppState};
 
-#[derive(Serialize)]
-struct SnippetsListResponse {
-    items: Vec<PromptSnippetSummary>,
-}
-
 #[derive(Serialize)]
 struct SnippetGetResponse {
     snippet: Option<PromptSnippet>,
@@ -36,12 +33,49 @@ struct SnippetPutRequest {
 struct SnippetRenameRequest {
     from: String,
     to: String,
+    /// Also rewrite `<snippet:from>` wherever it is used.
+    #[serde(default)]
+    update_references: bool,
+}
+
+#[derive(Serialize)]
+struct SnippetRenameResponse {
+    ok: bool,
+    updated: Vec<SnippetReferrer>,
+}
+
+#[derive(Deserialize)]
+struct FolderQuery {
+    /// Empty or missing for the root.
+    #[serde(default)]
+    path: String,
+}
+
+#[derive(Deserialize)]
+struct FolderMoveRequest {
+    from: String,
+    /// Empty for the root.
+    to: String,
+    #[serde(default)]
+    update_references: bool,
+}
+
+#[derive(Serialize)]
+struct FolderMoveResponse {
+    ok: bool,
+    moved: usize,
+    updated: Vec<SnippetReferrer>,
 }
 
 #[derive(Debug, Deserialize)]
 struct SnippetListQuery {
     q: Option<String>,
+    /// Comma-separated.
     tags: Option<String>,
+    #[serde(default)]
+    tag_mode: TagMode,
+    limit: Option<usize>,
+    offset: Option<usize>,
 }
 
 #[derive(Deserialize)]
@@ -65,6 +99,12 @@ struct SnippetPreviewResponse {
 pub fn routes() -> Router<Arc<AppState>> {
     Router::new()
         .route("/api/prompt_snippets", get(prompt_snippets_list))
+        .route("/api/prompt_snippets/graph", get(prompt_snippets_graph))
+        .route("/api/prompt_snippets/folder", get(prompt_snippets_folder))
+        .route(
+            "/api/prompt_snippets/folder/move",
+            post(prompt_snippets_folder_move),
+        )
         .route(
             "/api/prompt_snippet",
             get(prompt_snippet_get)
@@ -89,15 +129,21 @@ fn parse_tags(tags: Option<String>) -> Vec<String> {
 async fn prompt_snippets_list(
     State(state): State<Arc<AppState>>,
     Query(q): Query<SnippetListQuery>,
-) -> ApiResult<SnippetsListResponse> {
+) -> ApiResult<SnippetPage> {
     debug!(?q, "prompt_snippets_list");
-    let tags = parse_tags(q.tags);
-    let items = state
+    let query = SnippetQuery {
+        q: q.q,
+        tags: parse_tags(q.tags),
+        tag_mode: q.tag_mode,
+        limit: q.limit.unwrap_or(200).clamp(1, 500),
+        offset: q.offset.unwrap_or(0),
+    };
+    let page = state
         .prompt_snippets
-        .list(q.q.as_deref(), &tags)
+        .list(query)
         .await
         .map_err(ApiError::internal)?;
-    Ok(Json(SnippetsListResponse { items }))
+    Ok(Json(page))
 }
 
 async fn prompt_snippet_get(
@@ -118,6 +164,7 @@ async fn prompt_snippet_put(
     Json(req): Json<SnippetPutRequest>,
 ) -> ApiResult<serde_json::Value> {
     debug!(name = %req.name, "prompt_snippet_put");
+    check_snippet_name(&req.name).map_err(ApiError::bad_request)?;
     state
         .prompt_snippets
         .upsert(&req.name, req.snippet)
@@ -142,17 +189,71 @@ async fn prompt_snippet_delete(
 async fn prompt_snippet_rename(
     State(state): State<Arc<AppState>>,
     Json(req): Json<SnippetRenameRequest>,
-) -> ApiResult<serde_json::Value> {
-    debug!(from = %req.from, to = %req.to, "prompt_snippet_rename");
-    if req.to.trim().is_empty() {
-        return Err(ApiError::bad_request(anyhow::anyhow!("empty snippet name")));
+) -> ApiResult<SnippetRenameResponse> {
+    debug!(
+        from = %req.from,
+        to = %req.to,
+        update_references = req.update_references,
+        "prompt_snippet_rename"
+    );
+    check_snippet_name(&req.to).map_err(ApiError::bad_request)?;
+    let updated = state
+        .prompt_snippets
+        .rename(&req.from, &req.to, req.update_references)
+        .await
+        .map_err(ApiError::bad_request)?
+        .ok_or_else(|| ApiError::not_found(format!("snippet not found: {}", req.from)))?;
+    Ok(Json(SnippetRenameResponse { ok: true, updated }))
+}
+
+async fn prompt_snippets_folder(
+    State(state): State<Arc<AppState>>,
+    Query(q): Query<FolderQuery>,
+) -> ApiResult<SnippetFolder> {
+    debug!(path = %q.path, "prompt_snippets_folder");
+    let folder = state
+        .prompt_snippets
+        .folder(&q.path)
+        .await
+        .map_err(ApiError::internal)?;
+    Ok(Json(folder))
+}
+
+async fn prompt_snippets_folder_move(
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<FolderMoveRequest>,
+) -> ApiResult<FolderMoveResponse> {
+    debug!(
+        from = %req.from,
+        to = %req.to,
+        update_references = req.update_references,
+        "prompt_snippets_folder_move"
+    );
+    check_snippet_name(req.from.trim_matches('/')).map_err(ApiError::bad_request)?;
+    let to = req.to.trim_matches('/');
+    if !to.is_empty() {
+        check_snippet_name(to).map_err(ApiError::bad_request)?;
     }
-    state
+    let (moved, updated) = state
         .prompt_snippets
-        .rename(&req.from, &req.to)
+        .move_folder(&req.from, to, req.update_references)
         .await
         .map_err(ApiError::bad_request)?;
-    Ok(Json(super::error::ok_true()))
+    Ok(Json(FolderMoveResponse {
+        ok: true,
+        moved,
+        updated,
+    }))
+}
+
+async fn prompt_snippets_graph(State(state): State<Arc<AppState>>) -> ApiResult<SnippetGraph> {
+    debug!("prompt_snippets_graph");
+    let graph = state
+        .prompt_snippets
+        .graph()
+        .await
+        .map_err(ApiError::internal)?;
+    Ok(Json(graph))
 }
 
 async fn prompt_snippet_preview(
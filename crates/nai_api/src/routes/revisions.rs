@@ -0,0 +1,118 @@
+use std::sync::Arc;
+
+use axum::{
+    Json, Router,
+    extract::{Path, Query, State},
+    routing::{get, post},
+};
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+use crate::{Revision, RevisionDiff, RevisionKind, RevisionSummary};
+
+use super::{ApiError, ApiResult, AppState};
+
+#[derive(Deserialize)]
+struct RevisionsListQuery {
+    kind: RevisionKind,
+    name: String,
+    /// Generation presets only.
+    #[serde(default)]
+    model: String,
+}
+
+#[derive(Serialize)]
+struct RevisionsListResponse {
+    items: Vec<RevisionSummary>,
+}
+
+#[derive(Serialize)]
+struct RevisionGetResponse {
+    revision: Option<Revision>,
+}
+
+#[derive(Deserialize)]
+struct RevisionDiffQuery {
+    from: i64,
+    to: i64,
+}
+
+pub fn routes() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/api/revisions", get(revisions_list))
+        .route("/api/revisions/diff", get(revisions_diff))
+        .route("/api/revision/{id}", get(revision_get))
+        .route("/api/revision/{id}/restore", post(revision_restore))
+}
+
+async fn revisions_list(
+    State(state): State<Arc<AppState>>,
+    Query(q): Query<RevisionsListQuery>,
+) -> ApiResult<RevisionsListResponse> {
+    debug!(kind = ?q.kind, name = %q.name, "revisions_list");
+    let items = state
+        .revisions
+        .list(q.kind, &q.model, &q.name)
+        .await
+        .map_err(ApiError::internal)?;
+    Ok(Json(RevisionsListResponse { items }))
+}
+
+async fn revision_get(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<i64>,
+) -> ApiResult<RevisionGetResponse> {
+    debug!(id, "revision_get");
+    let revision = state.revisions.get(id).await.map_err(ApiError::internal)?;
+    Ok(Json(RevisionGetResponse { revision }))
+}
+
+async fn revisions_diff(
+    State(state): State<Arc<AppState>>,
+    Query(q): Query<RevisionDiffQuery>,
+) -> ApiResult<RevisionDiff> {
+    debug!(from = q.from, to = q.to, "revisions_diff");
+    let diff = state
+        .revisions
+        .diff(q.from, q.to)
+        .await
+        .map_err(ApiError::bad_request)?;
+    Ok(Json(diff))
+}
+
+/// Write a revision back as the current value, which records a new revision.
+async fn revision_restore(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<i64>,
+) -> ApiResult<serde_json::Value> {
+    debug!(id, "revision_restore");
+    let Some(revision) = state.revisions.get(id).await.map_err(ApiError::internal)? else {
+        return Err(ApiError::not_found(format!("revision not found: {id}")));
+    };
+    let Some(value) = revision.value else {
+        return Err(ApiError::bad_request(anyhow::anyhow!(
+            "revision {id} is a delete"
+        )));
+    };
+    let name = &revision.name;
+    match revision.kind {
+        RevisionKind::PromptSnippet => {
+            let snippet = serde_json::from_value(value).map_err(ApiError::internal)?;
+            state.prompt_snippets.upsert(name, snippet).await
+        }
+        RevisionKind::PromptPreset => {
+            let preset = serde_json::from_value(value).map_err(ApiError::internal)?;
+            state.prompt_presets.upsert(name, &preset).await
+        }
+        RevisionKind::CharacterPreset => {
+            let preset = serde_json::from_value(value).map_err(ApiError::internal)?;
+            state.character_presets.upsert(name, &preset).await
+        }
+        RevisionKind::Preset => {
+            let preset = serde_json::from_value(value).map_err(ApiError::internal)?;
+            state.presets.upsert(&revision.model, name, &preset).await
+        }
+    }
+    .map_err(ApiError::internal)?;
+    Ok(Json(super::error::ok_true()))
+}
@@ -0,0 +1,107 @@
+use std::sync::Arc;
+
+use axum::{
+    Json, Router,
+    extract::{DefaultBodyLimit, Query, State},
+    http::header,
+    response::{IntoResponse, Response},
+    routing::{get, post},
+};
+use chrono::Local;
+use serde::Deserialize;
+use tracing::{debug, info};
+
+use crate::{
+    BundleSection, ConflictStrategy, SettingsBundle, SettingsImportReport, SettingsStores,
+};
+
+use super::{ApiError, ApiResult, AppState};
+
+const IMPORT_BODY_LIMIT: usize = 64 * 1024 * 1024;
+
+fn default_true() -> bool {
+    true
+}
+
+#[derive(Deserialize)]
+struct ExportQuery {
+    /// Comma-separated; all when missing.
+    sections: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ImportQuery {
+    /// Comma-separated; all when missing.
+    sections: Option<String>,
+    #[serde(default)]
+    conflict: ConflictStrategy,
+    /// Report only; pass `false` to write.
+    #[serde(default = "default_true")]
+    dry_run: bool,
+}
+
+pub fn routes() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/api/export", get(settings_export))
+        .route(
+            "/api/import",
+            post(settings_import).layer(DefaultBodyLimit::max(IMPORT_BODY_LIMIT)),
+        )
+}
+
+fn stores(state: &AppState) -> SettingsStores {
+    SettingsStores {
+        presets: state.presets.clone(),
+        prompt_presets: state.prompt_presets.clone(),
+        character_presets: state.character_presets.clone(),
+        prompt_snippets: state.prompt_snippets.clone(),
+        last_generation: state.last_generation.clone(),
+        db: state.db.clone(),
+    }
+}
+
+fn sections(s: Option<&str>) -> Result<Vec<BundleSection>, ApiError> {
+    BundleSection::parse_list(s.unwrap_or_default()).map_err(ApiError::bad_request)
+}
+
+async fn settings_export(
+    State(state): State<Arc<AppState>>,
+    Query(q): Query<ExportQuery>,
+) -> Result<Response, ApiError> {
+    debug!(sections = ?q.sections, "settings_export");
+    let sections = sections(q.sections.as_deref())?;
+    let bundle = stores(&state)
+        .export(&sections)
+        .await
+        .map_err(ApiError::internal)?;
+    let filename = format!(
+        "nai-ui-settings-{}.json",
+        Local::now().format("%Y%m%d-%H%M%S")
+    );
+    Ok((
+        [(
+            header::CONTENT_DISPOSITION,
+            format!("attachment; filename=\"{filename}\""),
+        )],
+        Json(bundle),
+    )
+        .into_response())
+}
+
+async fn settings_import(
+    State(state): State<Arc<AppState>>,
+    Query(q): Query<ImportQuery>,
+    Json(bundle): Json<SettingsBundle>,
+) -> ApiResult<SettingsImportReport> {
+    debug!(sections = ?q.sections, conflict = ?q.conflict, dry_run = q.dry_run, "settings_import");
+    let sections = sections(q.sections.as_deref())?;
+    bundle.check_version().map_err(ApiError::bad_request)?;
+    let report = stores(&state)
+        .import(bundle, &sections, q.conflict, q.dry_run)
+        .await
+        .map_err(ApiError::internal)?;
+    if !q.dry_run {
+        info!(written = report.written(), "settings imported");
+    }
+    Ok(Json(report))
+}
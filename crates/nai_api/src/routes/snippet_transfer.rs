@@ -0,0 +1,109 @@
+use std::sync::Arc;
+
+use axum::{
+    Json, Router,
+    body::Bytes,
+    extract::{DefaultBodyLimit, Query, State},
+    http::header,
+    response::{IntoResponse, Response},
+    routing::{get, post},
+};
+use chrono::Local;
+use serde::Deserialize;
+use tracing::{debug, info};
+
+use crate::snippet_transfer::{
+    ConflictStrategy, ImportReport, SnippetFormat, export_snippets, import_snippets, parse_snippets,
+};
+
+use super::{ApiError, ApiResult, AppState};
+
+const IMPORT_BODY_LIMIT: usize = 64 * 1024 * 1024;
+
+fn default_true() -> bool {
+    true
+}
+
+#[derive(Deserialize)]
+struct ImportQuery {
+    format: SnippetFormat,
+    #[serde(default)]
+    conflict: ConflictStrategy,
+    /// Report only; pass `false` to write.
+    #[serde(default = "default_true")]
+    dry_run: bool,
+    /// Import under this folder.
+    #[serde(default)]
+    folder: String,
+}
+
+#[derive(Deserialize)]
+struct ExportQuery {
+    format: SnippetFormat,
+    /// Only snippets under this folder.
+    #[serde(default)]
+    folder: String,
+}
+
+pub fn routes() -> Router<Arc<AppState>> {
+    Router::new()
+        .route(
+            "/api/prompt_snippets/import",
+            post(snippets_import).layer(DefaultBodyLimit::max(IMPORT_BODY_LIMIT)),
+        )
+        .route("/api/prompt_snippets/export", get(snippets_export))
+}
+
+/// The file is the request body.
+async fn snippets_import(
+    State(state): State<Arc<AppState>>,
+    Query(q): Query<ImportQuery>,
+    body: Bytes,
+) -> ApiResult<ImportReport> {
+    debug!(format = ?q.format, conflict = ?q.conflict, dry_run = q.dry_run, bytes = body.len(), "snippets_import");
+    let items = parse_snippets(q.format, &body, &q.folder)
+        .map_err(|e| ApiError::bad_request(anyhow::anyhow!("{e:#}")))?;
+    let report = import_snippets(&state.prompt_snippets, items, q.conflict, q.dry_run)
+        .await
+        .map_err(ApiError::internal)?;
+    if !q.dry_run {
+        info!(
+            created = report.created.len(),
+            overwritten = report.overwritten.len(),
+            renamed = report.renamed.len(),
+            skipped = report.skipped.len(),
+            rejected = report.rejected.len(),
+            "snippets imported"
+        );
+    }
+    Ok(Json(report))
+}
+
+async fn snippets_export(
+    State(state): State<Arc<AppState>>,
+    Query(q): Query<ExportQuery>,
+) -> Result<Response, ApiError> {
+    debug!(format = ?q.format, folder = %q.folder, "snippets_export");
+    let snippets = state
+        .prompt_snippets
+        .snippets_in(&q.folder)
+        .await
+        .map_err(ApiError::internal)?;
+    let bytes = export_snippets(q.format, snippets).map_err(ApiError::internal)?;
+    let filename = format!(
+        "nai-snippets-{}.{}",
+        Local::now().format("%Y%m%d-%H%M%S"),
+        q.format.extension()
+    );
+    Ok((
+        [
+            (header::CONTENT_TYPE, q.format.content_type().to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{filename}\""),
+            ),
+        ],
+        bytes,
+    )
+        .into_response())
+}
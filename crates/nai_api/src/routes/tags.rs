@@ -0,0 +1,131 @@
+use std::sync::Arc;
+
+use axum::{
+    Json, Router,
+    extract::{DefaultBodyLimit, Query, State},
+    routing::{get, post},
+};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info};
+
+use crate::{TagImportReport, TagInfo, TagSuggestion};
+
+use super::{ApiError, ApiResult, AppState};
+
+/// Full Danbooru tag lists run to tens of megabytes.
+const IMPORT_BODY_LIMIT: usize = 256 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum ImportKind {
+    /// `name,category,post_count,"alias,alias"`
+    #[default]
+    Tags,
+    /// `antecedent,consequent`
+    Implications,
+}
+
+#[derive(Deserialize)]
+struct ImportQuery {
+    #[serde(default)]
+    kind: ImportKind,
+}
+
+#[derive(Deserialize)]
+struct CompleteQuery {
+    q: String,
+    limit: Option<usize>,
+}
+
+#[derive(Serialize)]
+struct CompleteResponse {
+    items: Vec<TagSuggestion>,
+}
+
+#[derive(Deserialize)]
+struct NameQuery {
+    name: String,
+}
+
+#[derive(Serialize)]
+struct TagGetResponse {
+    tag: Option<TagInfo>,
+}
+
+#[derive(Deserialize)]
+struct NormalizeRequest {
+    text: String,
+}
+
+#[derive(Serialize)]
+struct NormalizeResponse {
+    text: String,
+}
+
+pub fn routes() -> Router<Arc<AppState>> {
+    Router::new()
+        .route(
+            "/api/tags/import",
+            post(tags_import).layer(DefaultBodyLimit::max(IMPORT_BODY_LIMIT)),
+        )
+        .route("/api/tags/complete", get(tags_complete))
+        .route("/api/tags/normalize", post(tags_normalize))
+        .route("/api/tag", get(tag_get))
+}
+
+/// The CSV is the request body.
+async fn tags_import(
+    State(state): State<Arc<AppState>>,
+    Query(q): Query<ImportQuery>,
+    body: String,
+) -> ApiResult<TagImportReport> {
+    debug!(kind = ?q.kind, bytes = body.len(), "tags_import");
+    let report = match q.kind {
+        ImportKind::Tags => state.tags.import_tags(body).await,
+        ImportKind::Implications => state.tags.import_implications(body).await,
+    }
+    .map_err(ApiError::internal)?;
+    info!(
+        tags = report.tags,
+        aliases = report.aliases,
+        implications = report.implications,
+        skipped = report.skipped,
+        "tags imported"
+    );
+    Ok(Json(report))
+}
+
+async fn tags_complete(
+    State(state): State<Arc<AppState>>,
+    Query(q): Query<CompleteQuery>,
+) -> ApiResult<CompleteResponse> {
+    debug!(q = %q.q, "tags_complete");
+    let items = state
+        .tags
+        .complete(&q.q, q.limit.unwrap_or(20))
+        .await
+        .map_err(ApiError::internal)?;
+    Ok(Json(CompleteResponse { items }))
+}
+
+async fn tag_get(
+    State(state): State<Arc<AppState>>,
+    Query(q): Query<NameQuery>,
+) -> ApiResult<TagGetResponse> {
+    debug!(name = %q.name, "tag_get");
+    let tag = state.tags.get(&q.name).await.map_err(ApiError::internal)?;
+    Ok(Json(TagGetResponse { tag }))
+}
+
+async fn tags_normalize(
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<NormalizeRequest>,
+) -> ApiResult<NormalizeResponse> {
+    debug!("tags_normalize");
+    let text = state
+        .tags
+        .normalize_text(&req.text)
+        .await
+        .map_err(ApiError::internal)?;
+    Ok(Json(NormalizeResponse { text }))
+}
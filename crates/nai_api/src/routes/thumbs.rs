@@ -0,0 +1,44 @@
+use std::sync::Arc;
+
+use axum::{
+    Router,
+    extract::{Path, State},
+    http::header,
+    response::IntoResponse,
+    routing::get,
+};
+use tracing::debug;
+
+use nai_core::outputs::OutputError;
+
+use super::{ApiError, AppState};
+
+pub fn routes() -> Router<Arc<AppState>> {
+    Router::new().route("/thumbs/{width}/{*path}", get(thumbnail))
+}
+
+async fn thumbnail(
+    State(state): State<Arc<AppState>>,
+    Path((width, path)): Path<(u32, String)>,
+) -> Result<impl IntoResponse, ApiError> {
+    debug!(width, path = %path, "thumbnail");
+    let bytes = state
+        .outputs
+        .thumbnail(&path, width)
+        .await
+        .map_err(|e| match e {
+            OutputError::NotFound => ApiError::not_found("output not found"),
+            OutputError::InvalidPath | OutputError::UnsupportedThumbWidth(_) => {
+                ApiError::bad_request(e)
+            }
+            other => ApiError::internal(other),
+        })?;
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "image/jpeg"),
+            (header::CACHE_CONTROL, "public, max-age=86400"),
+        ],
+        bytes,
+    ))
+}
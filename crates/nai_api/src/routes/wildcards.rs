@@ -0,0 +1,134 @@
+use std::sync::Arc;
+
+use axum::{
+    Json, Router,
+    extract::Query,
+    extract::State,
+    routing::{get, post},
+};
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+use crate::{WildcardSummary, expand_wildcards_pair};
+
+use super::{ApiError, ApiResult, AppState};
+
+#[derive(Serialize)]
+struct WildcardsListResponse {
+    items: Vec<WildcardSummary>,
+}
+
+#[derive(Serialize)]
+struct WildcardGetResponse {
+    lines: Option<Vec<String>>,
+}
+
+#[derive(Deserialize)]
+struct WildcardPutRequest {
+    name: String,
+    lines: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct NameQuery {
+    name: String,
+}
+
+#[derive(Deserialize)]
+struct WildcardPreviewRequest {
+    positive: String,
+    negative: String,
+    /// Random when omitted; the response echoes the seed used.
+    seed: Option<u64>,
+}
+
+#[derive(Serialize)]
+struct WildcardPreviewResponse {
+    positive: String,
+    negative: String,
+    seed: u64,
+    /// How many distinct prompts the pair can expand to.
+    combinations: u64,
+    warnings: Vec<String>,
+}
+
+pub fn routes() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/api/wildcards", get(wildcards_list))
+        .route(
+            "/api/wildcard",
+            get(wildcard_get).put(wildcard_put).delete(wildcard_delete),
+        )
+        .route("/api/wildcard/preview", post(wildcard_preview))
+}
+
+async fn wildcards_list(State(state): State<Arc<AppState>>) -> ApiResult<WildcardsListResponse> {
+    debug!("wildcards_list");
+    let items = state.wildcards.list().await.map_err(ApiError::internal)?;
+    Ok(Json(WildcardsListResponse { items }))
+}
+
+async fn wildcard_get(
+    State(state): State<Arc<AppState>>,
+    Query(q): Query<NameQuery>,
+) -> ApiResult<WildcardGetResponse> {
+    debug!(name = %q.name, "wildcard_get");
+    let lines = state
+        .wildcards
+        .get(&q.name)
+        .await
+        .map_err(ApiError::bad_request)?;
+    Ok(Json(WildcardGetResponse { lines }))
+}
+
+async fn wildcard_put(
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<WildcardPutRequest>,
+) -> ApiResult<serde_json::Value> {
+    debug!(name = %req.name, "wildcard_put");
+    state
+        .wildcards
+        .upsert(&req.name, req.lines)
+        .await
+        .map_err(ApiError::bad_request)?;
+    Ok(Json(super::error::ok_true()))
+}
+
+async fn wildcard_delete(
+    State(state): State<Arc<AppState>>,
+    Query(q): Query<NameQuery>,
+) -> ApiResult<serde_json::Value> {
+    debug!(name = %q.name, "wildcard_delete");
+    let deleted = state
+        .wildcards
+        .delete(&q.name)
+        .await
+        .map_err(ApiError::bad_request)?;
+    if !deleted {
+        return Err(ApiError::not_found(format!(
+            "wildcard not found in database: {}",
+            q.name
+        )));
+    }
+    Ok(Json(super::error::ok_true()))
+}
+
+async fn wildcard_preview(
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<WildcardPreviewRequest>,
+) -> ApiResult<WildcardPreviewResponse> {
+    debug!(seed = ?req.seed, "wildcard_preview");
+    let seed = req.seed.unwrap_or_else(rand::random);
+    let (expanded, combinations) =
+        expand_wildcards_pair(&state.wildcards, &req.positive, &req.negative, seed)
+            .await
+            .map_err(ApiError::bad_request)?;
+
+    Ok(Json(WildcardPreviewResponse {
+        positive: expanded.positive,
+        negative: expanded.negative,
+        seed,
+        combinations,
+        warnings: expanded.warnings,
+    }))
+}
@@ -0,0 +1,391 @@
+//! Everything a setup is made of, as one versioned JSON bundle for moving it
+//! to another machine: generation presets, prompt and character presets,
+//! snippets and the last generation. Outputs stay where they are.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use serde::Serialize;
+
+use crate::{
+    CharacterPresetStore, CharacterSlotPreset, GeneratePreset, LastGenerationRecord,
+    LastGenerationStore, PresetStore, PromptPreset, PromptPresetStore, PromptSnippet,
+    PromptSnippetStore,
+    db::Database,
+    last_generation::{now_ms, write_last_generation},
+    preset_store::write_preset_json,
+    prompt_snippet_store::write_snippet,
+    revision_store::RevisionKind,
+    simple_json_store::write_named_json,
+    snippet_transfer::{
+        ConflictStrategy, ImportReport, RenamedItem, SnippetImportPlan, plan_snippet_import,
+    },
+};
+
+pub const SETTINGS_BUNDLE_VERSION: u32 = 1;
+
+/// Parts of a bundle, to export or import selectively.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BundleSection {
+    Presets,
+    PromptPresets,
+    CharacterPresets,
+    Snippets,
+    LastGeneration,
+}
+
+impl BundleSection {
+    /// A comma-separated list such as `presets,snippets`.
+    pub fn parse_list(s: &str) -> anyhow::Result<Vec<Self>> {
+        s.split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(|s| {
+                serde_json::from_value(serde_json::Value::String(s.to_string()))
+                    .map_err(|_| anyhow::anyhow!("unknown bundle section: {s}"))
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct SettingsBundle {
+    pub version: u32,
+    #[serde(default)]
+    pub exported_at_ms: i64,
+    /// By model, then name.
+    #[serde(default)]
+    pub presets: BTreeMap<String, BTreeMap<String, GeneratePreset>>,
+    #[serde(default)]
+    pub prompt_presets: BTreeMap<String, PromptPreset>,
+    #[serde(default)]
+    pub character_presets: BTreeMap<String, CharacterSlotPreset>,
+    #[serde(default)]
+    pub snippets: BTreeMap<String, PromptSnippet>,
+    #[serde(default)]
+    pub last_generation: Option<LastGenerationRecord>,
+}
+
+impl SettingsBundle {
+    pub fn check_version(&self) -> anyhow::Result<()> {
+        if self.version > SETTINGS_BUNDLE_VERSION {
+            anyhow::bail!(
+                "bundle version {} is newer than supported ({SETTINGS_BUNDLE_VERSION})",
+                self.version
+            );
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Default, serde::Serialize)]
+pub struct SettingsImportReport {
+    pub dry_run: bool,
+    /// Names are `model/name`.
+    pub presets: ImportReport,
+    pub prompt_presets: ImportReport,
+    pub character_presets: ImportReport,
+    pub snippets: ImportReport,
+    /// Named `last_generation`; never renamed, only overwritten.
+    pub last_generation: ImportReport,
+}
+
+impl SettingsImportReport {
+    /// Items created, overwritten or renamed in.
+    pub fn written(&self) -> usize {
+        [
+            &self.presets,
+            &self.prompt_presets,
+            &self.character_presets,
+            &self.snippets,
+            &self.last_generation,
+        ]
+        .iter()
+        .map(|r| r.created.len() + r.overwritten.len() + r.renamed.len())
+        .sum()
+    }
+}
+
+/// The stores a bundle is read from and written to.
+#[derive(Debug, Clone)]
+pub struct SettingsStores {
+    pub presets: PresetStore,
+    pub prompt_presets: PromptPresetStore,
+    pub character_presets: CharacterPresetStore,
+    pub prompt_snippets: PromptSnippetStore,
+    pub last_generation: LastGenerationStore,
+    /// The database behind them, to import in one transaction.
+    pub db: Database,
+}
+
+impl SettingsStores {
+    /// For use without the server, e.g. from the command line.
+    pub fn open(db: &Database) -> anyhow::Result<Self> {
+        Ok(Self {
+            presets: PresetStore::new(db.clone())?,
+            prompt_presets: PromptPresetStore::new(db.clone())?,
+            character_presets: CharacterPresetStore::new(db.clone())?,
+            last_generation: LastGenerationStore::new(db.clone())?,
+            prompt_snippets: PromptSnippetStore::new(db.clone())?,
+            db: db.clone(),
+        })
+    }
+
+    /// The `sections` of the current setup; all when empty.
+    pub async fn export(&self, sections: &[BundleSection]) -> anyhow::Result<SettingsBundle> {
+        let wanted = |s| sections.is_empty() || sections.contains(&s);
+        let mut bundle = SettingsBundle {
+            version: SETTINGS_BUNDLE_VERSION,
+            exported_at_ms: now_ms(),
+            ..Default::default()
+        };
+        if wanted(BundleSection::Presets) {
+            for model in self.presets.list_models().await? {
+                let mut presets = BTreeMap::new();
+                for name in self.presets.list_names(&model).await? {
+                    if let Some(preset) = self.presets.get(&model, &name).await? {
+                        presets.insert(name, preset);
+                    }
+                }
+                bundle.presets.insert(model, presets);
+            }
+        }
+        if wanted(BundleSection::PromptPresets) {
+            for name in self.prompt_presets.list_names().await? {
+                if let Some(preset) = self.prompt_presets.get(&name).await? {
+                    bundle.prompt_presets.insert(name, preset);
+                }
+            }
+        }
+        if wanted(BundleSection::CharacterPresets) {
+            for name in self.character_presets.list_names().await? {
+                if let Some(preset) = self.character_presets.get(&name).await? {
+                    bundle.character_presets.insert(name, preset);
+                }
+            }
+        }
+        if wanted(BundleSection::Snippets) {
+            bundle.snippets = self
+                .prompt_snippets
+                .snippets_in("")
+                .await?
+                .into_iter()
+                .collect();
+        }
+        if wanted(BundleSection::LastGeneration) {
+            bundle.last_generation = self.last_generation.get().await?;
+        }
+        Ok(bundle)
+    }
+
+    /// Merge the `sections` of `bundle` (all when empty) into the current
+    /// setup, names already taken resolved by `conflict`. Everything is
+    /// written in one transaction; nothing on a dry run. Presets and the last
+    /// generation follow the imported snippets renamed.
+    pub async fn import(
+        &self,
+        bundle: SettingsBundle,
+        sections: &[BundleSection],
+        conflict: ConflictStrategy,
+        dry_run: bool,
+    ) -> anyhow::Result<SettingsImportReport> {
+        bundle.check_version()?;
+        let wanted = |s| sections.is_empty() || sections.contains(&s);
+        let mut report = SettingsImportReport {
+            dry_run,
+            ..Default::default()
+        };
+
+        // Snippets first, so the rest know which were renamed.
+        let snippets = if wanted(BundleSection::Snippets) {
+            let existing: HashMap<String, PromptSnippet> = self
+                .prompt_snippets
+                .snippets_in("")
+                .await?
+                .into_iter()
+                .collect();
+            let mut plan = plan_snippet_import(
+                &existing,
+                bundle.snippets.into_iter().collect(),
+                conflict,
+                dry_run,
+            )?;
+            report.snippets = std::mem::take(&mut plan.report);
+            plan
+        } else {
+            SnippetImportPlan::default()
+        };
+
+        let mut preset_writes = Vec::new();
+        if wanted(BundleSection::Presets) {
+            report.presets.dry_run = dry_run;
+            for (model, presets) in bundle.presets {
+                let mut existing = HashMap::new();
+                for name in self.presets.list_names(&model).await? {
+                    if let Some(preset) = self.presets.get(&model, &name).await? {
+                        existing.insert(name, preset);
+                    }
+                }
+                let label = |name: &str| format!("{model}/{name}");
+                let presets = snippets.follow_renames(presets)?;
+                let writes = merge(&mut report.presets, &existing, presets, conflict, label)?;
+                for (name, preset) in writes {
+                    preset_writes.push((model.clone(), name, serde_json::to_string(&preset)?));
+                }
+            }
+        }
+
+        let mut prompt_preset_writes = Vec::new();
+        if wanted(BundleSection::PromptPresets) {
+            report.prompt_presets.dry_run = dry_run;
+            let mut existing = HashMap::new();
+            for name in self.prompt_presets.list_names().await? {
+                if let Some(preset) = self.prompt_presets.get(&name).await? {
+                    existing.insert(name, preset);
+                }
+            }
+            let writes = merge(
+                &mut report.prompt_presets,
+                &existing,
+                snippets.follow_renames(bundle.prompt_presets)?,
+                conflict,
+                str::to_string,
+            )?;
+            for (name, preset) in writes {
+                prompt_preset_writes.push((name, serde_json::to_string(&preset)?));
+            }
+        }
+
+        let mut character_preset_writes = Vec::new();
+        if wanted(BundleSection::CharacterPresets) {
+            report.character_presets.dry_run = dry_run;
+            let mut existing = HashMap::new();
+            for name in self.character_presets.list_names().await? {
+                if let Some(preset) = self.character_presets.get(&name).await? {
+                    existing.insert(name, preset);
+                }
+            }
+            let writes = merge(
+                &mut report.character_presets,
+                &existing,
+                snippets.follow_renames(bundle.character_presets)?,
+                conflict,
+                str::to_string,
+            )?;
+            for (name, preset) in writes {
+                character_preset_writes.push((name, serde_json::to_string(&preset)?));
+            }
+        }
+
+        let mut last_generation_write = None;
+        if wanted(BundleSection::LastGeneration)
+            && let Some(record) = bundle.last_generation
+        {
+            let record = snippets.follow_renames(record)?;
+            let section = &mut report.last_generation;
+            section.dry_run = dry_run;
+            let name = "last_generation".to_string();
+            let current = self.last_generation.get().await?;
+            let write = match current {
+                None => {
+                    section.created.push(name);
+                    true
+                }
+                Some(current) if same(&current.base, &record.base)? => {
+                    section.unchanged.push(name);
+                    false
+                }
+                Some(_) if conflict == ConflictStrategy::Overwrite => {
+                    section.overwritten.push(name);
+                    true
+                }
+                Some(_) => {
+                    section.skipped.push(name);
+                    false
+                }
+            };
+            if write {
+                last_generation_write = Some(record);
+            }
+        }
+
+        if !dry_run {
+            let snippet_writes = snippets.writes;
+            self.db
+                .with_conn_blocking("settings import", move |conn| {
+                    let tx = conn.transaction()?;
+                    for (model, name, json) in &preset_writes {
+                        write_preset_json(&tx, model, name, json)?;
+                    }
+                    let table = RevisionKind::PromptPreset.table();
+                    for (name, json) in &prompt_preset_writes {
+                        write_named_json(&tx, table, name, json)?;
+                    }
+                    let table = RevisionKind::CharacterPreset.table();
+                    for (name, json) in &character_preset_writes {
+                        write_named_json(&tx, table, name, json)?;
+                    }
+                    for (name, snippet) in snippet_writes {
+                        write_snippet(&tx, &name, snippet)?;
+                    }
+                    if let Some(record) = &last_generation_write {
+                        write_last_generation(&tx, record)?;
+                    }
+                    tx.commit()?;
+                    Ok(())
+                })
+                .await?;
+        }
+
+        Ok(report)
+    }
+}
+
+/// Sort `items` into `report` against `existing`, returning what to write.
+/// `label` names an item in the report.
+fn merge<T: Serialize>(
+    report: &mut ImportReport,
+    existing: &HashMap<String, T>,
+    items: BTreeMap<String, T>,
+    conflict: ConflictStrategy,
+    label: impl Fn(&str) -> String,
+) -> anyhow::Result<Vec<(String, T)>> {
+    // Renamed items mustn't take the name of another item, stored or imported.
+    let mut taken: HashSet<String> = existing.keys().chain(items.keys()).cloned().collect();
+    let mut writes = Vec::new();
+    for (name, item) in items {
+        let Some(current) = existing.get(&name) else {
+            report.created.push(label(&name));
+            writes.push((name, item));
+            continue;
+        };
+        if same(current, &item)? {
+            report.unchanged.push(label(&name));
+            continue;
+        }
+        match conflict {
+            ConflictStrategy::Skip => report.skipped.push(label(&name)),
+            ConflictStrategy::Overwrite => {
+                report.overwritten.push(label(&name));
+                writes.push((name, item));
+            }
+            ConflictStrategy::Rename => {
+                let to = (2..)
+                    .map(|n| format!("{name}_{n}"))
+                    .find(|n| !taken.contains(n))
+                    .expect("unbounded");
+                taken.insert(to.clone());
+                report.renamed.push(RenamedItem {
+                    from: label(&name),
+                    to: label(&to),
+                });
+                writes.push((to, item));
+            }
+        }
+    }
+    Ok(writes)
+}
+
+fn same<T: Serialize>(a: &T, b: &T) -> anyhow::Result<bool> {
+    Ok(serde_json::to_value(a)? == serde_json::to_value(b)?)
+}
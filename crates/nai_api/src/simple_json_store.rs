@@ -3,7 +3,11 @@ use std::marker::PhantomData;
 use anyhow::Context;
 use rusqlite::{Connection, OptionalExtension, params};
 
-use crate::{db::Database, last_generation::now_ms};
+use crate::{
+    db::Database,
+    last_generation::now_ms,
+    revision_store::{backfill_revisions, record_revision, rename_revisions},
+};
 
 #[derive(Debug, Clone)]
 pub(crate) struct NameJsonStore<T> {
@@ -65,11 +69,9 @@ where
         let preset_json = serde_json::to_string(preset).context("serialize preset")?;
         self.db
             .with_conn_blocking("upsert name-json", move |conn| {
-                let sql = format!(
-                    "INSERT INTO {table} (name, updated_at_ms, preset_json) VALUES (?1, ?2, ?3)\
-                     ON CONFLICT(name) DO UPDATE SET updated_at_ms=excluded.updated_at_ms, preset_json=excluded.preset_json"
-                );
-                conn.execute(&sql, params![name, now_ms(), preset_json])?;
+                let tx = conn.transaction()?;
+                write_named_json(&tx, table, &name, &preset_json)?;
+                tx.commit()?;
                 Ok(())
             })
             .await
@@ -80,8 +82,13 @@ where
         let name = name.to_string();
         self.db
             .with_conn_blocking("delete name-json", move |conn| {
+                let tx = conn.transaction()?;
                 let sql = format!("DELETE FROM {table} WHERE name = ?1");
-                let rows = conn.execute(&sql, params![name])?;
+                let rows = tx.execute(&sql, params![name])?;
+                if rows > 0 {
+                    record_revision(&tx, table, "", &name, None)?;
+                }
+                tx.commit()?;
                 Ok(rows > 0)
             })
             .await
@@ -114,6 +121,7 @@ where
                 let update_sql =
                     format!("UPDATE {table} SET name = ?2, updated_at_ms = ?3 WHERE name = ?1");
                 tx.execute(&update_sql, params![from, to, now_ms()])?;
+                rename_revisions(&tx, table, "", &from, &to)?;
                 tx.commit()?;
                 Ok(())
             })
@@ -133,5 +141,27 @@ fn init_name_table(conn: &Connection, table: &'static str) -> anyhow::Result<()>
     );
     conn.execute_batch(&sql)
         .with_context(|| format!("init {table} schema"))?;
+    backfill_revisions(
+        conn,
+        table,
+        &format!("SELECT '' AS scope, name, updated_at_ms, preset_json AS value_json FROM {table}"),
+    )?;
+    Ok(())
+}
+
+/// Insert or replace `name` in `table`, recording a revision. Runs inside the
+/// caller's transaction.
+pub(crate) fn write_named_json(
+    conn: &Connection,
+    table: &str,
+    name: &str,
+    preset_json: &str,
+) -> anyhow::Result<()> {
+    let sql = format!(
+        "INSERT INTO {table} (name, updated_at_ms, preset_json) VALUES (?1, ?2, ?3)\
+         ON CONFLICT(name) DO UPDATE SET updated_at_ms=excluded.updated_at_ms, preset_json=excluded.preset_json"
+    );
+    conn.execute(&sql, params![name, now_ms(), preset_json])?;
+    record_revision(conn, table, "", name, Some(preset_json))?;
     Ok(())
 }
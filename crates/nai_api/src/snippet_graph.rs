@@ -0,0 +1,463 @@
+//! Who refers to which snippet: other snippets, prompt and character presets
+//! and the last generation. Every string of their JSON is scanned, so new
+//! prompt fields are covered without listing them here.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+use anyhow::Context;
+use nai_core::prompt::snippet::{
+    resolve_snippet_name, rewrite_refs, snippet_folder, snippet_refs, spell_snippet_name,
+};
+use rusqlite::{Connection, OptionalExtension, params};
+use serde_json::Value;
+
+use crate::{last_generation::now_ms, revision_store::record_revision};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReferrerKind {
+    PromptSnippet,
+    PromptPreset,
+    CharacterPreset,
+    LastGeneration,
+}
+
+impl ReferrerKind {
+    /// Tables of named items referring to snippets.
+    const NAMED: [(ReferrerKind, &'static str); 3] = [
+        (ReferrerKind::PromptSnippet, "prompt_snippets"),
+        (ReferrerKind::PromptPreset, "prompt_presets"),
+        (ReferrerKind::CharacterPreset, "character_presets"),
+    ];
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, serde::Serialize)]
+pub struct SnippetReferrer {
+    pub kind: ReferrerKind,
+    /// Empty for the last generation.
+    pub name: String,
+}
+
+impl SnippetReferrer {
+    /// The folder its references are resolved against.
+    fn folder(&self) -> &str {
+        match self.kind {
+            ReferrerKind::PromptSnippet => snippet_folder(&self.name),
+            _ => "",
+        }
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct SnippetEdge {
+    pub from: SnippetReferrer,
+    pub to: String,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct SnippetGraph {
+    pub snippets: Vec<String>,
+    /// Edges point at full snippet names, relative references resolved.
+    pub edges: Vec<SnippetEdge>,
+    /// Referenced snippets that don't exist.
+    pub missing: Vec<String>,
+    /// Snippets referring to each other in a loop, each cycle sorted by name.
+    pub cycles: Vec<Vec<String>>,
+}
+
+pub(crate) fn build_graph(conn: &Connection) -> anyhow::Result<SnippetGraph> {
+    let mut referrers = Vec::new();
+    for (kind, table) in ReferrerKind::NAMED {
+        for (name, value) in load_named(conn, table)? {
+            referrers.push((SnippetReferrer { kind, name }, value));
+        }
+    }
+    if let Some(value) = last_generation_value(conn)? {
+        referrers.push((
+            SnippetReferrer {
+                kind: ReferrerKind::LastGeneration,
+                name: String::new(),
+            },
+            value,
+        ));
+    }
+    let snippets: BTreeSet<String> = referrers
+        .iter()
+        .filter(|(from, _)| from.kind == ReferrerKind::PromptSnippet)
+        .map(|(from, _)| from.name.clone())
+        .collect();
+
+    let mut edges = BTreeSet::new();
+    for (from, value) in referrers {
+        let folder = from.folder().to_string();
+        visit_strings(&value, &mut |s| {
+            for r in snippet_refs(s) {
+                let to = resolve_snippet_name(&folder, r.name, |n| snippets.contains(n))
+                    .unwrap_or_else(|| r.name.trim_start_matches('/').to_string());
+                edges.insert((from.clone(), to));
+            }
+        });
+    }
+
+    let missing = edges
+        .iter()
+        .map(|(_, to)| to)
+        .filter(|to| !snippets.contains(*to))
+        .cloned()
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect();
+
+    let mut adjacency: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+    for (from, to) in &edges {
+        if from.kind == ReferrerKind::PromptSnippet && snippets.contains(to) {
+            adjacency.entry(&from.name).or_default().push(to);
+        }
+    }
+    let cycles = find_cycles(&adjacency);
+
+    Ok(SnippetGraph {
+        snippets: snippets.into_iter().collect(),
+        edges: edges
+            .into_iter()
+            .map(|(from, to)| SnippetEdge { from, to })
+            .collect(),
+        missing,
+        cycles,
+    })
+}
+
+/// After snippets were renamed (`renames`, old to new name), point every
+/// reference that resolved to an old name at the new one, and keep the rest
+/// resolving as before. `before` is the set of snippet names before the
+/// renames. Revisions are recorded for the snippets and presets changed.
+/// Runs inside the caller's transaction.
+pub(crate) fn rewrite_references(
+    conn: &Connection,
+    before: &HashSet<String>,
+    renames: &HashMap<String, String>,
+) -> anyhow::Result<Vec<SnippetReferrer>> {
+    let old_names: HashMap<&str, &str> = renames
+        .iter()
+        .map(|(from, to)| (to.as_str(), from.as_str()))
+        .collect();
+    let renamed = SnippetRenames::new(before, renames);
+    rewrite_referrers(conn, &old_names, &|old_folder, new_folder, text| {
+        renamed.rewrite(old_folder, new_folder, text)
+    })
+}
+
+/// Snippet names before and after renames, to point references at the new
+/// names.
+pub(crate) struct SnippetRenames<'a> {
+    before: &'a HashSet<String>,
+    after: HashSet<&'a str>,
+    renames: &'a HashMap<String, String>,
+}
+
+impl<'a> SnippetRenames<'a> {
+    /// `before` is every name before the renames, `renames` old to new name.
+    pub(crate) fn new(before: &'a HashSet<String>, renames: &'a HashMap<String, String>) -> Self {
+        let after = before
+            .iter()
+            .map(|n| renames.get(n).unwrap_or(n).as_str())
+            .collect();
+        Self {
+            before,
+            after,
+            renames,
+        }
+    }
+
+    /// `text` of a referrer that moved from `old_folder` to `new_folder`,
+    /// with every reference that resolved to a renamed snippet pointing at
+    /// its new name; `None` when nothing changes.
+    pub(crate) fn rewrite(&self, old_folder: &str, new_folder: &str, text: &str) -> Option<String> {
+        rewrite_refs(text, |written| {
+            let old_target =
+                resolve_snippet_name(old_folder, written, |n| self.before.contains(n))?;
+            let new_target = self.renames.get(&old_target).cloned().unwrap_or(old_target);
+            if resolve_snippet_name(new_folder, written, |n| self.after.contains(n)).as_ref()
+                == Some(&new_target)
+            {
+                return None;
+            }
+            Some(spell_snippet_name(new_folder, &new_target, |n| {
+                self.after.contains(n)
+            }))
+        })
+    }
+
+    /// [`Self::rewrite`] for every string of `value`; whether any changed.
+    pub(crate) fn rewrite_value(
+        &self,
+        old_folder: &str,
+        new_folder: &str,
+        value: &mut Value,
+    ) -> bool {
+        rewrite_in_value(value, &|s| self.rewrite(old_folder, new_folder, s))
+    }
+}
+
+/// Before snippet arguments, everything between `<snippet:` and `>` was the
+/// name, so names with spaces were allowed. Point such tokens naming a
+/// snippet of `renames` (old to new name) at the new name, before the
+/// snippets themselves are renamed. Runs inside the caller's transaction.
+pub(crate) fn rewrite_legacy_references(
+    conn: &Connection,
+    renames: &HashMap<String, String>,
+) -> anyhow::Result<Vec<SnippetReferrer>> {
+    let before: HashSet<String> = load_named(conn, "prompt_snippets")?
+        .into_iter()
+        .map(|(name, _)| name)
+        .collect();
+    let after: HashSet<&str> = before
+        .iter()
+        .map(|n| renames.get(n).unwrap_or(n).as_str())
+        .collect();
+    rewrite_referrers(conn, &HashMap::new(), &|folder, _, text| {
+        rewrite_legacy_refs(text, |written| {
+            let target = resolve_snippet_name(folder, written, |n| before.contains(n))?;
+            let to = renames.get(&target)?;
+            Some(spell_snippet_name(folder, to, |n| after.contains(n)))
+        })
+    })
+}
+
+/// Apply `rewrite(old_folder, new_folder, text)` to every string of every
+/// referrer, saving those changed. Snippets renamed in `old_names` (new to
+/// old name) get their old folder; everything else has the same folder
+/// before and after, the root outside snippets.
+fn rewrite_referrers(
+    conn: &Connection,
+    old_names: &HashMap<&str, &str>,
+    rewrite: &dyn Fn(&str, &str, &str) -> Option<String>,
+) -> anyhow::Result<Vec<SnippetReferrer>> {
+    let mut updated = Vec::new();
+    for (kind, table) in ReferrerKind::NAMED {
+        for (name, mut value) in load_named(conn, table)? {
+            let (old_folder, new_folder) = if kind == ReferrerKind::PromptSnippet {
+                let old = old_names.get(name.as_str()).copied().unwrap_or(&name);
+                (snippet_folder(old), snippet_folder(&name))
+            } else {
+                ("", "")
+            };
+            if !rewrite_in_value(&mut value, &|s| rewrite(old_folder, new_folder, s)) {
+                continue;
+            }
+            let json = serde_json::to_string(&value)?;
+            conn.execute(
+                &format!("UPDATE {table} SET updated_at_ms = ?2, preset_json = ?3 WHERE name = ?1"),
+                params![name, now_ms(), json],
+            )?;
+            record_revision(conn, table, "", &name, Some(&json))?;
+            updated.push(SnippetReferrer { kind, name });
+        }
+    }
+
+    if let Some((base_json, positive, negative, characters_json)) = last_generation_row(conn)? {
+        let root = |s: &str| rewrite("", "", s);
+        let mut base: Value = serde_json::from_str(&base_json).context("parse last generation")?;
+        let mut characters: Value =
+            serde_json::from_str(&characters_json).context("parse last generation")?;
+        let base_changed = rewrite_in_value(&mut base, &root);
+        let characters_changed = rewrite_in_value(&mut characters, &root);
+        let positive_new = root(&positive);
+        let negative_new = root(&negative);
+        if base_changed || characters_changed || positive_new.is_some() || negative_new.is_some() {
+            conn.execute(
+                "UPDATE last_generation SET base_json = ?1, positive = ?2, negative = ?3, \
+                 character_prompts_json = ?4 WHERE id = 1",
+                params![
+                    serde_json::to_string(&base)?,
+                    positive_new.unwrap_or(positive),
+                    negative_new.unwrap_or(negative),
+                    serde_json::to_string(&characters)?,
+                ],
+            )?;
+            updated.push(SnippetReferrer {
+                kind: ReferrerKind::LastGeneration,
+                name: String::new(),
+            });
+        }
+    }
+    Ok(updated)
+}
+
+/// Every row of a snippet or preset table, parsed.
+fn load_named(conn: &Connection, table: &str) -> anyhow::Result<Vec<(String, Value)>> {
+    let mut stmt = conn.prepare(&format!("SELECT name, preset_json FROM {table}"))?;
+    let rows = stmt
+        .query_map([], |r| Ok((r.get::<_, String>(0)?, r.get::<_, String>(1)?)))?
+        .collect::<Result<Vec<_>, _>>()?;
+    rows.into_iter()
+        .map(|(name, json)| {
+            let value =
+                serde_json::from_str(&json).with_context(|| format!("parse {table} {name}"))?;
+            Ok((name, value))
+        })
+        .collect()
+}
+
+fn last_generation_value(conn: &Connection) -> anyhow::Result<Option<Value>> {
+    let Some((base_json, positive, negative, characters_json)) = last_generation_row(conn)? else {
+        return Ok(None);
+    };
+    Ok(Some(Value::Array(vec![
+        serde_json::from_str(&base_json).context("parse last generation")?,
+        Value::String(positive),
+        Value::String(negative),
+        serde_json::from_str(&characters_json).context("parse last generation")?,
+    ])))
+}
+
+/// `base_json`, `positive`, `negative` and `character_prompts_json`.
+fn last_generation_row(
+    conn: &Connection,
+) -> anyhow::Result<Option<(String, String, String, String)>> {
+    Ok(conn
+        .query_row(
+            "SELECT base_json, positive, negative, character_prompts_json \
+             FROM last_generation WHERE id = 1",
+            [],
+            |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?, r.get(3)?)),
+        )
+        .optional()?)
+}
+
+fn visit_strings(value: &Value, f: &mut impl FnMut(&str)) {
+    match value {
+        Value::String(s) => f(s),
+        Value::Array(items) => items.iter().for_each(|v| visit_strings(v, f)),
+        Value::Object(map) => map.values().for_each(|v| visit_strings(v, f)),
+        _ => {}
+    }
+}
+
+/// `<snippet:...>` tokens as the old grammar read them, the trimmed text up
+/// to the first `>` being the name; those `rename` gives a new name for are
+/// written again as `<snippet:new>`.
+fn rewrite_legacy_refs(text: &str, rename: impl Fn(&str) -> Option<String>) -> Option<String> {
+    let mut out = String::with_capacity(text.len());
+    let mut last = 0;
+    let mut i = 0;
+    while let Some(off) = text[i..].find('<') {
+        let start = i + off;
+        i = start + 1;
+        let Some(after) = text[start + 1..].trim_start().strip_prefix("snippet:") else {
+            continue;
+        };
+        let Some(close) = after.find('>') else {
+            continue;
+        };
+        let Some(to) = Some(after[..close].trim())
+            .filter(|inner| !inner.is_empty())
+            .and_then(&rename)
+        else {
+            continue;
+        };
+        let end = text.len() - after.len() + close + 1;
+        out.push_str(&text[last..start]);
+        out.push_str(&format!("<snippet:{to}>"));
+        last = end;
+        i = end;
+    }
+    if last == 0 {
+        return None;
+    }
+    out.push_str(&text[last..]);
+    Some(out)
+}
+
+fn rewrite_in_value(value: &mut Value, rewrite: &dyn Fn(&str) -> Option<String>) -> bool {
+    match value {
+        Value::String(s) => match rewrite(s) {
+            Some(renamed) => {
+                *s = renamed;
+                true
+            }
+            None => false,
+        },
+        Value::Array(items) => {
+            let mut changed = false;
+            for v in items.iter_mut() {
+                changed |= rewrite_in_value(v, rewrite);
+            }
+            changed
+        }
+        Value::Object(map) => {
+            let mut changed = false;
+            for v in map.values_mut() {
+                changed |= rewrite_in_value(v, rewrite);
+            }
+            changed
+        }
+        _ => false,
+    }
+}
+
+/// Strongly connected components with more than one snippet, plus snippets
+/// referring to themselves (Tarjan).
+fn find_cycles(adjacency: &BTreeMap<&str, Vec<&str>>) -> Vec<Vec<String>> {
+    struct Tarjan<'a, 'g> {
+        adjacency: &'g BTreeMap<&'a str, Vec<&'a str>>,
+        next: usize,
+        index: BTreeMap<&'a str, (usize, usize)>,
+        stack: Vec<&'a str>,
+        on_stack: BTreeSet<&'a str>,
+        cycles: Vec<Vec<String>>,
+    }
+
+    impl<'a> Tarjan<'a, '_> {
+        fn visit(&mut self, v: &'a str) {
+            self.index.insert(v, (self.next, self.next));
+            self.next += 1;
+            self.stack.push(v);
+            self.on_stack.insert(v);
+            let adjacency = self.adjacency;
+            for &w in adjacency.get(v).into_iter().flatten() {
+                if !self.index.contains_key(w) {
+                    self.visit(w);
+                    let low = self.index[w].1.min(self.index[v].1);
+                    self.index.get_mut(v).unwrap().1 = low;
+                } else if self.on_stack.contains(w) {
+                    let low = self.index[w].0.min(self.index[v].1);
+                    self.index.get_mut(v).unwrap().1 = low;
+                }
+            }
+            let (index, low) = self.index[v];
+            if index != low {
+                return;
+            }
+            let mut component = Vec::new();
+            while let Some(w) = self.stack.pop() {
+                self.on_stack.remove(w);
+                component.push(w.to_string());
+                if w == v {
+                    break;
+                }
+            }
+            let self_loop = adjacency.get(v).is_some_and(|ws| ws.contains(&v));
+            if component.len() > 1 || self_loop {
+                component.sort();
+                self.cycles.push(component);
+            }
+        }
+    }
+
+    let mut t = Tarjan {
+        adjacency,
+        next: 0,
+        index: BTreeMap::new(),
+        stack: Vec::new(),
+        on_stack: BTreeSet::new(),
+        cycles: Vec::new(),
+    };
+    for &v in adjacency.keys() {
+        if !t.index.contains_key(v) {
+            t.visit(v);
+        }
+    }
+    t.cycles.sort();
+    t.cycles
+}
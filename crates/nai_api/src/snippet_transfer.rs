@@ -0,0 +1,473 @@
+//! Bulk import and export of snippets: our JSON/YAML bundle, A1111/Forge
+//! `styles.csv` and a zip of `.txt` files (`char/alice.txt` is `char/alice`).
+
+use std::{
+    collections::{HashMap, HashSet},
+    io::{Cursor, Read, Write},
+};
+
+use anyhow::Context;
+use nai_core::prompt::snippet::{check_snippet_name, snippet_folder};
+use zip::{CompressionMethod, ZipArchive, ZipWriter, write::SimpleFileOptions};
+
+use crate::{
+    prompt_snippet_store::{PromptSnippet, PromptSnippetStore, SnippetParam, normalize_tags},
+    snippet_graph::SnippetRenames,
+};
+
+const BUNDLE_VERSION: u32 = 1;
+
+/// A1111 styles put the user's prompt where `{prompt}` is; imported styles
+/// take it as this argument.
+const STYLE_PROMPT_PARAM: &str = "prompt";
+/// A style's negative prompt becomes this snippet under the style's name.
+const STYLE_NEGATIVE: &str = "negative";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SnippetFormat {
+    Json,
+    Yaml,
+    /// `name,prompt,negative_prompt`
+    StylesCsv,
+    /// Bodies only; tags, descriptions and parameters are not kept.
+    TextZip,
+}
+
+impl SnippetFormat {
+    pub fn content_type(self) -> &'static str {
+        match self {
+            SnippetFormat::Json => "application/json",
+            SnippetFormat::Yaml => "application/yaml",
+            SnippetFormat::StylesCsv => "text/csv; charset=utf-8",
+            SnippetFormat::TextZip => "application/zip",
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            SnippetFormat::Json => "json",
+            SnippetFormat::Yaml => "yaml",
+            SnippetFormat::StylesCsv => "csv",
+            SnippetFormat::TextZip => "zip",
+        }
+    }
+}
+
+/// What to do with an imported item whose name is taken.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictStrategy {
+    #[default]
+    Skip,
+    Overwrite,
+    /// Import as `name_2`, `name_3`, ...
+    Rename,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct SnippetBundle {
+    pub version: u32,
+    pub snippets: Vec<BundleSnippet>,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct BundleSnippet {
+    pub name: String,
+    #[serde(flatten)]
+    pub snippet: PromptSnippet,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct RenamedItem {
+    pub from: String,
+    pub to: String,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct RejectedItem {
+    pub name: String,
+    pub error: String,
+}
+
+/// What an import did, or would do on a dry run, per item name.
+#[derive(Debug, Clone, Default, serde::Serialize)]
+pub struct ImportReport {
+    pub dry_run: bool,
+    pub created: Vec<String>,
+    pub overwritten: Vec<String>,
+    pub renamed: Vec<RenamedItem>,
+    /// Taken names left alone under the skip strategy.
+    pub skipped: Vec<String>,
+    /// Identical to the item already stored.
+    pub unchanged: Vec<String>,
+    pub rejected: Vec<RejectedItem>,
+}
+
+/// Snippets in `bytes`, named under `folder` (the root when empty).
+pub fn parse_snippets(
+    format: SnippetFormat,
+    bytes: &[u8],
+    folder: &str,
+) -> anyhow::Result<Vec<(String, PromptSnippet)>> {
+    let items = match format {
+        SnippetFormat::Json => {
+            parse_bundle(serde_json::from_slice(bytes).context("parse JSON bundle")?)?
+        }
+        SnippetFormat::Yaml => {
+            parse_bundle(serde_yaml::from_slice(bytes).context("parse YAML bundle")?)?
+        }
+        SnippetFormat::StylesCsv => parse_styles_csv(bytes)?,
+        SnippetFormat::TextZip => parse_text_zip(bytes)?,
+    };
+    let folder = folder.trim_matches('/');
+    Ok(items
+        .into_iter()
+        .map(|(name, snippet)| {
+            let name = if folder.is_empty() {
+                name
+            } else {
+                format!("{folder}/{name}")
+            };
+            (name, snippet)
+        })
+        .collect())
+}
+
+fn parse_bundle(bundle: SnippetBundle) -> anyhow::Result<Vec<(String, PromptSnippet)>> {
+    if bundle.version > BUNDLE_VERSION {
+        anyhow::bail!(
+            "bundle version {} is newer than supported ({BUNDLE_VERSION})",
+            bundle.version
+        );
+    }
+    Ok(bundle
+        .snippets
+        .into_iter()
+        .map(|s| (s.name, s.snippet))
+        .collect())
+}
+
+fn parse_styles_csv(bytes: &[u8]) -> anyhow::Result<Vec<(String, PromptSnippet)>> {
+    let mut reader = csv::ReaderBuilder::new().flexible(true).from_reader(bytes);
+    let headers = reader.headers().context("read styles.csv header")?.clone();
+    let column = |name: &str| headers.iter().position(|h| h.trim() == name);
+    let name_col = column("name").context("styles.csv has no name column")?;
+    let prompt_col = column("prompt").context("styles.csv has no prompt column")?;
+    let negative_col = column("negative_prompt");
+
+    let mut out = Vec::new();
+    for record in reader.records() {
+        let record = record.context("read styles.csv")?;
+        let name = style_name(record.get(name_col).unwrap_or_default());
+        if name.is_empty() {
+            continue;
+        }
+        out.push((
+            name.clone(),
+            style_snippet(record.get(prompt_col).unwrap_or_default()),
+        ));
+        let negative = negative_col.and_then(|c| record.get(c)).unwrap_or_default();
+        if !negative.trim().is_empty() {
+            out.push((format!("{name}/{STYLE_NEGATIVE}"), style_snippet(negative)));
+        }
+    }
+    Ok(out)
+}
+
+/// Style names are free text; snippet names can't have whitespace or
+/// brackets, and a `/` would start a folder.
+fn style_name(name: &str) -> String {
+    name.split_whitespace()
+        .collect::<Vec<_>>()
+        .join("_")
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' => '_',
+            '<' | '>' | '"' | '\'' => '_',
+            c => c,
+        })
+        .collect()
+}
+
+fn style_snippet(prompt: &str) -> PromptSnippet {
+    let has_placeholder = prompt.contains("{prompt}");
+    PromptSnippet {
+        body: prompt.replace("{prompt}", &format!("${{{STYLE_PROMPT_PARAM}}}")),
+        tags: vec!["style".to_string()],
+        description: None,
+        params: if has_placeholder {
+            vec![SnippetParam {
+                name: STYLE_PROMPT_PARAM.to_string(),
+                default: Some(String::new()),
+            }]
+        } else {
+            Vec::new()
+        },
+    }
+}
+
+fn parse_text_zip(bytes: &[u8]) -> anyhow::Result<Vec<(String, PromptSnippet)>> {
+    let mut archive = ZipArchive::new(Cursor::new(bytes)).context("open zip")?;
+    let mut out = Vec::new();
+    for i in 0..archive.len() {
+        let mut file = archive.by_index(i)?;
+        if file.is_dir() {
+            continue;
+        }
+        let path = file.name().replace('\\', "/");
+        let Some(name) = path.strip_suffix(".txt") else {
+            continue;
+        };
+        let name = name.trim_start_matches('/').to_string();
+        let mut body = String::new();
+        file.read_to_string(&mut body)
+            .with_context(|| format!("read {path}"))?;
+        out.push((
+            name,
+            PromptSnippet {
+                body: body.trim_end().to_string(),
+                ..Default::default()
+            },
+        ));
+    }
+    Ok(out)
+}
+
+/// Write `items` to `store` in one transaction (unless `dry_run`),
+/// reporting what happened to each.
+pub async fn import_snippets(
+    store: &PromptSnippetStore,
+    items: Vec<(String, PromptSnippet)>,
+    conflict: ConflictStrategy,
+    dry_run: bool,
+) -> anyhow::Result<ImportReport> {
+    let existing: HashMap<String, PromptSnippet> =
+        store.snippets_in("").await?.into_iter().collect();
+    let plan = plan_snippet_import(&existing, items, conflict, dry_run)?;
+    if !dry_run {
+        store.upsert_many(plan.writes).await?;
+    }
+    Ok(plan.report)
+}
+
+/// What a snippet import does.
+#[derive(Debug, Default)]
+pub(crate) struct SnippetImportPlan {
+    pub report: ImportReport,
+    pub writes: Vec<(String, PromptSnippet)>,
+    /// Every name imported.
+    imported: HashSet<String>,
+    /// Imported snippets written under another name, old to new name.
+    renames: HashMap<String, String>,
+}
+
+impl SnippetImportPlan {
+    /// Point references to renamed snippets in the strings of `item`, such as
+    /// imported presets or a generation, at the new names.
+    pub fn follow_renames<T>(&self, item: T) -> anyhow::Result<T>
+    where
+        T: serde::Serialize + serde::de::DeserializeOwned,
+    {
+        if self.renames.is_empty() {
+            return Ok(item);
+        }
+        let mut value = serde_json::to_value(&item)?;
+        if !SnippetRenames::new(&self.imported, &self.renames).rewrite_value("", "", &mut value) {
+            return Ok(item);
+        }
+        Ok(serde_json::from_value(value)?)
+    }
+}
+
+/// Sort `items` into a report against the `existing` snippets, with what to
+/// write. Tags are normalized first, so re-importing an export is a no-op.
+/// References between the imported snippets follow those written under a
+/// new name.
+pub(crate) fn plan_snippet_import(
+    existing: &HashMap<String, PromptSnippet>,
+    items: Vec<(String, PromptSnippet)>,
+    conflict: ConflictStrategy,
+    dry_run: bool,
+) -> anyhow::Result<SnippetImportPlan> {
+    let mut report = ImportReport {
+        dry_run,
+        ..Default::default()
+    };
+    // Names written by this import, so duplicates in it conflict too.
+    let mut taken: HashSet<String> = existing.keys().cloned().collect();
+    // (original name, name written, snippet)
+    let mut writes = Vec::new();
+    let imported: HashSet<String> = items.iter().map(|(name, _)| name.clone()).collect();
+
+    // Imported snippets renamed or skipped, by original name; snippets below
+    // them (a style's `negative`) follow them.
+    let mut moved: Vec<(String, Option<String>)> = Vec::new();
+
+    for (original, mut snippet) in items {
+        if let Err(e) = check_snippet_name(&original) {
+            report.rejected.push(RejectedItem {
+                name: original,
+                error: e.to_string(),
+            });
+            continue;
+        }
+        snippet.tags = normalize_tags(snippet.tags.iter().map(|s| s.as_str()));
+
+        let parent = moved
+            .iter()
+            .filter(|(from, _)| {
+                original
+                    .strip_prefix(from.as_str())
+                    .is_some_and(|rest| rest.starts_with('/'))
+            })
+            .max_by_key(|(from, _)| from.len());
+        let name = match parent {
+            Some((_, None)) => {
+                report.skipped.push(original.clone());
+                moved.push((original, None));
+                continue;
+            }
+            Some((from, Some(to))) => format!("{to}{}", &original[from.len()..]),
+            None => original.clone(),
+        };
+
+        if !taken.contains(&name) {
+            taken.insert(name.clone());
+            if name == original {
+                report.created.push(name.clone());
+            } else {
+                report.renamed.push(RenamedItem {
+                    from: original.clone(),
+                    to: name.clone(),
+                });
+                moved.push((original.clone(), Some(name.clone())));
+            }
+            writes.push((original, name, snippet));
+            continue;
+        }
+        if existing.get(&name) == Some(&snippet) {
+            report.unchanged.push(name);
+            continue;
+        }
+        match conflict {
+            ConflictStrategy::Skip => {
+                report.skipped.push(name);
+                moved.push((original, None));
+            }
+            ConflictStrategy::Overwrite => {
+                report.overwritten.push(name.clone());
+                writes.push((original, name, snippet));
+            }
+            ConflictStrategy::Rename => {
+                let to = (2..)
+                    .map(|n| format!("{name}_{n}"))
+                    .find(|n| !taken.contains(n))
+                    .expect("unbounded");
+                taken.insert(to.clone());
+                report.renamed.push(RenamedItem {
+                    from: original.clone(),
+                    to: to.clone(),
+                });
+                moved.push((original.clone(), Some(to.clone())));
+                writes.push((original, to, snippet));
+            }
+        }
+    }
+
+    // A name imported twice keeps pointing at the item written under it.
+    let kept: HashSet<&String> = writes
+        .iter()
+        .filter(|(original, name, _)| original == name)
+        .map(|(original, _, _)| original)
+        .collect();
+    let renames: HashMap<String, String> = report
+        .renamed
+        .iter()
+        .filter(|r| !kept.contains(&r.from))
+        .map(|r| (r.from.clone(), r.to.clone()))
+        .collect();
+    let renamed = SnippetRenames::new(&imported, &renames);
+    let writes = writes
+        .into_iter()
+        .map(|(original, name, snippet)| {
+            if renames.is_empty() {
+                return Ok((name, snippet));
+            }
+            let mut value = serde_json::to_value(&snippet)?;
+            if !renamed.rewrite_value(snippet_folder(&original), snippet_folder(&name), &mut value)
+            {
+                return Ok((name, snippet));
+            }
+            Ok((name, serde_json::from_value(value)?))
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    Ok(SnippetImportPlan {
+        report,
+        writes,
+        imported,
+        renames,
+    })
+}
+
+/// `snippets` in `format`.
+pub fn export_snippets(
+    format: SnippetFormat,
+    snippets: Vec<(String, PromptSnippet)>,
+) -> anyhow::Result<Vec<u8>> {
+    match format {
+        SnippetFormat::Json => Ok(serde_json::to_vec_pretty(&bundle(snippets))?),
+        SnippetFormat::Yaml => Ok(serde_yaml::to_string(&bundle(snippets))?.into_bytes()),
+        SnippetFormat::StylesCsv => export_styles_csv(snippets),
+        SnippetFormat::TextZip => export_text_zip(snippets),
+    }
+}
+
+fn bundle(snippets: Vec<(String, PromptSnippet)>) -> SnippetBundle {
+    SnippetBundle {
+        version: BUNDLE_VERSION,
+        snippets: snippets
+            .into_iter()
+            .map(|(name, snippet)| BundleSnippet { name, snippet })
+            .collect(),
+    }
+}
+
+/// The reverse of import: `x/negative` becomes the negative prompt of `x`.
+fn export_styles_csv(snippets: Vec<(String, PromptSnippet)>) -> anyhow::Result<Vec<u8>> {
+    let bodies: HashMap<&str, &str> = snippets
+        .iter()
+        .map(|(name, s)| (name.as_str(), s.body.as_str()))
+        .collect();
+    let placeholder = format!("${{{STYLE_PROMPT_PARAM}}}");
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    writer.write_record(["name", "prompt", "negative_prompt"])?;
+    for (name, snippet) in &snippets {
+        if let Some(style) = name.strip_suffix(&format!("/{STYLE_NEGATIVE}"))
+            && bodies.contains_key(style)
+        {
+            continue;
+        }
+        let negative = bodies
+            .get(format!("{name}/{STYLE_NEGATIVE}").as_str())
+            .copied()
+            .unwrap_or_default();
+        writer.write_record([
+            name.as_str(),
+            &snippet.body.replace(&placeholder, "{prompt}"),
+            &negative.replace(&placeholder, "{prompt}"),
+        ])?;
+    }
+    writer.into_inner().context("write styles.csv")
+}
+
+fn export_text_zip(snippets: Vec<(String, PromptSnippet)>) -> anyhow::Result<Vec<u8>> {
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    for (name, snippet) in snippets {
+        zip.start_file(format!("{name}.txt"), options)?;
+        zip.write_all(snippet.body.as_bytes())?;
+        zip.write_all(b"\n")?;
+    }
+    Ok(zip.finish()?.into_inner())
+}
@@ -0,0 +1,414 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use anyhow::Context;
+use rusqlite::{Connection, OptionalExtension, params};
+
+use nai_core::{
+    dto::BaseGenerateRequest,
+    prompt::{
+        ast::Prompt,
+        tags::{danbooru_name, replace_tags},
+    },
+};
+
+use crate::db::Database;
+
+/// Suggestions a completion returns at most.
+const MAX_COMPLETIONS: usize = 100;
+
+/// How a suggestion matched the query.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TagMatch {
+    /// The name starts with the query.
+    Prefix,
+    /// An alias starts with the query.
+    Alias,
+    /// The query's characters appear in the name in order.
+    Fuzzy,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct TagSuggestion {
+    pub name: String,
+    /// Danbooru category: 0 general, 1 artist, 3 copyright, 4 character, 5 meta.
+    pub category: i64,
+    pub post_count: i64,
+    /// The alias that matched, for `alias` matches.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub alias: Option<String>,
+    #[serde(rename = "match")]
+    pub matched: TagMatch,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct TagInfo {
+    pub name: String,
+    pub category: i64,
+    pub post_count: i64,
+    pub aliases: Vec<String>,
+    /// Tags this one implies, directly or through others.
+    pub implies: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, serde::Serialize)]
+pub struct TagImportReport {
+    pub tags: usize,
+    pub aliases: usize,
+    pub implications: usize,
+    /// Rows that couldn't be read or name unknown tags.
+    pub skipped: usize,
+}
+
+/// Danbooru tags for prompt autocomplete and alias normalisation, imported from
+/// CSV dumps. Names are stored in Danbooru's form, `long_hair`.
+#[derive(Debug, Clone)]
+pub struct TagStore {
+    db: Database,
+}
+
+impl TagStore {
+    pub fn new(db: Database) -> anyhow::Result<Self> {
+        db.with_conn(Self::init_schema)?;
+        Ok(Self { db })
+    }
+
+    /// Replace the tags and aliases with a `name,category,post_count,"alias,alias"`
+    /// CSV, the format of Danbooru tag lists for autocomplete extensions. A header
+    /// row is skipped. Implications of tags no longer listed are dropped.
+    pub async fn import_tags(&self, csv: String) -> anyhow::Result<TagImportReport> {
+        self.db
+            .with_conn_blocking("tag import", move |conn| {
+                let tx = conn.transaction()?;
+                tx.execute_batch("DELETE FROM tag_aliases; DELETE FROM tags;")?;
+                let mut report = TagImportReport::default();
+                {
+                    let mut insert_tag = tx.prepare(
+                        "INSERT OR REPLACE INTO tags (name, category, post_count) VALUES (?1, ?2, ?3)",
+                    )?;
+                    let mut insert_alias = tx.prepare(
+                        "INSERT OR IGNORE INTO tag_aliases (alias, name) VALUES (?1, ?2)",
+                    )?;
+                    for (i, record) in csv_reader(&csv).records().enumerate() {
+                        let Ok(record) = record else {
+                            report.skipped += 1;
+                            continue;
+                        };
+                        let name = danbooru_name(record.get(0).unwrap_or_default());
+                        let category = record.get(1).and_then(|v| v.trim().parse::<i64>().ok());
+                        let post_count = record.get(2).and_then(|v| v.trim().parse::<i64>().ok());
+                        let (Some(category), Some(post_count)) = (category, post_count) else {
+                            // Header
+                            if i > 0 {
+                                report.skipped += 1;
+                            }
+                            continue;
+                        };
+                        if name.is_empty() {
+                            report.skipped += 1;
+                            continue;
+                        }
+                        insert_tag.execute(params![name, category, post_count])?;
+                        for alias in record.get(3).unwrap_or_default().split(',') {
+                            let alias = danbooru_name(alias);
+                            if !alias.is_empty() && alias != name {
+                                insert_alias.execute(params![alias, name])?;
+                            }
+                        }
+                    }
+                }
+                // A tag's own name is never an alias of another.
+                tx.execute(
+                    "DELETE FROM tag_aliases WHERE alias IN (SELECT name FROM tags)",
+                    [],
+                )?;
+                tx.execute(
+                    "DELETE FROM tag_implications \
+                     WHERE antecedent NOT IN (SELECT name FROM tags) \
+                        OR consequent NOT IN (SELECT name FROM tags)",
+                    [],
+                )?;
+                report.tags = count_rows(&tx, "tags")?;
+                report.aliases = count_rows(&tx, "tag_aliases")?;
+                report.implications = count_rows(&tx, "tag_implications")?;
+                tx.commit()?;
+                Ok(report)
+            })
+            .await
+    }
+
+    /// Replace the implications with an `antecedent,consequent` CSV. Rows naming
+    /// tags that aren't imported, such as a header, are skipped.
+    pub async fn import_implications(&self, csv: String) -> anyhow::Result<TagImportReport> {
+        self.db
+            .with_conn_blocking("tag implication import", move |conn| {
+                let tx = conn.transaction()?;
+                tx.execute("DELETE FROM tag_implications", [])?;
+                let mut report = TagImportReport::default();
+                {
+                    let mut insert = tx.prepare(
+                        "INSERT OR IGNORE INTO tag_implications (antecedent, consequent) \
+                         SELECT ?1, ?2 \
+                         WHERE EXISTS (SELECT 1 FROM tags WHERE name = ?1) \
+                           AND EXISTS (SELECT 1 FROM tags WHERE name = ?2)",
+                    )?;
+                    for record in csv_reader(&csv).records() {
+                        let Ok(record) = record else {
+                            report.skipped += 1;
+                            continue;
+                        };
+                        let antecedent = danbooru_name(record.get(0).unwrap_or_default());
+                        let consequent = danbooru_name(record.get(1).unwrap_or_default());
+                        if antecedent == consequent
+                            || insert.execute(params![antecedent, consequent])? == 0
+                        {
+                            report.skipped += 1;
+                        }
+                    }
+                }
+                report.tags = count_rows(&tx, "tags")?;
+                report.aliases = count_rows(&tx, "tag_aliases")?;
+                report.implications = count_rows(&tx, "tag_implications")?;
+                tx.commit()?;
+                Ok(report)
+            })
+            .await
+    }
+
+    /// Tags for a partly typed `query`, most used first: names and aliases
+    /// starting with it, then fuzzy matches when those run short.
+    pub async fn complete(&self, query: &str, limit: usize) -> anyhow::Result<Vec<TagSuggestion>> {
+        let key = danbooru_name(query);
+        let limit = limit.clamp(1, MAX_COMPLETIONS);
+        if key.is_empty() {
+            return Ok(Vec::new());
+        }
+        self.db
+            .with_conn_blocking("tag complete", move |conn| {
+                let prefix = format!("{}%", escape_like(&key));
+                let mut out = Vec::new();
+                let mut stmt = conn.prepare(
+                    "SELECT name, category, post_count FROM tags \
+                     WHERE name LIKE ?1 ESCAPE '\\' ORDER BY post_count DESC LIMIT ?2",
+                )?;
+                let rows = stmt.query_map(params![prefix, limit as i64], |r| {
+                    Ok(TagSuggestion {
+                        name: r.get(0)?,
+                        category: r.get(1)?,
+                        post_count: r.get(2)?,
+                        alias: None,
+                        matched: TagMatch::Prefix,
+                    })
+                })?;
+                for row in rows {
+                    out.push(row?);
+                }
+                let mut stmt = conn.prepare(
+                    "SELECT t.name, t.category, t.post_count, a.alias \
+                     FROM tag_aliases a JOIN tags t ON t.name = a.name \
+                     WHERE a.alias LIKE ?1 ESCAPE '\\' ORDER BY t.post_count DESC LIMIT ?2",
+                )?;
+                let rows = stmt.query_map(params![prefix, limit as i64], |r| {
+                    Ok(TagSuggestion {
+                        name: r.get(0)?,
+                        category: r.get(1)?,
+                        post_count: r.get(2)?,
+                        alias: Some(r.get(3)?),
+                        matched: TagMatch::Alias,
+                    })
+                })?;
+                for row in rows {
+                    out.push(row?);
+                }
+                // Stable, so a name match stays ahead of an alias match of the same tag.
+                out.sort_by_key(|s| std::cmp::Reverse(s.post_count));
+                let mut seen = HashSet::new();
+                out.retain(|s| seen.insert(s.name.clone()));
+                out.truncate(limit);
+
+                if out.len() < limit && key.chars().count() > 1 {
+                    let pattern: String = key
+                        .chars()
+                        .map(|c| escape_like(&c.to_string()))
+                        .fold(String::from("%"), |acc, c| acc + &c + "%");
+                    let mut stmt = conn.prepare(
+                        "SELECT name, category, post_count FROM tags \
+                         WHERE name LIKE ?1 ESCAPE '\\' ORDER BY post_count DESC LIMIT ?2",
+                    )?;
+                    let rows = stmt.query_map(params![pattern, limit as i64 * 2], |r| {
+                        Ok(TagSuggestion {
+                            name: r.get(0)?,
+                            category: r.get(1)?,
+                            post_count: r.get(2)?,
+                            alias: None,
+                            matched: TagMatch::Fuzzy,
+                        })
+                    })?;
+                    for row in rows {
+                        let row = row?;
+                        if out.len() < limit && seen.insert(row.name.clone()) {
+                            out.push(row);
+                        }
+                    }
+                }
+                Ok(out)
+            })
+            .await
+    }
+
+    /// A tag by name or alias.
+    pub async fn get(&self, name: &str) -> anyhow::Result<Option<TagInfo>> {
+        let key = danbooru_name(name);
+        self.db
+            .with_conn_blocking("tag get", move |conn| {
+                let name = resolve(conn, &key)?.unwrap_or(key);
+                let row = conn
+                    .query_row(
+                        "SELECT category, post_count FROM tags WHERE name = ?1",
+                        params![name],
+                        |r| Ok((r.get::<_, i64>(0)?, r.get::<_, i64>(1)?)),
+                    )
+                    .optional()?;
+                let Some((category, post_count)) = row else {
+                    return Ok(None);
+                };
+                let mut stmt =
+                    conn.prepare("SELECT alias FROM tag_aliases WHERE name = ?1 ORDER BY alias")?;
+                let aliases = stmt
+                    .query_map(params![name], |r| r.get(0))?
+                    .collect::<Result<Vec<String>, _>>()?;
+
+                let mut stmt =
+                    conn.prepare("SELECT consequent FROM tag_implications WHERE antecedent = ?1")?;
+                let mut implies = Vec::new();
+                let mut seen = HashSet::from([name.clone()]);
+                let mut pending = VecDeque::from([name.clone()]);
+                while let Some(tag) = pending.pop_front() {
+                    let next = stmt
+                        .query_map(params![tag], |r| r.get::<_, String>(0))?
+                        .collect::<Result<Vec<_>, _>>()?;
+                    for consequent in next {
+                        if seen.insert(consequent.clone()) {
+                            implies.push(consequent.clone());
+                            pending.push_back(consequent);
+                        }
+                    }
+                }
+                Ok(Some(TagInfo {
+                    name,
+                    category,
+                    post_count,
+                    aliases,
+                    implies,
+                }))
+            })
+            .await
+    }
+
+    /// Canonical names of the known tags and aliases among `keys`, which are in
+    /// Danbooru's form.
+    pub async fn canonical_names(
+        &self,
+        keys: Vec<String>,
+    ) -> anyhow::Result<HashMap<String, String>> {
+        if keys.is_empty() {
+            return Ok(HashMap::new());
+        }
+        self.db
+            .with_conn_blocking("tag canonical names", move |conn| {
+                let mut out = HashMap::new();
+                for key in keys {
+                    if out.contains_key(&key) {
+                        continue;
+                    }
+                    if let Some(name) = resolve(conn, &key)? {
+                        out.insert(key, name);
+                    }
+                }
+                Ok(out)
+            })
+            .await
+    }
+
+    /// Rewrite aliased tags of a prompt to their canonical names, with spaces
+    /// for underscores.
+    pub async fn normalize_text(&self, text: &str) -> anyhow::Result<String> {
+        let keys = Prompt::parse(text)
+            .tags()
+            .iter()
+            .map(|t| danbooru_name(&t.text))
+            .collect();
+        let canonical = self.canonical_names(keys).await?;
+        Ok(replace_tags(text, &canonical))
+    }
+
+    /// `normalize_text` over every prompt of a request.
+    pub async fn normalize_request(&self, base: &mut BaseGenerateRequest) -> anyhow::Result<()> {
+        base.positive = self.normalize_text(&base.positive).await?;
+        base.negative = self.normalize_text(&base.negative).await?;
+        if let Some(chars) = base.character_prompts.as_mut() {
+            for cp in chars.iter_mut() {
+                cp.prompt = self.normalize_text(&cp.prompt).await?;
+                cp.uc = self.normalize_text(&cp.uc).await?;
+            }
+        }
+        Ok(())
+    }
+
+    fn init_schema(conn: &mut Connection) -> anyhow::Result<()> {
+        conn.execute_batch(
+            "\
+            CREATE TABLE IF NOT EXISTS tags (\
+                name TEXT NOT NULL PRIMARY KEY,\
+                category INTEGER NOT NULL,\
+                post_count INTEGER NOT NULL\
+            );\
+            CREATE INDEX IF NOT EXISTS tags_post_count ON tags(post_count DESC);\
+            CREATE TABLE IF NOT EXISTS tag_aliases (\
+                alias TEXT NOT NULL PRIMARY KEY,\
+                name TEXT NOT NULL\
+            );\
+            CREATE INDEX IF NOT EXISTS tag_aliases_name ON tag_aliases(name);\
+            CREATE TABLE IF NOT EXISTS tag_implications (\
+                antecedent TEXT NOT NULL,\
+                consequent TEXT NOT NULL,\
+                PRIMARY KEY (antecedent, consequent)\
+            );\
+            ",
+        )
+        .context("init tags schema")?;
+        Ok(())
+    }
+}
+
+/// The tag a name or alias stands for, if known.
+fn resolve(conn: &Connection, key: &str) -> anyhow::Result<Option<String>> {
+    let name = conn
+        .query_row(
+            "SELECT name FROM tags WHERE name = ?1 \
+             UNION ALL SELECT name FROM tag_aliases WHERE alias = ?1 LIMIT 1",
+            params![key],
+            |r| r.get(0),
+        )
+        .optional()?;
+    Ok(name)
+}
+
+fn csv_reader(csv: &str) -> csv::Reader<&[u8]> {
+    csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(csv.as_bytes())
+}
+
+fn count_rows(conn: &Connection, table: &str) -> anyhow::Result<usize> {
+    let n: i64 = conn.query_row(&format!("SELECT COUNT(*) FROM {table}"), [], |r| r.get(0))?;
+    Ok(n as usize)
+}
+
+/// `_` and `%` are wildcards in `LIKE`; Danbooru names are full of underscores.
+fn escape_like(s: &str) -> String {
+    s.replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
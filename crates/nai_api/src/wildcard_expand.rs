@@ -0,0 +1,174 @@
+use std::collections::VecDeque;
+
+use rand::{SeedableRng, rngs::StdRng};
+
+use nai_core::{
+    dto::BaseGenerateRequest,
+    prompt::dynamic::{self, Expander, MAX_COMBINATIONS, Node, WildcardLines},
+};
+
+use crate::{
+    prompt_snippet_expand::{MAX_DEPTH, MAX_TOTAL_EXPANSIONS, SnippetExpansionResult},
+    wildcard_store::WildcardStore,
+};
+
+/// Whether any prompt of the request has a `__name__` token or `{a|b}` choice.
+pub fn has_wildcards(base: &BaseGenerateRequest) -> bool {
+    prompts(base).any(dynamic::is_dynamic)
+}
+
+/// Pin a random `wildcard_seed` when the prompts use wildcards and none was given,
+/// so the job's picks can be reproduced from the seed. Combinatorial requests
+/// don't pick at random and get none.
+pub fn resolve_wildcard_seed(base: &mut BaseGenerateRequest) -> Option<u64> {
+    if base.combinatorial == Some(true) {
+        return None;
+    }
+    if base.wildcard_seed.is_none() && has_wildcards(base) {
+        base.wildcard_seed = Some(rand::random());
+    }
+    base.wildcard_seed
+}
+
+/// Number of prompt combinations of a combinatorial request, checked against
+/// `MAX_COMBINATIONS`; 1 for other requests.
+pub async fn count_combinations(
+    store: &WildcardStore,
+    base: &BaseGenerateRequest,
+) -> anyhow::Result<usize> {
+    if base.combinatorial != Some(true) {
+        return Ok(1);
+    }
+    let parsed: Vec<Vec<Node>> = prompts(base).map(dynamic::parse).collect();
+    let lines = load_wildcards(store, &parsed).await?;
+    let mut exp = Expander::new(&lines, MAX_DEPTH, MAX_TOTAL_EXPANSIONS);
+    let total = parsed
+        .iter()
+        .fold(1u64, |acc, nodes| acc.saturating_mul(exp.count(nodes)));
+    if total > MAX_COMBINATIONS as u64 {
+        anyhow::bail!("prompts have {total} combinations; the limit is {MAX_COMBINATIONS}");
+    }
+    Ok(total as usize)
+}
+
+/// Replace every `__name__` token with a line of that wildcard and every `{a|b}`
+/// choice with one option.
+///
+/// Picks are random for image `index` of a job; the same seed and index always
+/// give the same picks. Combinatorial requests take combination `index` instead,
+/// the positive prompt varying fastest.
+///
+/// Returns warnings for unknown or empty wildcards and exceeded limits.
+pub async fn expand_wildcards(
+    store: &WildcardStore,
+    base: &mut BaseGenerateRequest,
+    index: usize,
+) -> anyhow::Result<Vec<String>> {
+    if !has_wildcards(base) {
+        return Ok(Vec::new());
+    }
+    let parsed: Vec<Vec<Node>> = prompts(base).map(dynamic::parse).collect();
+    let lines = load_wildcards(store, &parsed).await?;
+    let mut exp = Expander::new(&lines, MAX_DEPTH, MAX_TOTAL_EXPANSIONS);
+
+    let rendered: Vec<String> = if base.combinatorial == Some(true) {
+        let mut index = index as u64;
+        parsed
+            .iter()
+            .map(|nodes| {
+                let n = exp.count(nodes);
+                let here = index % n;
+                index /= n;
+                exp.nth(nodes, here)
+            })
+            .collect()
+    } else {
+        let seed = base.wildcard_seed.unwrap_or_else(rand::random);
+        let mut rng = StdRng::seed_from_u64(seed.wrapping_add(index as u64));
+        parsed
+            .iter()
+            .map(|nodes| exp.random(nodes, &mut rng))
+            .collect()
+    };
+    let warnings = exp.warnings;
+
+    let mut it = rendered.into_iter();
+    base.positive = it.next().unwrap_or_default();
+    base.negative = it.next().unwrap_or_default();
+    if let Some(chars) = base.character_prompts.as_mut() {
+        for cp in chars.iter_mut() {
+            cp.prompt = it.next().unwrap_or_default();
+            cp.uc = it.next().unwrap_or_default();
+        }
+    }
+    Ok(warnings)
+}
+
+/// Expand a positive/negative pair as image 0 with `seed`, like a job would.
+/// Also returns how many combinations the pair has.
+pub async fn expand_wildcards_pair(
+    store: &WildcardStore,
+    positive: &str,
+    negative: &str,
+    seed: u64,
+) -> anyhow::Result<(SnippetExpansionResult, u64)> {
+    let parsed = [dynamic::parse(positive), dynamic::parse(negative)];
+    let lines = load_wildcards(store, &parsed).await?;
+    let mut exp = Expander::new(&lines, MAX_DEPTH, MAX_TOTAL_EXPANSIONS);
+    let combinations = exp.count(&parsed[0]).saturating_mul(exp.count(&parsed[1]));
+    let mut rng = StdRng::seed_from_u64(seed);
+    let positive = exp.random(&parsed[0], &mut rng);
+    let negative = exp.random(&parsed[1], &mut rng);
+    Ok((
+        SnippetExpansionResult {
+            positive,
+            negative,
+            warnings: exp.warnings,
+        },
+        combinations,
+    ))
+}
+
+fn prompts(base: &BaseGenerateRequest) -> impl Iterator<Item = &str> {
+    [base.positive.as_str(), base.negative.as_str()]
+        .into_iter()
+        .chain(
+            base.character_prompts
+                .iter()
+                .flatten()
+                .flat_map(|c| [c.prompt.as_str(), c.uc.as_str()]),
+        )
+}
+
+/// Load every wildcard reachable from `parsed`, following the ones used in
+/// wildcard lines up to `MAX_DEPTH`. Breadth first, so each list is reached at
+/// its shallowest depth.
+async fn load_wildcards(
+    store: &WildcardStore,
+    parsed: &[Vec<Node>],
+) -> anyhow::Result<WildcardLines> {
+    let mut lines = WildcardLines::new();
+    let mut pending: VecDeque<(String, usize)> = parsed
+        .iter()
+        .flat_map(|nodes| dynamic::wildcard_names(nodes))
+        .map(|name| (name.to_string(), 0))
+        .collect();
+    while let Some((name, depth)) = pending.pop_front() {
+        if depth >= MAX_DEPTH || lines.contains_key(&name) {
+            continue;
+        }
+        let list = store
+            .get(&name)
+            .await?
+            .map(|list| list.iter().map(|l| dynamic::parse(l)).collect::<Vec<_>>());
+        for nodes in list.iter().flatten() {
+            pending.extend(
+                dynamic::wildcard_names(nodes)
+                    .into_iter()
+                    .map(|n| (n.to_string(), depth + 1)),
+            );
+        }
+        lines.insert(name, list);
+    }
+    Ok(lines)
+}
@@ -0,0 +1,204 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use rusqlite::{Connection, OptionalExtension, params};
+
+use crate::{db::Database, last_generation::now_ms};
+
+/// Where a wildcard list comes from. Database lists shadow files of the same name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WildcardSource {
+    Database,
+    File,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct WildcardSummary {
+    pub name: String,
+    pub count: usize,
+    pub source: WildcardSource,
+}
+
+/// Line lists for `__name__` prompt tokens, from SQLite and from `.txt` files
+/// under `wildcards_dir` (`hair/color.txt` is `__hair/color__`).
+#[derive(Debug, Clone)]
+pub struct WildcardStore {
+    db: Database,
+    dir: PathBuf,
+}
+
+impl WildcardStore {
+    pub fn new(db: Database, dir: PathBuf) -> anyhow::Result<Self> {
+        db.with_conn(Self::init_schema)?;
+        Ok(Self { db, dir })
+    }
+
+    pub async fn list(&self) -> anyhow::Result<Vec<WildcardSummary>> {
+        let mut out = BTreeMap::new();
+
+        let dir = self.dir.clone();
+        let files = tokio::task::spawn_blocking(move || list_files(&dir))
+            .await
+            .context("wildcard scan task")??;
+        for (name, path) in files {
+            let count = read_file(&path).await?.len();
+            out.insert(
+                name.clone(),
+                WildcardSummary {
+                    name,
+                    count,
+                    source: WildcardSource::File,
+                },
+            );
+        }
+
+        let rows = self
+            .db
+            .with_conn_blocking("wildcard list", |conn| {
+                let mut stmt = conn.prepare("SELECT name, lines_json FROM wildcards")?;
+                let rows = stmt
+                    .query_map([], |r| Ok((r.get::<_, String>(0)?, r.get::<_, String>(1)?)))?
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(rows)
+            })
+            .await?;
+        for (name, json) in rows {
+            let lines: Vec<String> = serde_json::from_str(&json).context("parse wildcard")?;
+            out.insert(
+                name.clone(),
+                WildcardSummary {
+                    name,
+                    count: lines.len(),
+                    source: WildcardSource::Database,
+                },
+            );
+        }
+
+        Ok(out.into_values().collect())
+    }
+
+    /// The options of a wildcard, from the database or else its file.
+    pub async fn get(&self, name: &str) -> anyhow::Result<Option<Vec<String>>> {
+        let name = normalize_name(name)?;
+        let key = name.clone();
+        let row: Option<String> = self
+            .db
+            .with_conn_blocking("wildcard get", move |conn| {
+                Ok(conn
+                    .query_row(
+                        "SELECT lines_json FROM wildcards WHERE name = ?1",
+                        params![key],
+                        |r| r.get(0),
+                    )
+                    .optional()?)
+            })
+            .await?;
+        if let Some(json) = row {
+            return Ok(Some(serde_json::from_str(&json).context("parse wildcard")?));
+        }
+
+        let path = self.dir.join(format!("{name}.txt"));
+        if !tokio::fs::try_exists(&path).await.unwrap_or(false) {
+            return Ok(None);
+        }
+        Ok(Some(read_file(&path).await?))
+    }
+
+    pub async fn upsert(&self, name: &str, lines: Vec<String>) -> anyhow::Result<()> {
+        let name = normalize_name(name)?;
+        let json = serde_json::to_string(&clean_lines(lines.iter().map(String::as_str)))
+            .context("serialize wildcard")?;
+        self.db
+            .with_conn_blocking("wildcard upsert", move |conn| {
+                conn.execute(
+                    "INSERT INTO wildcards (name, updated_at_ms, lines_json) VALUES (?1, ?2, ?3)\
+                     ON CONFLICT(name) DO UPDATE SET updated_at_ms=excluded.updated_at_ms, lines_json=excluded.lines_json",
+                    params![name, now_ms(), json],
+                )?;
+                Ok(())
+            })
+            .await
+    }
+
+    /// Delete a database list. Files are never touched.
+    pub async fn delete(&self, name: &str) -> anyhow::Result<bool> {
+        let name = normalize_name(name)?;
+        self.db
+            .with_conn_blocking("wildcard delete", move |conn| {
+                let rows = conn.execute("DELETE FROM wildcards WHERE name = ?1", params![name])?;
+                Ok(rows > 0)
+            })
+            .await
+    }
+
+    fn init_schema(conn: &mut Connection) -> anyhow::Result<()> {
+        conn.execute_batch(
+            "\
+            CREATE TABLE IF NOT EXISTS wildcards (\
+                name TEXT NOT NULL PRIMARY KEY,\
+                updated_at_ms INTEGER NOT NULL,\
+                lines_json TEXT NOT NULL\
+            );\
+            ",
+        )
+        .context("init wildcards schema")?;
+        Ok(())
+    }
+}
+
+/// Names are `/`-separated like their file paths; `..` and empty segments are rejected.
+fn normalize_name(name: &str) -> anyhow::Result<String> {
+    let name = name.trim().trim_matches('/').replace('\\', "/");
+    if name.is_empty()
+        || name
+            .split('/')
+            .any(|seg| seg.is_empty() || seg == "." || seg == ".." || seg.trim() != seg)
+    {
+        anyhow::bail!("invalid wildcard name: {name:?}");
+    }
+    Ok(name)
+}
+
+/// Blank lines and `#` comments are skipped.
+fn clean_lines<'a>(lines: impl IntoIterator<Item = &'a str>) -> Vec<String> {
+    lines
+        .into_iter()
+        .map(str::trim)
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .map(str::to_string)
+        .collect()
+}
+
+async fn read_file(path: &Path) -> anyhow::Result<Vec<String>> {
+    let text = tokio::fs::read_to_string(path)
+        .await
+        .with_context(|| format!("read wildcard {}", path.display()))?;
+    Ok(clean_lines(text.lines()))
+}
+
+fn list_files(dir: &Path) -> anyhow::Result<Vec<(String, PathBuf)>> {
+    let mut out = Vec::new();
+    if dir.is_dir() {
+        walk(dir, dir, &mut out)?;
+    }
+    Ok(out)
+}
+
+fn walk(root: &Path, dir: &Path, out: &mut Vec<(String, PathBuf)>) -> anyhow::Result<()> {
+    for entry in std::fs::read_dir(dir).with_context(|| format!("read {}", dir.display()))? {
+        let path = entry?.path();
+        if path.is_dir() {
+            walk(root, &path, out)?;
+        } else if path.extension().is_some_and(|e| e == "txt")
+            && let Ok(rel) = path.with_extension("").strip_prefix(root)
+        {
+            let name = rel.to_string_lossy().replace('\\', "/");
+            out.push((name, path));
+        }
+    }
+    Ok(())
+}
@@ -1,15 +1,39 @@
+use std::collections::BTreeMap;
 use std::path::PathBuf;
 
 use thiserror::Error;
 
+use crate::{output_format::OutputFormat, path_template::PathTemplate, prompt::lint::ConflictRule};
+
 #[derive(Debug, Clone)]
 pub struct AppConfig {
     pub token: String,
     pub proxy: Option<String>,
     pub bind: String,
+    /// Local outputs directory. Also holds the SQLite database, so it is used even
+    /// when outputs are stored elsewhere.
     pub output_dir: PathBuf,
-    pub custom_path_template: String,
+    /// Directory of `.txt` wildcard lists for `__name__` tokens; may not exist.
+    pub wildcards_dir: PathBuf,
+    /// Directory with `clip/tokenizer.json` and `t5/tokenizer.json` for token
+    /// counts; may not exist.
+    pub tokenizer_dir: PathBuf,
+    /// Where outputs, thumbnails and the trash are stored.
+    pub storage: StorageConfig,
+    /// Output path template, parsed from `custom_path`.
+    pub custom_path_template: PathTemplate,
+    /// Default format outputs are saved in; requests may override it.
+    pub output_format: OutputFormat,
+    /// Quality (1-100) for lossy output formats (JPEG, AVIF).
+    pub output_quality: u8,
     pub format_input: bool,
+    /// Rewrite aliased tags to their canonical names from the tag database
+    /// before generating.
+    pub normalize_tags: bool,
+    /// Conflicting tag rules for the prompt linter, from the JSON file `lint_rules_file`.
+    pub lint_rules: Vec<ConflictRule>,
+    /// Lint prompts when jobs are submitted and refuse ones with errors.
+    pub lint_on_submit: bool,
     /// Base cooldown seconds between generation calls (job pacing).
     /// 0 disables cooldown.
     pub cool_time: u64,
@@ -18,6 +42,62 @@ pub struct AppConfig {
     pub cool_jitter: f64,
     /// Optional directory to serve static frontend assets (index.html, etc.).
     pub static_dir: Option<PathBuf>,
+    /// Days a deleted output stays in the trash before it is purged.
+    /// 0 disables auto-purge.
+    pub trash_days: u64,
+    /// Output retention rules; empty/`None` means keep everything.
+    pub retention: RetentionConfig,
+    /// `min_free_disk_mb`: refuse generations and pause the job queue when free
+    /// space on the output volume drops below this many MB. 0 (the default)
+    /// disables the guard.
+    pub min_free_disk_mb: u64,
+}
+
+#[derive(Debug, Clone)]
+pub enum StorageConfig {
+    /// Files under `output_dir`.
+    Local,
+    S3(S3Config),
+}
+
+/// S3-compatible bucket (AWS, MinIO, ...).
+#[derive(Debug, Clone)]
+pub struct S3Config {
+    pub bucket: String,
+    pub region: String,
+    /// Custom endpoint such as `http://127.0.0.1:9000` for MinIO; `None` for AWS.
+    pub endpoint: Option<String>,
+    pub access_key_id: Option<String>,
+    pub secret_access_key: Option<String>,
+    /// Key prefix inside the bucket, e.g. `nai-ui/outputs`.
+    pub prefix: String,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct RetentionConfig {
+    /// Max age in days per output kind (`op_type`, or its first segment such as
+    /// `director`). The key `*` applies to kinds without their own rule.
+    pub max_age_days: BTreeMap<String, u64>,
+    /// Max total size of outputs; oldest non-favourites are pruned first.
+    pub max_total_mb: Option<u64>,
+    /// Minutes between background retention runs.
+    pub interval_minutes: u64,
+}
+
+impl RetentionConfig {
+    pub fn is_enabled(&self) -> bool {
+        !self.max_age_days.is_empty() || self.max_total_mb.is_some()
+    }
+
+    /// Max age in days that applies to an output kind, if any.
+    pub fn max_age_for(&self, op_type: &str) -> Option<u64> {
+        let head = op_type.split('/').next().unwrap_or(op_type);
+        self.max_age_days
+            .get(op_type)
+            .or_else(|| self.max_age_days.get(head))
+            .or_else(|| self.max_age_days.get("*"))
+            .copied()
+    }
 }
 
 #[derive(Debug, Error)]
@@ -26,18 +106,32 @@ pub enum ConfigError {
     MissingToken,
     #[error("invalid port in env var port/PORT: {0}")]
     InvalidPort(String),
+    #[error("invalid retention_days (expected kind=days,...): {0}")]
+    InvalidRetention(String),
+    #[error("invalid storage (expected local or s3): {0}")]
+    InvalidStorage(String),
+    #[error("storage=s3 requires env var s3_bucket/S3_BUCKET")]
+    MissingS3Bucket,
+    #[error("invalid output_format (expected png/webp/jpeg/avif): {0}")]
+    InvalidOutputFormat(String),
+    #[error("invalid lint_rules_file: {0}")]
+    InvalidLintRules(String),
+    #[error("invalid custom_path template {0}")]
+    InvalidPathTemplate(String),
     #[error("io error: {0}")]
     Io(#[from] std::io::Error),
 }
 
 impl AppConfig {
+    /// Where outputs and the database live, without the rest of the config,
+    /// for commands that run without a NovelAI token.
+    pub fn output_dir() -> PathBuf {
+        load_dotenv();
+        output_dir_from_env()
+    }
+
     pub fn load() -> Result<Self, ConfigError> {
-        // Try to load .env from common locations.
-        // - Running from repo root: .env
-        // - Running from rust/backend: ../.env or ../../.env
-        let _ = dotenvy::from_path(".env");
-        let _ = dotenvy::from_path("../.env");
-        let _ = dotenvy::from_path("../../.env");
+        load_dotenv();
 
         let token = std::env::var("token")
             .or_else(|_| std::env::var("TOKEN"))
@@ -58,22 +152,70 @@ impl AppConfig {
             .or_else(|_| std::env::var("BIND"))
             .unwrap_or_else(|_| format!("127.0.0.1:{port}"));
 
-        let output_dir = std::env::var("output_dir")
-            .or_else(|_| std::env::var("OUTPUT_DIR"))
+        let output_dir = output_dir_from_env();
+
+        let wildcards_dir = std::env::var("wildcards_dir")
+            .or_else(|_| std::env::var("WILDCARDS_DIR"))
             .map(PathBuf::from)
-            .unwrap_or_else(|_| PathBuf::from("outputs"));
+            .unwrap_or_else(|_| PathBuf::from("wildcards"));
+
+        let tokenizer_dir = std::env::var("tokenizer_dir")
+            .or_else(|_| std::env::var("TOKENIZER_DIR"))
+            .map(PathBuf::from)
+            .unwrap_or_else(|_| PathBuf::from("tokenizers"));
+
+        let storage = match std::env::var("storage")
+            .or_else(|_| std::env::var("STORAGE"))
+            .unwrap_or_else(|_| "local".to_string())
+            .to_ascii_lowercase()
+            .as_str()
+        {
+            "local" => StorageConfig::Local,
+            "s3" => StorageConfig::S3(load_s3_config()?),
+            other => return Err(ConfigError::InvalidStorage(other.to_string())),
+        };
 
         let custom_path_template = std::env::var("custom_path")
             .or_else(|_| std::env::var("CUSTOM_PATH"))
             // 文件名：编号在前，随机字符在后，方便排序。
             // 示例：text2image/2025-12-24/00001_a1b2c3_123456789.png
             .unwrap_or_else(|_| "<类型>/<日期>/<编号>_<随机字符>_<种子>".to_string());
+        let custom_path_template = PathTemplate::parse(&custom_path_template)?;
+
+        let output_format =
+            match std::env::var("output_format").or_else(|_| std::env::var("OUTPUT_FORMAT")) {
+                Ok(v) => v.parse().map_err(ConfigError::InvalidOutputFormat)?,
+                Err(_) => OutputFormat::Png,
+            };
+
+        let output_quality: u8 = std::env::var("output_quality")
+            .or_else(|_| std::env::var("OUTPUT_QUALITY"))
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(90)
+            .clamp(1, 100);
 
         let format_input = std::env::var("format_input")
             .or_else(|_| std::env::var("FORMAT_INPUT"))
             .map(|v| matches!(v.as_str(), "1" | "true" | "True" | "TRUE"))
             .unwrap_or(true);
 
+        let normalize_tags = std::env::var("normalize_tags")
+            .or_else(|_| std::env::var("NORMALIZE_TAGS"))
+            .map(|v| matches!(v.as_str(), "1" | "true" | "True" | "TRUE"))
+            .unwrap_or(false);
+
+        let lint_rules =
+            match std::env::var("lint_rules_file").or_else(|_| std::env::var("LINT_RULES_FILE")) {
+                Ok(path) => load_lint_rules(&PathBuf::from(path))?,
+                Err(_) => Vec::new(),
+            };
+
+        let lint_on_submit = std::env::var("lint_on_submit")
+            .or_else(|_| std::env::var("LINT_ON_SUBMIT"))
+            .map(|v| matches!(v.as_str(), "1" | "true" | "True" | "TRUE"))
+            .unwrap_or(false);
+
         let cool_time: u64 = std::env::var("cool_time")
             .or_else(|_| std::env::var("COOL_TIME"))
             .ok()
@@ -91,16 +233,124 @@ impl AppConfig {
             .ok()
             .map(PathBuf::from);
 
+        let trash_days: u64 = std::env::var("trash_days")
+            .or_else(|_| std::env::var("TRASH_DAYS"))
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(30);
+
+        let retention_days = std::env::var("retention_days")
+            .or_else(|_| std::env::var("RETENTION_DAYS"))
+            .unwrap_or_default();
+        let max_age_days = parse_retention_days(&retention_days)?;
+
+        let max_total_mb: Option<u64> = std::env::var("retention_max_mb")
+            .or_else(|_| std::env::var("RETENTION_MAX_MB"))
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .filter(|v| *v > 0);
+
+        let interval_minutes: u64 = std::env::var("retention_interval_minutes")
+            .or_else(|_| std::env::var("RETENTION_INTERVAL_MINUTES"))
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(60)
+            .max(1);
+
+        let min_free_disk_mb: u64 = std::env::var("min_free_disk_mb")
+            .or_else(|_| std::env::var("MIN_FREE_DISK_MB"))
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(0);
+
         Ok(Self {
             token,
             proxy,
             bind,
             output_dir,
+            wildcards_dir,
+            tokenizer_dir,
+            storage,
             custom_path_template,
+            output_format,
+            output_quality,
             format_input,
+            normalize_tags,
+            lint_rules,
+            lint_on_submit,
             cool_time,
             cool_jitter,
             static_dir,
+            trash_days,
+            retention: RetentionConfig {
+                max_age_days,
+                max_total_mb,
+                interval_minutes,
+            },
+            min_free_disk_mb,
         })
     }
 }
+
+/// `s3_*` settings, read when `storage=s3`.
+fn load_s3_config() -> Result<S3Config, ConfigError> {
+    let var = |lower: &str, upper: &str| {
+        std::env::var(lower)
+            .or_else(|_| std::env::var(upper))
+            .ok()
+            .filter(|v| !v.is_empty())
+    };
+    Ok(S3Config {
+        bucket: var("s3_bucket", "S3_BUCKET").ok_or(ConfigError::MissingS3Bucket)?,
+        region: var("s3_region", "S3_REGION").unwrap_or_else(|| "us-east-1".to_string()),
+        endpoint: var("s3_endpoint", "S3_ENDPOINT"),
+        access_key_id: var("s3_access_key_id", "S3_ACCESS_KEY_ID"),
+        secret_access_key: var("s3_secret_access_key", "S3_SECRET_ACCESS_KEY"),
+        prefix: var("s3_prefix", "S3_PREFIX")
+            .map(|p| p.trim_matches('/').to_string())
+            .unwrap_or_default(),
+    })
+}
+
+/// A JSON array of `{"tags": ["smile", "frown"], "message": "..."}`.
+fn load_lint_rules(path: &std::path::Path) -> Result<Vec<ConflictRule>, ConfigError> {
+    let raw = std::fs::read_to_string(path)?;
+    serde_json::from_str(&raw)
+        .map_err(|e| ConfigError::InvalidLintRules(format!("{}: {e}", path.display())))
+}
+
+/// Parse `text2image=30,director=7,*=90`.
+fn parse_retention_days(raw: &str) -> Result<BTreeMap<String, u64>, ConfigError> {
+    let mut out = BTreeMap::new();
+    for part in raw.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+        let (kind, days) = part
+            .split_once('=')
+            .ok_or_else(|| ConfigError::InvalidRetention(part.to_string()))?;
+        let days: u64 = days
+            .trim()
+            .parse()
+            .map_err(|_| ConfigError::InvalidRetention(part.to_string()))?;
+        let kind = kind.trim();
+        if kind.is_empty() {
+            return Err(ConfigError::InvalidRetention(part.to_string()));
+        }
+        out.insert(kind.to_string(), days);
+    }
+    Ok(out)
+}
+
+fn load_dotenv() {
+    // Try to load .env from common locations.
+    // - Running from repo root: .env
+    // - Running from rust/backend: ../.env or ../../.env
+    let _ = dotenvy::from_path(".env");
+    let _ = dotenvy::from_path("../.env");
+    let _ = dotenvy::from_path("../../.env");
+}
+
+fn output_dir_from_env() -> PathBuf {
+    std::env::var("output_dir")
+        .or_else(|_| std::env::var("OUTPUT_DIR"))
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| PathBuf::from("outputs"))
+}
@@ -2,6 +2,8 @@ use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use uuid::Uuid;
 
+use crate::{grid::GridOptions, output_format::OutputFormat, seed::SeedMode};
+
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct BaseGenerateRequest {
     pub model: String,
@@ -18,6 +20,15 @@ pub struct BaseGenerateRequest {
     pub noise_schedule: Option<String>,
     pub cfg_rescale: Option<f32>,
     pub seed: i64, // -1 => random
+    /// How seeds vary across `quantity` images; defaults to `random`.
+    pub seed_mode: Option<SeedMode>,
+    /// Seeds for `seed_mode: list`.
+    pub seeds: Option<Vec<u64>>,
+    /// Seeds the `__wildcard__` and `{a|b}` picks; random per job when unset.
+    pub wildcard_seed: Option<u64>,
+    /// Generate every combination of the choices and wildcards, `quantity` images
+    /// each, instead of random picks. Jobs only.
+    pub combinatorial: Option<bool>,
     pub add_quality_tags: Option<bool>,
     pub undesired_content_preset: Option<String>,
     pub sm: Option<bool>,
@@ -28,6 +39,12 @@ pub struct BaseGenerateRequest {
     pub reference_image_multiple: Option<Vec<String>>,
     pub reference_information_extracted_multiple: Option<Vec<i32>>,
     pub reference_strength_multiple: Option<Vec<f32>>,
+    /// Name of the generation preset the request came from; only used for output paths.
+    pub preset_name: Option<String>,
+    /// Overrides `AppConfig::output_format` for this request.
+    pub output_format: Option<OutputFormat>,
+    /// Compose the outputs into a contact sheet when the job finishes. Jobs only.
+    pub grid: Option<GridOptions>,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
@@ -86,6 +103,9 @@ pub struct GenerateResponse {
 #[derive(Debug, Serialize)]
 pub struct JobSubmitResponse {
     pub job_id: Uuid,
+    /// Set when the prompts use wildcards; resubmit with it to get the same picks.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub wildcard_seed: Option<u64>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -118,6 +138,20 @@ pub struct OutputItem {
     pub date: String,
     /// File name (including extension).
     pub filename: String,
+    /// Browser URL of the cached gallery thumbnail.
+    pub thumb_url: String,
+    pub size: u64,
+    pub modified_ms: i64,
+    /// User annotations; filled in by the API layer.
+    pub annotation: OutputAnnotation,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Default, PartialEq)]
+pub struct OutputAnnotation {
+    pub favorite: bool,
+    /// 1..=5, `None` when unrated.
+    pub rating: Option<u8>,
+    pub tags: Vec<String>,
 }
 
 #[derive(Debug, Serialize)]
@@ -135,6 +169,68 @@ pub struct OutputsDeleteRequest {
 #[derive(Debug, Serialize)]
 pub struct OutputsDeleteResponse {
     pub deleted: usize,
+    /// Trash entry ids, usable for an immediate undo.
+    pub trash_ids: Vec<String>,
+}
+
+/// A deleted output waiting in the trash.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct TrashEntry {
+    pub id: String,
+    /// Relative path the file was deleted from.
+    pub original_path: String,
+    /// Relative path of the file inside the trash.
+    pub trash_path: String,
+    pub deleted_at_ms: i64,
+    pub size: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TrashListResponse {
+    pub items: Vec<TrashEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TrashRestoreRequest {
+    pub ids: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Default)]
+pub struct TrashRestoreResponse {
+    /// Original paths that were restored.
+    pub restored: Vec<String>,
+    /// Original paths left in the trash because a file already exists there.
+    pub conflicts: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TrashEmptyRequest {
+    /// Entries to purge; all of them when omitted.
+    pub ids: Option<Vec<String>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TrashEmptyResponse {
+    pub purged: usize,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct OutputsAnnotateRequest {
+    pub items: Vec<String>,
+    pub favorite: Option<bool>,
+    /// 1..=5 sets the rating, 0 clears it.
+    pub rating: Option<u8>,
+    /// Replaces all tags when present.
+    pub tags: Option<Vec<String>>,
+    #[serde(default)]
+    pub add_tags: Vec<String>,
+    #[serde(default)]
+    pub remove_tags: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct OutputsAnnotateResponse {
+    pub updated: usize,
 }
 
 /// Internal: used for job payload storage.
@@ -0,0 +1,366 @@
+use std::io::Cursor;
+
+use font8x8::legacy::BASIC_LEGACY;
+use image::{DynamicImage, ImageFormat, Rgb, RgbImage, imageops::FilterType};
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+use uuid::Uuid;
+
+use crate::{
+    output_format::OutputFormat,
+    outputs::{OutputError, OutputStore, normalize_rel_path},
+    path_template::PathContext,
+    services::output_url,
+    thumbs::THUMB_WIDTHS,
+};
+
+/// Output kind grids are saved under.
+pub const GRID_KIND: &str = "grid";
+
+/// Most images one grid may hold.
+pub const MAX_GRID_ITEMS: usize = 100;
+
+const DEFAULT_CELL_WIDTH: u32 = 384;
+const MIN_CELL_WIDTH: u32 = 64;
+const MAX_CELL_WIDTH: u32 = 1024;
+const MAX_COLUMNS: u32 = 16;
+
+const GAP: u32 = 8;
+/// Caption glyphs are the 8x8 font scaled up by this factor.
+const FONT_SCALE: u32 = 2;
+const CAPTION_HEIGHT: u32 = 8 * FONT_SCALE + 2 * GAP;
+/// Row labels of a sweep grid are cut to this many characters.
+const MAX_ROW_LABEL_CHARS: u32 = 24;
+const BACKGROUND: Rgb<u8> = Rgb([24, 24, 27]);
+const TEXT: Rgb<u8> = Rgb([228, 228, 231]);
+
+/// Layout of a contact sheet.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct GridOptions {
+    /// Images per row; defaults to a roughly square grid.
+    pub columns: Option<u32>,
+    /// Width of each cell in pixels (64-1024, default 384).
+    pub cell_width: Option<u32>,
+    /// Print each image's caption (its seed) under it. Defaults to true.
+    pub captions: Option<bool>,
+}
+
+/// One image placed on a grid.
+#[derive(Debug, Clone)]
+pub struct GridItem {
+    /// Relative output path.
+    pub path: String,
+    pub caption: String,
+}
+
+/// Axis labels of a comparison grid. Unused axes are empty.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct GridAxes {
+    /// Column headers.
+    pub x: Vec<String>,
+    /// Row labels.
+    pub y: Vec<String>,
+    /// One panel per Z value, stacked vertically under its title.
+    pub z: Vec<String>,
+}
+
+impl GridAxes {
+    fn dims(&self) -> (usize, usize, usize) {
+        (
+            self.x.len().max(1),
+            self.y.len().max(1),
+            self.z.len().max(1),
+        )
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GridResponse {
+    pub output_path: String,
+    pub url: String,
+}
+
+impl OutputStore {
+    /// Compose outputs into one labelled grid image and save it under the `grid` kind.
+    ///
+    /// Missing outputs are skipped; it's an error if none are left.
+    pub async fn compose_grid(
+        &self,
+        items: &[GridItem],
+        opts: &GridOptions,
+        job_id: Option<Uuid>,
+    ) -> Result<GridResponse, OutputError> {
+        if items.len() > MAX_GRID_ITEMS {
+            return Err(OutputError::TooManyGridItems(items.len()));
+        }
+
+        let cells: Vec<_> = self
+            .grid_cells(items.iter().map(Some))
+            .await?
+            .into_iter()
+            .flatten()
+            .collect();
+        if cells.is_empty() {
+            return Err(OutputError::NotFound);
+        }
+
+        let opts = opts.clone();
+        let (png, size) = tokio::task::spawn_blocking(move || render_grid(cells, &opts))
+            .await
+            .map_err(std::io::Error::other)??;
+        self.save_grid(&png, size, job_id).await
+    }
+
+    /// Compose a parameter sweep into a comparison grid with labelled axes.
+    ///
+    /// `cells` are in X-fastest order; `None` (or a missing output) leaves its
+    /// cell empty.
+    pub async fn compose_sweep_grid(
+        &self,
+        cells: &[Option<GridItem>],
+        axes: &GridAxes,
+        cell_width: Option<u32>,
+        job_id: Option<Uuid>,
+    ) -> Result<GridResponse, OutputError> {
+        let (nx, ny, nz) = axes.dims();
+        if cells.len() > MAX_GRID_ITEMS {
+            return Err(OutputError::TooManyGridItems(cells.len()));
+        }
+        if cells.len() != nx * ny * nz {
+            return Err(OutputError::GridLayout);
+        }
+
+        let images = self.grid_cells(cells.iter().map(Option::as_ref)).await?;
+        if images.iter().all(Option::is_none) {
+            return Err(OutputError::NotFound);
+        }
+
+        let axes = axes.clone();
+        let (png, size) =
+            tokio::task::spawn_blocking(move || render_sweep_grid(images, &axes, cell_width))
+                .await
+                .map_err(std::io::Error::other)??;
+        self.save_grid(&png, size, job_id).await
+    }
+
+    /// Decode each item, keeping positions; missing outputs become `None`.
+    async fn grid_cells<'a>(
+        &self,
+        items: impl Iterator<Item = Option<&'a GridItem>>,
+    ) -> Result<Vec<Option<(DynamicImage, String)>>, OutputError> {
+        let mut cells = Vec::new();
+        for item in items {
+            let Some(item) = item else {
+                cells.push(None);
+                continue;
+            };
+            match self.grid_cell(&item.path).await {
+                Ok(img) => cells.push(Some((img, item.caption.clone()))),
+                Err(OutputError::NotFound) => {
+                    warn!(path = %item.path, "grid: output missing, skipped");
+                    cells.push(None);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(cells)
+    }
+
+    async fn save_grid(
+        &self,
+        png: &[u8],
+        size: (u32, u32),
+        job_id: Option<Uuid>,
+    ) -> Result<GridResponse, OutputError> {
+        let mut ctx = PathContext::new(GRID_KIND, rand::random::<u64>());
+        ctx.job_id = job_id;
+        ctx.size = Some(size);
+        let output_path = self.save_png(&ctx, png, None).await?;
+        let url = output_url(&output_path);
+        Ok(GridResponse { output_path, url })
+    }
+
+    /// Decoded image for a grid cell. Formats that can't be decoded use their
+    /// largest cached thumbnail, which is written when they are saved.
+    async fn grid_cell(&self, rel: &str) -> Result<DynamicImage, OutputError> {
+        let rel = normalize_rel_path(rel);
+        let decodable = OutputFormat::from_path(&rel).is_none_or(|f| f.is_decodable());
+        let bytes = if decodable {
+            self.read(&rel).await?
+        } else {
+            let width = THUMB_WIDTHS[THUMB_WIDTHS.len() - 1];
+            self.thumbnail(&rel, width).await?
+        };
+        tokio::task::spawn_blocking(move || image::load_from_memory(&bytes))
+            .await
+            .map_err(std::io::Error::other)?
+            .map_err(OutputError::from)
+    }
+}
+
+/// Returns the PNG and its size.
+fn render_grid(
+    cells: Vec<(DynamicImage, String)>,
+    opts: &GridOptions,
+) -> Result<(Vec<u8>, (u32, u32)), OutputError> {
+    let count = cells.len() as u32;
+    let columns = opts
+        .columns
+        .unwrap_or_else(|| (count as f64).sqrt().ceil() as u32)
+        .clamp(1, MAX_COLUMNS)
+        .min(count);
+    let rows = count.div_ceil(columns);
+    let cell_width = clamp_cell_width(opts.cell_width);
+    let captions = opts.captions.unwrap_or(true);
+
+    let scaled: Vec<(RgbImage, String)> = cells
+        .into_iter()
+        .map(|(img, caption)| (scale_to_width(&img, cell_width), caption))
+        .collect();
+
+    // Rows are as tall as their tallest image so mixed aspect ratios still line up.
+    let caption_height = if captions { CAPTION_HEIGHT } else { 0 };
+    let row_heights: Vec<u32> = scaled
+        .chunks(columns as usize)
+        .map(|row| row.iter().map(|(img, _)| img.height()).max().unwrap_or(0) + caption_height)
+        .collect();
+
+    let width = columns * cell_width + (columns + 1) * GAP;
+    let height = row_heights.iter().sum::<u32>() + (rows + 1) * GAP;
+    let mut canvas = RgbImage::from_pixel(width, height, BACKGROUND);
+
+    let mut y = GAP;
+    for (row, row_height) in scaled.chunks(columns as usize).zip(&row_heights) {
+        for (col, (img, caption)) in row.iter().enumerate() {
+            let x = GAP + col as u32 * (cell_width + GAP);
+            image::imageops::replace(&mut canvas, img, x as i64, y as i64);
+            if captions {
+                let text_y = y + img.height() + GAP;
+                draw_text(&mut canvas, x, text_y, caption, cell_width);
+            }
+        }
+        y += row_height + GAP;
+    }
+
+    encode_png(&canvas)
+}
+
+/// Returns the PNG and its size.
+fn render_sweep_grid(
+    cells: Vec<Option<(DynamicImage, String)>>,
+    axes: &GridAxes,
+    cell_width: Option<u32>,
+) -> Result<(Vec<u8>, (u32, u32)), OutputError> {
+    let (nx, ny, _) = axes.dims();
+    let cell_width = clamp_cell_width(cell_width);
+    let advance = 8 * FONT_SCALE;
+
+    let scaled: Vec<Option<(RgbImage, String)>> = cells
+        .into_iter()
+        .map(|c| c.map(|(img, caption)| (scale_to_width(&img, cell_width), caption)))
+        .collect();
+    // Every cell gets the tallest image's height so rows and columns line up.
+    let image_height = scaled
+        .iter()
+        .flatten()
+        .map(|(img, _)| img.height())
+        .max()
+        .unwrap_or(cell_width);
+    let row_height = image_height + CAPTION_HEIGHT;
+
+    let label_width = axes
+        .y
+        .iter()
+        .map(|l| l.chars().count() as u32)
+        .max()
+        .map(|chars| chars.min(MAX_ROW_LABEL_CHARS) * advance + GAP)
+        .unwrap_or(0);
+    let header_height = if axes.x.is_empty() { 0 } else { CAPTION_HEIGHT };
+    let title_height = if axes.z.is_empty() { 0 } else { CAPTION_HEIGHT };
+    let panel_height = title_height + header_height + ny as u32 * (row_height + GAP);
+
+    let left = GAP + label_width;
+    let width = left + nx as u32 * (cell_width + GAP);
+    let height = GAP + (scaled.len() / (nx * ny)) as u32 * panel_height;
+    let mut canvas = RgbImage::from_pixel(width, height, BACKGROUND);
+
+    let mut y = GAP;
+    for (z, panel) in scaled.chunks(nx * ny).enumerate() {
+        if let Some(title) = axes.z.get(z) {
+            draw_text(&mut canvas, GAP, y, title, width - 2 * GAP);
+            y += title_height;
+        }
+        for (x, label) in axes.x.iter().enumerate() {
+            let cx = left + x as u32 * (cell_width + GAP);
+            draw_text(&mut canvas, cx, y, label, cell_width);
+        }
+        y += header_height;
+        for (row_idx, row) in panel.chunks(nx).enumerate() {
+            if let Some(label) = axes.y.get(row_idx) {
+                let ly = y + image_height.saturating_sub(advance) / 2;
+                draw_text(&mut canvas, GAP, ly, label, label_width - GAP);
+            }
+            for (x, cell) in row.iter().enumerate() {
+                let Some((img, caption)) = cell else {
+                    continue;
+                };
+                let cx = left + x as u32 * (cell_width + GAP);
+                image::imageops::replace(&mut canvas, img, cx as i64, y as i64);
+                draw_text(&mut canvas, cx, y + img.height() + GAP, caption, cell_width);
+            }
+            y += row_height + GAP;
+        }
+    }
+
+    encode_png(&canvas)
+}
+
+fn clamp_cell_width(width: Option<u32>) -> u32 {
+    width
+        .unwrap_or(DEFAULT_CELL_WIDTH)
+        .clamp(MIN_CELL_WIDTH, MAX_CELL_WIDTH)
+}
+
+fn scale_to_width(img: &DynamicImage, width: u32) -> RgbImage {
+    let height = ((img.height() as u64 * width as u64) / img.width().max(1) as u64).max(1) as u32;
+    img.resize_exact(width, height, FilterType::Triangle)
+        .to_rgb8()
+}
+
+fn encode_png(canvas: &RgbImage) -> Result<(Vec<u8>, (u32, u32)), OutputError> {
+    let mut buf = Cursor::new(Vec::new());
+    canvas.write_to(&mut buf, ImageFormat::Png)?;
+    Ok((buf.into_inner(), canvas.dimensions()))
+}
+
+/// Draw ASCII text with the built-in 8x8 font, clipped to `max_width`.
+/// Characters outside ASCII are drawn as '?'.
+fn draw_text(canvas: &mut RgbImage, x: u32, y: u32, text: &str, max_width: u32) {
+    let advance = 8 * FONT_SCALE;
+    let max_chars = (max_width / advance) as usize;
+    for (i, ch) in text.chars().take(max_chars).enumerate() {
+        let code = if ch.is_ascii() {
+            ch as usize
+        } else {
+            '?' as usize
+        };
+        let glyph = BASIC_LEGACY[code];
+        let gx = x + i as u32 * advance;
+        for (row, bits) in glyph.iter().enumerate() {
+            for col in 0..8u32 {
+                if bits & (1 << col) == 0 {
+                    continue;
+                }
+                for dy in 0..FONT_SCALE {
+                    for dx in 0..FONT_SCALE {
+                        let px = gx + col * FONT_SCALE + dx;
+                        let py = y + row as u32 * FONT_SCALE + dy;
+                        if px < canvas.width() && py < canvas.height() {
+                            canvas.put_pixel(px, py, TEXT);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
@@ -7,7 +7,7 @@ use tokio::sync::Mutex;
 use tokio_util::sync::CancellationToken;
 use uuid::Uuid;
 
-use crate::dto::GenerateResponse;
+use crate::{dto::GenerateResponse, grid::GridResponse};
 
 #[derive(Debug, Clone, Serialize)]
 pub struct JobSummary {
@@ -25,7 +25,11 @@ pub struct JobSummary {
 pub enum JobStatus {
     Queued,
     Running,
-    Succeeded { outputs: Vec<GenerateResponse> },
+    Succeeded {
+        outputs: Vec<GenerateResponse>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        grid: Option<GridResponse>,
+    },
     Failed { error: String },
     Cancelled,
 }
@@ -67,10 +71,8 @@ fn apply_status(record: &mut JobRecord, status: JobStatus) {
     record.status = status;
     record.updated_at_ms = ts;
 
-    if matches!(record.status, JobStatus::Running) {
-        if record.started_at_ms.is_none() {
-            record.started_at_ms = Some(ts);
-        }
+    if matches!(record.status, JobStatus::Running) && record.started_at_ms.is_none() {
+        record.started_at_ms = Some(ts);
     }
 
     if is_terminal(&record.status) {
@@ -99,6 +101,12 @@ fn prune_terminal_jobs(map: &mut HashMap<Uuid, JobRecord>) {
     }
 }
 
+impl Default for JobStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl JobStore {
     pub fn new() -> Self {
         Self {
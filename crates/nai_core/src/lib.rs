@@ -1,8 +1,18 @@
 pub mod config;
 pub mod dto;
+pub mod grid;
 pub mod job;
+pub mod metadata;
 pub mod nai;
+pub mod output_format;
 pub mod outputs;
+pub mod path_template;
 pub mod prompt;
+pub mod seed;
 pub mod services;
+pub mod storage;
+pub mod sweep;
+pub mod thumbs;
+pub mod tokenizer;
+pub mod trash;
 pub mod util;
@@ -0,0 +1,103 @@
+use std::collections::BTreeMap;
+
+use async_trait::async_trait;
+use serde_json::Value;
+
+const PNG_SIGNATURE: &[u8; 8] = b"\x89PNG\r\n\x1a\n";
+
+/// Read uncompressed `tEXt` / `iTXt` chunks from a PNG.
+///
+/// NovelAI writes its generation parameters as plain text chunks (`Comment` holds the
+/// JSON parameters, `Description` the prompt), so compressed variants are skipped.
+pub fn png_text_chunks(bytes: &[u8]) -> BTreeMap<String, String> {
+    let mut out = BTreeMap::new();
+    if bytes.len() < 8 || &bytes[..8] != PNG_SIGNATURE {
+        return out;
+    }
+
+    let mut pos = 8usize;
+    while pos + 8 <= bytes.len() {
+        let len = u32::from_be_bytes([bytes[pos], bytes[pos + 1], bytes[pos + 2], bytes[pos + 3]])
+            as usize;
+        let kind = &bytes[pos + 4..pos + 8];
+        let data_start = pos + 8;
+        let Some(data_end) = data_start.checked_add(len).filter(|&e| e <= bytes.len()) else {
+            break;
+        };
+        let data = &bytes[data_start..data_end];
+
+        match kind {
+            b"tEXt" => {
+                if let Some(nul) = data.iter().position(|&b| b == 0) {
+                    let key = latin1(&data[..nul]);
+                    out.insert(key, latin1(&data[nul + 1..]));
+                }
+            }
+            b"iTXt" => {
+                if let Some((key, text)) = parse_itxt(data) {
+                    out.insert(key, text);
+                }
+            }
+            b"IEND" => break,
+            _ => {}
+        }
+
+        // length + type + data + crc
+        pos = data_end + 4;
+    }
+    out
+}
+
+/// Text chunks of outputs saved in formats that can't carry them (WebP, JPEG, AVIF),
+/// keyed by relative output path.
+#[async_trait]
+pub trait OutputMetadataStore: Send + Sync {
+    async fn save(&self, rel: &str, chunks: BTreeMap<String, String>) -> anyhow::Result<()>;
+    async fn load(&self, rel: &str) -> anyhow::Result<Option<BTreeMap<String, String>>>;
+    async fn remove(&self, rels: &[String]) -> anyhow::Result<()>;
+}
+
+/// Generation metadata as JSON: every text chunk, with `Comment` parsed when it is JSON.
+pub fn png_generation_metadata(bytes: &[u8]) -> Option<Value> {
+    generation_metadata(png_text_chunks(bytes))
+}
+
+/// Same shape as [`png_generation_metadata`], from already extracted chunks.
+pub fn generation_metadata(chunks: BTreeMap<String, String>) -> Option<Value> {
+    if chunks.is_empty() {
+        return None;
+    }
+    let map = chunks
+        .into_iter()
+        .map(|(k, v)| {
+            let value = if k == "Comment" {
+                serde_json::from_str(&v).unwrap_or(Value::String(v))
+            } else {
+                Value::String(v)
+            };
+            (k, value)
+        })
+        .collect();
+    Some(Value::Object(map))
+}
+
+fn parse_itxt(data: &[u8]) -> Option<(String, String)> {
+    let nul = data.iter().position(|&b| b == 0)?;
+    let key = latin1(&data[..nul]);
+    let rest = &data[nul + 1..];
+    let (&compressed, rest) = rest.split_first()?;
+    if compressed != 0 {
+        return None;
+    }
+    // compression method, language tag, translated keyword
+    let rest = rest.get(1..)?;
+    let lang_end = rest.iter().position(|&b| b == 0)?;
+    let rest = &rest[lang_end + 1..];
+    let trans_end = rest.iter().position(|&b| b == 0)?;
+    let text = String::from_utf8_lossy(&rest[trans_end + 1..]).to_string();
+    Some((key, text))
+}
+
+fn latin1(bytes: &[u8]) -> String {
+    bytes.iter().map(|&b| b as char).collect()
+}
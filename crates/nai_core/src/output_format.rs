@@ -0,0 +1,124 @@
+use std::io::Cursor;
+use std::path::Path;
+use std::str::FromStr;
+
+use image::{
+    ImageFormat,
+    codecs::{avif::AvifEncoder, jpeg::JpegEncoder, webp::WebPEncoder},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::outputs::OutputError;
+
+/// File extensions recognised as outputs by the scanners and the gallery.
+pub const OUTPUT_EXTENSIONS: [&str; 5] = ["png", "webp", "jpg", "jpeg", "avif"];
+
+/// AVIF encoder speed (1 = slowest/best, 10 = fastest).
+const AVIF_SPEED: u8 = 6;
+
+/// Format outputs are written in. NovelAI always returns PNG; other formats are
+/// re-encoded on save and the PNG text chunks are kept in the database instead.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    #[default]
+    Png,
+    /// Lossless WebP.
+    Webp,
+    #[serde(alias = "jpg")]
+    Jpeg,
+    Avif,
+}
+
+impl OutputFormat {
+    /// Format of an output path, by extension.
+    pub fn from_path(path: &str) -> Option<Self> {
+        path.rsplit_once('.')?.1.parse().ok()
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            OutputFormat::Png => "png",
+            OutputFormat::Webp => "webp",
+            OutputFormat::Jpeg => "jpg",
+            OutputFormat::Avif => "avif",
+        }
+    }
+
+    /// Whether thumbnails can be rendered from the saved file. AVIF decoding needs a
+    /// native decoder, so its thumbnails are rendered from the PNG at save time.
+    pub fn is_decodable(self) -> bool {
+        !matches!(self, OutputFormat::Avif)
+    }
+
+    /// Re-encode NovelAI's PNG. `quality` (1-100) applies to JPEG and AVIF.
+    pub fn encode(self, png_bytes: &[u8], quality: u8) -> Result<Vec<u8>, OutputError> {
+        if self == OutputFormat::Png {
+            return Ok(png_bytes.to_vec());
+        }
+        let img = image::load_from_memory_with_format(png_bytes, ImageFormat::Png)?;
+        let mut buf = Cursor::new(Vec::new());
+        match self {
+            OutputFormat::Png => unreachable!(),
+            OutputFormat::Webp => img.write_with_encoder(WebPEncoder::new_lossless(&mut buf))?,
+            OutputFormat::Jpeg => img
+                .to_rgb8()
+                .write_with_encoder(JpegEncoder::new_with_quality(&mut buf, quality))?,
+            OutputFormat::Avif => {
+                let encoder = AvifEncoder::new_with_speed_quality(&mut buf, AVIF_SPEED, quality);
+                if img.color().has_alpha() {
+                    img.to_rgba8().write_with_encoder(encoder)?
+                } else {
+                    img.to_rgb8().write_with_encoder(encoder)?
+                }
+            }
+        }
+        Ok(buf.into_inner())
+    }
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "png" => Ok(OutputFormat::Png),
+            "webp" => Ok(OutputFormat::Webp),
+            "jpg" | "jpeg" => Ok(OutputFormat::Jpeg),
+            "avif" => Ok(OutputFormat::Avif),
+            other => Err(other.to_string()),
+        }
+    }
+}
+
+/// MIME type for an output path, by extension; `None` for non-output files.
+pub fn content_type(path: &str) -> Option<&'static str> {
+    let ext = path.rsplit_once('.')?.1.to_ascii_lowercase();
+    match ext.as_str() {
+        "png" => Some("image/png"),
+        "webp" => Some("image/webp"),
+        "jpg" | "jpeg" => Some("image/jpeg"),
+        "avif" => Some("image/avif"),
+        _ => None,
+    }
+}
+
+pub fn is_output_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|s| s.to_str())
+        .is_some_and(|ext| OUTPUT_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()))
+}
+
+/// Replace a known output extension (or append one) so `rel` ends with `.{ext}`.
+pub(crate) fn with_output_extension(rel: &str, format: OutputFormat) -> String {
+    let stem = match rel.rsplit_once('.') {
+        Some((stem, ext))
+            if !ext.contains('/')
+                && OUTPUT_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()) =>
+        {
+            stem
+        }
+        _ => rel,
+    };
+    format!("{stem}.{}", format.extension())
+}
@@ -1,13 +1,26 @@
 use std::collections::BTreeMap;
-use std::path::{Component, Path, PathBuf};
+use std::path::{Component, Path};
 use std::sync::Arc;
 
+use async_trait::async_trait;
 use chrono::Local;
+use sha2::{Digest, Sha256};
 use thiserror::Error;
-use tokio::sync::Mutex;
+use tokio::task::JoinSet;
 use tracing::warn;
 
-use crate::{config::AppConfig, dto::OutputItem, util};
+use crate::{
+    config::AppConfig,
+    dto::{OutputAnnotation, OutputItem},
+    metadata::{
+        OutputMetadataStore, generation_metadata, png_generation_metadata, png_text_chunks,
+    },
+    output_format::{OutputFormat, is_output_file, with_output_extension},
+    path_template::{PathContext, PathTemplate},
+    storage::{OutputStorage, StoredObject},
+    thumbs::{DEFAULT_THUMB_WIDTH, thumb_url},
+    util,
+};
 
 #[derive(Debug, Error)]
 pub enum OutputError {
@@ -17,123 +30,237 @@ pub enum OutputError {
     Json(#[from] serde_json::Error),
     #[error("invalid output path")]
     InvalidPath,
+    #[error("output not found")]
+    NotFound,
+    #[error("image error: {0}")]
+    Image(#[from] image::ImageError),
+    #[error("unsupported thumbnail width: {0}")]
+    UnsupportedThumbWidth(u32),
+    #[error("too many images for one grid: {0}")]
+    TooManyGridItems(usize),
+    #[error("grid cells don't match its axes")]
+    GridLayout,
+    #[error("output database: {0:#}")]
+    Backend(anyhow::Error),
+}
+
+/// Persistent per-directory `<编号>` cursors.
+///
+/// Keys are normalized relative leaf dirs using '/'; the empty string is the outputs root.
+/// Implementations must make `next_index` atomic across processes sharing an output dir.
+#[async_trait]
+pub trait OutputCounters: Send + Sync {
+    /// Reserve and return the next index for `dir_key`.
+    async fn next_index(&self, dir_key: &str) -> anyhow::Result<usize>;
+    /// Raise cursors so each is at least the given next index. Never lowers one.
+    async fn raise_to(&self, floors: BTreeMap<String, usize>) -> anyhow::Result<()>;
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct OutputFileStat {
+    pub size: u64,
+    pub modified_ms: i64,
 }
 
 #[derive(Clone)]
 pub struct OutputStore {
-    output_dir: PathBuf,
-    template: String,
-    counters_path: PathBuf,
+    storage: Arc<dyn OutputStorage>,
+    template: PathTemplate,
+    format: OutputFormat,
+    quality: u8,
     /// Per-leaf directory "next index" cursor.
-    /// Key is a normalized relative dir path using '/'. Empty string means outputs root.
-    counters: Arc<Mutex<BTreeMap<String, usize>>>,
+    counters: Arc<dyn OutputCounters>,
+    metadata: Arc<dyn OutputMetadataStore>,
 }
 
 impl OutputStore {
-    pub fn new(cfg: &AppConfig) -> Result<Self, OutputError> {
-        let output_dir = cfg.output_dir.clone();
-        std::fs::create_dir_all(&output_dir)?;
-
-        let counters_path = output_dir.join("output_counters.json");
-        let mut counters: BTreeMap<String, usize> =
-            load_counters_sync(&counters_path).unwrap_or_default();
-
+    pub async fn new(
+        cfg: &AppConfig,
+        storage: Arc<dyn OutputStorage>,
+        counters: Arc<dyn OutputCounters>,
+        metadata: Arc<dyn OutputMetadataStore>,
+    ) -> Result<Self, OutputError> {
         // 启动时扫描一下游标位置：根据已有文件名推断每个目录的 next index。
         // 仅支持新命名：00001_xxxxxx_seed.png（编号在前）
-        let changed = scan_existing_outputs_sync(&output_dir, &mut counters)?;
-        if changed {
-            save_counters_sync(&counters_path, &counters)?;
-        }
+        let objects = storage.list("").await?;
+        let floors = scan_existing_outputs(&objects);
+        counters
+            .raise_to(floors)
+            .await
+            .map_err(OutputError::Backend)?;
 
         Ok(Self {
-            output_dir,
+            storage,
             template: cfg.custom_path_template.clone(),
-            counters_path,
-            counters: Arc::new(Mutex::new(counters)),
+            format: cfg.output_format,
+            quality: cfg.output_quality,
+            counters,
+            metadata,
         })
     }
 
-    pub fn root(&self) -> &Path {
-        &self.output_dir
+    pub(crate) fn storage(&self) -> &dyn OutputStorage {
+        self.storage.as_ref()
+    }
+
+    /// Read an output's bytes.
+    pub async fn read(&self, rel: &str) -> Result<Vec<u8>, OutputError> {
+        let rel_norm = normalize_rel_path(rel);
+        if !is_safe_rel_path(&rel_norm) || is_internal(&rel_norm) {
+            return Err(OutputError::InvalidPath);
+        }
+        self.storage
+            .get(&rel_norm)
+            .await?
+            .ok_or(OutputError::NotFound)
     }
 
-    /// Save image and return a *relative path* under the outputs root.
+    /// Save NovelAI's PNG and return a *relative path* under the outputs root.
+    ///
+    /// The image is re-encoded when `format` (or the configured default) isn't PNG.
     pub async fn save_png(
         &self,
-        kind: &str,
-        seed: u64,
+        ctx: &PathContext<'_>,
         png_bytes: &[u8],
+        format: Option<OutputFormat>,
     ) -> Result<String, OutputError> {
-        let date = Local::now().date_naive().to_string();
+        let format = format.unwrap_or(self.format);
+        let now = Local::now();
         let random = util::random_str(6);
 
-        let rel_template = self
-            .template
-            .replace("<类型>", kind)
-            .replace("<日期>", &date)
-            .replace("<种子>", &seed.to_string())
-            .replace("<随机字符>", &random);
-
-        // We must not use raw "<编号>" in any on-disk directory name (Windows forbids '<' and '>').
-        // Compute leaf directory using a safe placeholder index, then compute the real index.
-        let mut rel_for_dir = rel_template.replace("<编号>", "00000");
-        if !rel_for_dir.ends_with(".png") {
-            rel_for_dir.push_str(".png");
-        }
-        let rel_for_dir = sanitize_rel_path(&rel_for_dir);
+        // The index depends on the leaf directory, so render once with a placeholder
+        // index to find the directory, then again with the real one.
+        let rel_for_dir = self.template.render(ctx, &now, "00000", &random);
+        let rel_for_dir = sanitize_rel_path(&with_output_extension(&rel_for_dir, format));
 
         let leaf_dir = Path::new(&rel_for_dir)
             .parent()
             .map(|p| p.to_path_buf())
             .unwrap_or_default();
 
-        let abs_leaf = self.output_dir.join(&leaf_dir);
-        tokio::fs::create_dir_all(&abs_leaf).await?;
-
         // Use a persistent per-directory cursor so deletions won't cause index reuse.
         let leaf_key = normalize_rel_dir_key(&leaf_dir);
-        let next_index = {
-            let mut map = self.counters.lock().await;
-            let next = map.get(&leaf_key).copied().unwrap_or(0);
-            map.insert(leaf_key.clone(), next + 1);
-            next
-        };
+        let next_index = self
+            .counters
+            .next_index(&leaf_key)
+            .await
+            .map_err(OutputError::Backend)?;
         let idx = format!("{:0>5}", next_index);
-        self.save_counters().await?;
 
-        let mut rel = rel_template.replace("<编号>", &idx);
-        if !rel.ends_with(".png") {
-            rel.push_str(".png");
-        }
+        let rel = self.template.render(ctx, &now, &idx, &random);
+        let mut rel = with_output_extension(&rel, format);
 
         let rel_sanitized = sanitize_rel_path(&rel);
         if rel_sanitized != rel {
             warn!(
                 original = %rel,
                 sanitized = %rel_sanitized,
-                template = %self.template,
+                template = %self.template.as_str(),
                 "output path contained invalid characters; sanitized"
             );
         }
         rel = rel_sanitized;
 
-        let path = self.output_dir.join(&rel);
-        if let Some(parent) = path.parent() {
-            tokio::fs::create_dir_all(parent).await?;
+        if format == OutputFormat::Png {
+            self.storage.put(&rel, png_bytes.to_vec()).await?;
+            // Return relative path for browser usage.
+            return Ok(rel);
+        }
+
+        let quality = self.quality;
+        let png = png_bytes.to_vec();
+        let encoded = tokio::task::spawn_blocking(move || format.encode(&png, quality))
+            .await
+            .map_err(std::io::Error::other)??;
+        let chunks = png_text_chunks(png_bytes);
+        if !chunks.is_empty() {
+            self.metadata
+                .save(&rel, chunks)
+                .await
+                .map_err(OutputError::Backend)?;
+        }
+        self.storage.put(&rel, encoded).await?;
+        if !format.is_decodable() {
+            self.prime_thumbnails(&rel, png_bytes).await?;
         }
-        tokio::fs::write(&path, png_bytes).await?;
-        // Return relative path for browser usage.
         Ok(rel)
     }
 
-    /// List png files as relative paths under outputs root.
-    pub async fn list_pngs(&self, limit: usize) -> Result<Vec<String>, OutputError> {
-        let mut out = Vec::new();
-        if !self.output_dir.exists() {
-            return Ok(out);
+    /// Generation metadata for an output: PNG text chunks, or what was stored for
+    /// outputs saved in other formats.
+    pub async fn generation_metadata(
+        &self,
+        rel: &str,
+    ) -> Result<Option<serde_json::Value>, OutputError> {
+        let rel_norm = normalize_rel_path(rel);
+        if !is_safe_rel_path(&rel_norm) || is_internal(&rel_norm) {
+            return Err(OutputError::InvalidPath);
+        }
+        if rel_norm.to_ascii_lowercase().ends_with(".png") {
+            let bytes = self.read(&rel_norm).await?;
+            return Ok(png_generation_metadata(&bytes));
+        }
+        if self.storage.stat(&rel_norm).await?.is_none() {
+            return Err(OutputError::NotFound);
         }
-        walk_pngs(&self.output_dir, &self.output_dir, &mut out, limit)?;
-        Ok(out)
+        self.stored_metadata(&rel_norm).await
+    }
+
+    /// Metadata kept in the database for non-PNG outputs.
+    pub async fn stored_metadata(
+        &self,
+        rel: &str,
+    ) -> Result<Option<serde_json::Value>, OutputError> {
+        let chunks = self
+            .metadata
+            .load(&normalize_rel_path(rel))
+            .await
+            .map_err(OutputError::Backend)?;
+        Ok(chunks.and_then(generation_metadata))
+    }
+
+    /// Forget stored metadata for outputs that are gone for good.
+    pub(crate) async fn forget_metadata(&self, rels: &[String]) {
+        if rels.is_empty() {
+            return;
+        }
+        if let Err(e) = self.metadata.remove(rels).await {
+            warn!(error = %e, "failed to remove output metadata");
+        }
+    }
+
+    /// Every output file (no thumbnails, trash or other internal files).
+    pub async fn list_objects(&self) -> Result<Vec<StoredObject>, OutputError> {
+        let mut objects = self.storage.list("").await?;
+        objects.retain(|o| is_output_file(Path::new(&o.key)));
+        Ok(objects)
+    }
+
+    /// List output files as relative paths under outputs root.
+    pub async fn list_outputs(&self, limit: usize) -> Result<Vec<String>, OutputError> {
+        let objects = self.list_objects().await?;
+        Ok(objects.into_iter().take(limit).map(|o| o.key).collect())
+    }
+
+    /// All output items in gallery order.
+    pub async fn list_items(&self) -> Result<Vec<OutputItem>, OutputError> {
+        let mut items: Vec<OutputItem> = self
+            .list_objects()
+            .await?
+            .into_iter()
+            .map(|o| output_item_from_object(&o))
+            .collect();
+
+        items.sort_by(|a, b| {
+            let a_idx = parse_output_index(&a.filename).unwrap_or(0);
+            let b_idx = parse_output_index(&b.filename).unwrap_or(0);
+            a.op_type
+                .cmp(&b.op_type)
+                .then_with(|| b.date.cmp(&a.date))
+                .then_with(|| b_idx.cmp(&a_idx))
+                .then_with(|| b.filename.cmp(&a.filename))
+        });
+        Ok(items)
     }
 
     pub async fn list_items_paginated(
@@ -141,65 +268,104 @@ impl OutputStore {
         limit: usize,
         offset: usize,
     ) -> Result<(Vec<OutputItem>, bool, usize), OutputError> {
-        let root = self.output_dir.clone();
-        let items = tokio::task::spawn_blocking(move || -> Result<Vec<OutputItem>, OutputError> {
-            let mut rels = Vec::new();
-            if !root.exists() {
-                return Ok(Vec::new());
+        let items = self.list_items().await?;
+        Ok(paginate(items, limit, offset))
+    }
+
+    /// Size and mtime for each relative path; `None` when missing or unsafe.
+    ///
+    /// Listed items already carry these; this is for paths named by a request.
+    /// The stats run concurrently, since on a remote backend each is a request.
+    pub async fn file_stats(
+        &self,
+        rel_paths: &[String],
+    ) -> Result<Vec<Option<OutputFileStat>>, OutputError> {
+        const CONCURRENCY: usize = 16;
+
+        let mut stats = vec![None; rel_paths.len()];
+        let mut pending = rel_paths
+            .iter()
+            .map(|rel| normalize_rel_path(rel))
+            .enumerate()
+            .filter(|(_, rel)| is_safe_rel_path(rel));
+        let mut tasks = JoinSet::new();
+        loop {
+            while tasks.len() < CONCURRENCY
+                && let Some((i, rel)) = pending.next()
+            {
+                let storage = self.storage.clone();
+                tasks.spawn(async move { (i, storage.stat(&rel).await) });
             }
-            walk_pngs(&root, &root, &mut rels, usize::MAX)?;
-            let mut items: Vec<OutputItem> =
-                rels.into_iter().map(|p| output_item_from_rel(&p)).collect();
-
-            items.sort_by(|a, b| {
-                let a_idx = parse_output_index(&a.filename).unwrap_or(0);
-                let b_idx = parse_output_index(&b.filename).unwrap_or(0);
-                a.op_type
-                    .cmp(&b.op_type)
-                    .then_with(|| b.date.cmp(&a.date))
-                    .then_with(|| b_idx.cmp(&a_idx))
-                    .then_with(|| b.filename.cmp(&a.filename))
+            let Some(joined) = tasks.join_next().await else {
+                break;
+            };
+            let (i, stat) = joined.map_err(std::io::Error::other)?;
+            stats[i] = stat?.map(|o| OutputFileStat {
+                size: o.size,
+                modified_ms: o.modified_ms,
             });
-            Ok(items)
-        })
-        .await
-        .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))??;
-        let total = items.len();
-        let sliced: Vec<OutputItem> = items.into_iter().skip(offset).take(limit).collect();
-        let next_offset = offset + sliced.len();
-        let has_more = next_offset < total;
-        Ok((sliced, has_more, next_offset))
+        }
+        Ok(stats)
     }
 
-    pub async fn delete_rel_files(&self, rel_paths: &[String]) -> Result<usize, OutputError> {
+    /// Content fingerprint that stays stable when a file is moved or renamed.
+    ///
+    /// Hashes the size plus the head and tail of the file, which for generated images
+    /// already covers the embedded generation metadata, without reading whole files.
+    /// `size` is the file's current size, from a listing or stat.
+    pub async fn fingerprint(&self, rel: &str, size: u64) -> Result<String, OutputError> {
+        const CHUNK: u64 = 64 * 1024;
+
+        let rel_norm = normalize_rel_path(rel);
+        if !is_safe_rel_path(&rel_norm) {
+            return Err(OutputError::InvalidPath);
+        }
+        let mut hasher = Sha256::new();
+        hasher.update(size.to_le_bytes());
+        let head = self
+            .storage
+            .get_range(&rel_norm, 0..size.min(CHUNK))
+            .await?
+            .ok_or(OutputError::NotFound)?;
+        hasher.update(&head);
+        if size > CHUNK {
+            let tail = self
+                .storage
+                .get_range(&rel_norm, size.saturating_sub(CHUNK).max(CHUNK)..size)
+                .await?
+                .ok_or(OutputError::NotFound)?;
+            hasher.update(&tail);
+        }
+
+        let digest = hasher.finalize();
+        Ok(digest.iter().map(|b| format!("{b:02x}")).collect())
+    }
+
+    /// Permanently remove files. User-facing deletes go through the trash instead.
+    pub async fn purge_rel_files(&self, rel_paths: &[String]) -> Result<usize, OutputError> {
         let mut deleted = 0usize;
         for rel in rel_paths {
             let rel_norm = normalize_rel_path(rel);
-            if !is_safe_rel_path(&rel_norm) {
+            if !is_safe_rel_path(&rel_norm) || is_internal(&rel_norm) {
                 continue;
             }
-            let abs = self.output_dir.join(&rel_norm);
-            match tokio::fs::remove_file(&abs).await {
-                Ok(()) => {
-                    deleted += 1;
-                }
-                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
-                    // ignore
-                }
-                Err(e) => return Err(OutputError::Io(e)),
+            if self.storage.stat(&rel_norm).await?.is_some() {
+                self.storage.delete(&rel_norm).await?;
+                deleted += 1;
             }
-            // Best-effort: clean empty parent dirs up to outputs root.
-            let _ = cleanup_empty_parents(&self.output_dir, abs.parent()).await;
+            self.remove_thumbnails(&rel_norm).await;
         }
+        self.forget_metadata(rel_paths).await;
         Ok(deleted)
     }
+}
 
-    async fn save_counters(&self) -> Result<(), OutputError> {
-        let map = self.counters.lock().await;
-        let bytes = serde_json::to_vec_pretty(&*map)?;
-        tokio::fs::write(&self.counters_path, bytes).await?;
-        Ok(())
-    }
+pub fn paginate<T>(items: Vec<T>, limit: usize, offset: usize) -> (Vec<T>, bool, usize) {
+    let total = items.len();
+    let sliced: Vec<T> = items.into_iter().skip(offset).take(limit).collect();
+    let next_offset = offset + sliced.len();
+    let has_more = next_offset < total;
+    (sliced, has_more, next_offset)
 }
 
 fn is_date_component(s: &str) -> bool {
@@ -215,13 +381,13 @@ fn is_date_component(s: &str) -> bool {
         && b[8..10].iter().all(|c| c.is_ascii_digit())
 }
 
-fn output_item_from_rel(rel: &str) -> OutputItem {
-    let rel_norm = normalize_rel_path(rel);
+fn output_item_from_object(object: &StoredObject) -> OutputItem {
+    let rel_norm = normalize_rel_path(&object.key);
     let parts: Vec<&str> = rel_norm.split('/').filter(|s| !s.is_empty()).collect();
     let filename = parts.last().copied().unwrap_or("").to_string();
 
     // Default: <op>/<date>/<file>
-    let mut op_type = parts.get(0).copied().unwrap_or("").to_string();
+    let mut op_type = parts.first().copied().unwrap_or("").to_string();
     let mut date = parts.get(1).copied().unwrap_or("").to_string();
 
     // Director: prefer grouping as director/<type> when possible.
@@ -242,10 +408,14 @@ fn output_item_from_rel(rel: &str) -> OutputItem {
     }
 
     OutputItem {
+        thumb_url: thumb_url(&rel_norm, DEFAULT_THUMB_WIDTH),
         path: rel_norm,
         op_type,
         date,
         filename,
+        size: object.size,
+        modified_ms: object.modified_ms,
+        annotation: OutputAnnotation::default(),
     }
 }
 
@@ -299,7 +469,7 @@ fn sanitize_rel_path(rel: &str) -> String {
     parts.join("/")
 }
 
-fn normalize_rel_path(rel: &str) -> String {
+pub fn normalize_rel_path(rel: &str) -> String {
     rel.replace('\\', "/")
 }
 
@@ -310,7 +480,7 @@ fn normalize_rel_dir_key(dir: &Path) -> String {
         .to_string()
 }
 
-fn is_safe_rel_path(rel: &str) -> bool {
+pub fn is_safe_rel_path(rel: &str) -> bool {
     let p = Path::new(rel);
     for c in p.components() {
         match c {
@@ -321,7 +491,10 @@ fn is_safe_rel_path(rel: &str) -> bool {
     true
 }
 
-async fn cleanup_empty_parents(root: &Path, start: Option<&Path>) -> Result<(), std::io::Error> {
+pub(crate) async fn cleanup_empty_parents(
+    root: &Path,
+    start: Option<&Path>,
+) -> Result<(), std::io::Error> {
     let Some(mut cur) = start.map(|p| p.to_path_buf()) else {
         return Ok(());
     };
@@ -352,70 +525,36 @@ async fn cleanup_empty_parents(root: &Path, start: Option<&Path>) -> Result<(),
     Ok(())
 }
 
-fn load_counters_sync(path: &Path) -> Result<BTreeMap<String, usize>, OutputError> {
-    if !path.exists() {
-        return Ok(BTreeMap::new());
-    }
-    let bytes = std::fs::read(path)?;
-    let map: BTreeMap<String, usize> = serde_json::from_slice(&bytes)?;
-    Ok(map)
-}
-
-fn save_counters_sync(path: &Path, map: &BTreeMap<String, usize>) -> Result<(), OutputError> {
-    let bytes = serde_json::to_vec_pretty(map)?;
-    std::fs::write(path, bytes)?;
-    Ok(())
-}
-
-fn scan_existing_outputs_sync(
-    root: &Path,
-    counters: &mut BTreeMap<String, usize>,
-) -> Result<bool, OutputError> {
-    if !root.exists() {
-        return Ok(false);
-    }
-    let mut changed = false;
-    scan_dir_sync(root, root, counters, &mut changed)?;
-    Ok(changed)
-}
-
-fn scan_dir_sync(
-    root: &Path,
-    dir: &Path,
-    counters: &mut BTreeMap<String, usize>,
-    changed: &mut bool,
-) -> Result<(), OutputError> {
-    for entry in std::fs::read_dir(dir)? {
-        let entry = entry?;
-        let p = entry.path();
-        if p.is_dir() {
-            scan_dir_sync(root, &p, counters, changed)?;
-            continue;
-        }
-        if p.extension().and_then(|s| s.to_str()) != Some("png") {
+/// Next index per leaf dir implied by the files already stored.
+fn scan_existing_outputs(objects: &[StoredObject]) -> BTreeMap<String, usize> {
+    let mut floors = BTreeMap::new();
+    for o in objects {
+        let p = Path::new(&o.key);
+        if !is_output_file(p) {
             continue;
         }
-        let Ok(rel) = p.strip_prefix(root) else {
-            continue;
-        };
         let Some(file_name) = p.file_name().and_then(|s| s.to_str()) else {
             continue;
         };
         let Some(idx) = parse_output_index(file_name) else {
             continue;
         };
-        let dir_key = rel
+        let dir_key = p
             .parent()
             .map(|p| p.to_string_lossy().replace('\\', "/"))
             .unwrap_or_default();
         let next = idx + 1;
-        let cur = counters.get(&dir_key).copied().unwrap_or(0);
+        let cur = floors.get(&dir_key).copied().unwrap_or(0);
         if next > cur {
-            counters.insert(dir_key, next);
-            *changed = true;
+            floors.insert(dir_key, next);
         }
     }
-    Ok(())
+    floors
+}
+
+/// Paths under dot-directories (thumbnail cache, trash) are internal.
+pub fn is_internal(rel_norm: &str) -> bool {
+    rel_norm.split('/').any(|c| c.starts_with('.'))
 }
 
 fn parse_output_index(file_name: &str) -> Option<usize> {
@@ -429,32 +568,3 @@ fn parse_output_index(file_name: &str) -> Option<usize> {
 
     None
 }
-
-fn walk_pngs(
-    root: &Path,
-    dir: &Path,
-    out: &mut Vec<String>,
-    limit: usize,
-) -> Result<(), OutputError> {
-    if out.len() >= limit {
-        return Ok(());
-    }
-    for entry in std::fs::read_dir(dir)? {
-        let entry = entry?;
-        let p = entry.path();
-        if p.is_dir() {
-            walk_pngs(root, &p, out, limit)?;
-            if out.len() >= limit {
-                return Ok(());
-            }
-        } else if p.extension().and_then(|s| s.to_str()) == Some("png") {
-            if let Ok(rel) = p.strip_prefix(root) {
-                out.push(rel.to_string_lossy().replace('\\', "/"));
-            }
-            if out.len() >= limit {
-                return Ok(());
-            }
-        }
-    }
-    Ok(())
-}
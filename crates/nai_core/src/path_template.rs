@@ -0,0 +1,216 @@
+use chrono::{DateTime, Local, format::StrftimeItems};
+use uuid::Uuid;
+
+use crate::config::ConfigError;
+
+/// Default strftime format for `<日期>` / `<date>`; the gallery groups by this.
+const DEFAULT_DATE_FORMAT: &str = "%Y-%m-%d";
+/// Tags used by `<prompt>` when no count is given.
+const DEFAULT_PROMPT_TAGS: usize = 3;
+const MAX_PROMPT_SLUG_CHARS: usize = 80;
+
+/// Parsed output path template, e.g. `<type>/<date>/<index>_<random>_<seed>`.
+///
+/// Placeholders accept the original Chinese names or English aliases. Some take an
+/// argument after a colon: `<date:%Y-%m>` (strftime) and `<prompt:5>` (tag count).
+#[derive(Debug, Clone)]
+pub struct PathTemplate {
+    source: String,
+    segments: Vec<Segment>,
+}
+
+#[derive(Debug, Clone)]
+enum Segment {
+    Literal(String),
+    Placeholder(Placeholder),
+}
+
+#[derive(Debug, Clone)]
+enum Placeholder {
+    Kind,
+    Date(String),
+    Index,
+    Random,
+    Seed,
+    Model,
+    Sampler,
+    JobId,
+    Size,
+    Prompt(usize),
+    Preset,
+}
+
+/// Values available to a template when saving one image.
+///
+/// Fields that don't apply (e.g. `model` for director tools) render as empty strings.
+#[derive(Debug, Clone, Default)]
+pub struct PathContext<'a> {
+    pub kind: &'a str,
+    pub seed: u64,
+    pub model: Option<&'a str>,
+    pub sampler: Option<&'a str>,
+    pub job_id: Option<Uuid>,
+    pub size: Option<(u32, u32)>,
+    pub prompt: Option<&'a str>,
+    pub preset: Option<&'a str>,
+}
+
+impl<'a> PathContext<'a> {
+    pub fn new(kind: &'a str, seed: u64) -> Self {
+        Self {
+            kind,
+            seed,
+            ..Default::default()
+        }
+    }
+}
+
+impl PathTemplate {
+    pub fn parse(source: &str) -> Result<Self, ConfigError> {
+        let mut segments = Vec::new();
+        let mut rest = source;
+        while let Some(start) = rest.find('<') {
+            if start > 0 {
+                segments.push(Segment::Literal(rest[..start].to_string()));
+            }
+            let after = &rest[start + 1..];
+            let end = after.find('>').ok_or_else(|| {
+                ConfigError::InvalidPathTemplate(format!("unclosed placeholder in {source:?}"))
+            })?;
+            segments.push(Segment::Placeholder(parse_placeholder(&after[..end])?));
+            rest = &after[end + 1..];
+        }
+        if !rest.is_empty() {
+            segments.push(Segment::Literal(rest.to_string()));
+        }
+        Ok(Self {
+            source: source.to_string(),
+            segments,
+        })
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.source
+    }
+
+    /// Render a relative path. `index` and `random` are passed in so the caller can
+    /// render twice (placeholder index, then the real one) with identical other parts.
+    pub fn render(
+        &self,
+        ctx: &PathContext<'_>,
+        now: &DateTime<Local>,
+        index: &str,
+        random: &str,
+    ) -> String {
+        let mut out = String::new();
+        for seg in &self.segments {
+            match seg {
+                Segment::Literal(s) => out.push_str(s),
+                Segment::Placeholder(p) => match p {
+                    // Kind may intentionally contain '/' (e.g. `director/remove_bg/0`).
+                    Placeholder::Kind => out.push_str(ctx.kind),
+                    Placeholder::Date(fmt) => out.push_str(&now.format(fmt).to_string()),
+                    Placeholder::Index => out.push_str(index),
+                    Placeholder::Random => out.push_str(random),
+                    Placeholder::Seed => out.push_str(&ctx.seed.to_string()),
+                    Placeholder::Model => out.push_str(&flatten(ctx.model.unwrap_or(""))),
+                    Placeholder::Sampler => out.push_str(&flatten(ctx.sampler.unwrap_or(""))),
+                    Placeholder::JobId => {
+                        if let Some(id) = ctx.job_id {
+                            out.push_str(&id.to_string());
+                        }
+                    }
+                    Placeholder::Size => {
+                        if let Some((w, h)) = ctx.size {
+                            out.push_str(&format!("{w}x{h}"));
+                        }
+                    }
+                    Placeholder::Prompt(n) => {
+                        out.push_str(&prompt_slug(ctx.prompt.unwrap_or(""), *n))
+                    }
+                    Placeholder::Preset => out.push_str(&flatten(ctx.preset.unwrap_or(""))),
+                },
+            }
+        }
+        out
+    }
+}
+
+fn parse_placeholder(raw: &str) -> Result<Placeholder, ConfigError> {
+    let (name, arg) = match raw.split_once(':') {
+        Some((n, a)) => (n.trim(), Some(a)),
+        None => (raw.trim(), None),
+    };
+    let invalid = |why: &str| ConfigError::InvalidPathTemplate(format!("<{raw}>: {why}"));
+    let no_arg = |p: Placeholder| match arg {
+        Some(_) => Err(invalid("takes no argument")),
+        None => Ok(p),
+    };
+
+    match name {
+        "类型" | "type" | "kind" => no_arg(Placeholder::Kind),
+        "日期" | "date" => {
+            let fmt = arg.unwrap_or(DEFAULT_DATE_FORMAT);
+            if fmt.is_empty()
+                || StrftimeItems::new(fmt).any(|i| matches!(i, chrono::format::Item::Error))
+            {
+                return Err(invalid("invalid date format"));
+            }
+            Ok(Placeholder::Date(fmt.to_string()))
+        }
+        "编号" | "index" => no_arg(Placeholder::Index),
+        "随机字符" | "random" => no_arg(Placeholder::Random),
+        "种子" | "seed" => no_arg(Placeholder::Seed),
+        "模型" | "model" => no_arg(Placeholder::Model),
+        "采样器" | "sampler" => no_arg(Placeholder::Sampler),
+        "任务" | "job_id" | "job" => no_arg(Placeholder::JobId),
+        "尺寸" | "size" => no_arg(Placeholder::Size),
+        "提示词" | "prompt" => {
+            let n = match arg {
+                Some(a) => a
+                    .trim()
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|&n| n > 0)
+                    .ok_or_else(|| invalid("expected a positive tag count"))?,
+                None => DEFAULT_PROMPT_TAGS,
+            };
+            Ok(Placeholder::Prompt(n))
+        }
+        "预设" | "preset" => no_arg(Placeholder::Preset),
+        _ => Err(invalid("unknown placeholder")),
+    }
+}
+
+/// Keep a value inside one path component.
+fn flatten(s: &str) -> String {
+    s.replace(['/', '\\'], "_")
+}
+
+/// First `n` prompt tags as a filename-friendly slug, e.g. `1girl_solo_long_hair`.
+fn prompt_slug(prompt: &str, n: usize) -> String {
+    let tags = prompt
+        .split([',', '\n'])
+        .map(|t| {
+            // Drop weighting syntax such as `{tag}`, `[tag]` and `1.2::tag::`.
+            let t = t.rsplit("::").find(|p| !p.trim().is_empty()).unwrap_or("");
+            t.chars()
+                .filter(|c| !matches!(c, '{' | '}' | '[' | ']' | '(' | ')'))
+                .collect::<String>()
+        })
+        .map(|t| {
+            t.split_whitespace()
+                .collect::<Vec<_>>()
+                .join("_")
+                .to_lowercase()
+        })
+        .filter(|t| !t.is_empty() && t.parse::<f64>().is_err())
+        .take(n)
+        .collect::<Vec<_>>()
+        .join("_");
+    flatten(&tags)
+        .chars()
+        .filter(|c| c.is_alphanumeric() || matches!(c, '_' | '-'))
+        .take(MAX_PROMPT_SLUG_CHARS)
+        .collect()
+}
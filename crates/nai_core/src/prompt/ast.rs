@@ -0,0 +1,620 @@
+//! NovelAI prompt grammar: `{}` emphasis, `[]` de-emphasis, `1.2::text::` numeric
+//! weights (negative ones too), `|` alternations and line breaks.
+//!
+//! Parsing is lossless: `Prompt::parse(s).to_string() == s` for any input, with
+//! unclosed groups and stray closers kept as they were written.
+
+use std::{fmt, ops::Range};
+
+/// What one level of `{}` multiplies a tag's weight by (and `[]` divides it by).
+pub const EMPHASIS_STEP: f32 = 1.05;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Prompt {
+    pub nodes: Vec<Node>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Node {
+    pub kind: NodeKind,
+    /// Byte range in the parsed text.
+    pub span: Range<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum NodeKind {
+    /// Text between separators and groups, whitespace included.
+    Text(String),
+    Comma,
+    Pipe,
+    Newline,
+    Group(Group),
+    /// A `}`, `]` that closes nothing.
+    Unmatched(char),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Group {
+    pub kind: GroupKind,
+    pub children: Vec<Node>,
+    /// False when the text ended before the closer. An unclosed numeric weight is
+    /// valid NovelAI syntax and runs to the end of the prompt.
+    pub closed: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum GroupKind {
+    /// `{...}`
+    Emphasis,
+    /// `[...]`
+    Deemphasis,
+    /// `1.2::...::`, with the weight as written.
+    Weight(String),
+}
+
+/// A tag with the weight it ends up with, e.g. `hair` at 1.1025 in `{{hair}}`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tag {
+    /// Trimmed, whitespace collapsed.
+    pub text: String,
+    /// Byte range of the trimmed text in the parsed prompt.
+    pub span: Range<usize>,
+    pub weight: f32,
+}
+
+impl GroupKind {
+    pub fn weight(&self) -> f32 {
+        match self {
+            GroupKind::Emphasis => EMPHASIS_STEP,
+            GroupKind::Deemphasis => 1.0 / EMPHASIS_STEP,
+            GroupKind::Weight(raw) => raw.parse().unwrap_or(1.0),
+        }
+    }
+
+    pub(crate) fn open(&self) -> String {
+        match self {
+            GroupKind::Emphasis => "{".to_string(),
+            GroupKind::Deemphasis => "[".to_string(),
+            GroupKind::Weight(raw) => format!("{raw}::"),
+        }
+    }
+
+    pub(crate) fn close(&self) -> &'static str {
+        match self {
+            GroupKind::Emphasis => "}",
+            GroupKind::Deemphasis => "]",
+            GroupKind::Weight(_) => "::",
+        }
+    }
+}
+
+impl Prompt {
+    pub fn parse(text: &str) -> Self {
+        Parser::new(text).run()
+    }
+
+    /// Every tag with its effective weight, in order. Groups split tags, so
+    /// `red {hair}` is `red` and `hair`.
+    pub fn tags(&self) -> Vec<Tag> {
+        fn walk(nodes: &[Node], weight: f32, out: &mut Vec<Tag>) {
+            for node in nodes {
+                match &node.kind {
+                    NodeKind::Text(t) => {
+                        let trimmed = t.trim_start();
+                        let start = node.span.start + (t.len() - trimmed.len());
+                        let trimmed = trimmed.trim_end();
+                        if !trimmed.is_empty() {
+                            out.push(Tag {
+                                text: collapse_whitespace(trimmed),
+                                span: start..start + trimmed.len(),
+                                weight,
+                            });
+                        }
+                    }
+                    NodeKind::Group(g) => walk(&g.children, weight * g.kind.weight(), out),
+                    _ => {}
+                }
+            }
+        }
+        let mut out = Vec::new();
+        walk(&self.nodes, 1.0, &mut out);
+        out
+    }
+
+    /// Collapse whitespace, write commas as `, `, drop repeated commas and trim
+    /// lines and the insides of groups. Line breaks, weights as written and the
+    /// spacing around `|` are kept.
+    pub fn normalized(&self) -> Prompt {
+        let mut out = String::new();
+        write_normalized(&self.nodes, &mut out);
+        Prompt::parse(&out)
+    }
+
+    /// The text without group syntax, as the text encoder reads it.
+    pub fn plain_text(&self) -> String {
+        weighted_runs(&self.nodes)
+            .into_iter()
+            .map(|(_, text)| text)
+            .collect()
+    }
+
+    /// Rewrite `{}`/`[]` emphasis as numeric weights, e.g. `{{a}}, b` as
+    /// `1.1::a::, b`.
+    pub fn to_numeric_weights(&self) -> Prompt {
+        let runs = weighted_runs(&self.normalized().nodes);
+        let mut out = String::new();
+        for (weight, text) in runs {
+            if is_unit(weight) {
+                out.push_str(&text);
+            } else {
+                out.push_str(&format!("{}::{text}::", format_weight(weight)));
+            }
+        }
+        Prompt::parse(&out)
+    }
+
+    /// Rewrite numeric weights as the nearest number of `{}` or `[]`. Weights of
+    /// zero or below have no brace form and stay numeric.
+    pub fn to_brace_weights(&self) -> Prompt {
+        let runs = weighted_runs(&self.normalized().nodes);
+        let mut out = String::new();
+        for (weight, text) in runs {
+            if weight <= 0.0 {
+                out.push_str(&format!("{}::{text}::", format_weight(weight)));
+                continue;
+            }
+            let levels = (weight.ln() / EMPHASIS_STEP.ln()).round() as i32;
+            let (open, close) = if levels >= 0 { ("{", "}") } else { ("[", "]") };
+            let n = levels.unsigned_abs() as usize;
+            out.push_str(&open.repeat(n));
+            out.push_str(&text);
+            out.push_str(&close.repeat(n));
+        }
+        Prompt::parse(&out)
+    }
+}
+
+impl fmt::Display for Prompt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn write(nodes: &[Node], f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            for node in nodes {
+                match &node.kind {
+                    NodeKind::Text(t) => f.write_str(t)?,
+                    NodeKind::Comma => f.write_str(",")?,
+                    NodeKind::Pipe => f.write_str("|")?,
+                    NodeKind::Newline => f.write_str("\n")?,
+                    NodeKind::Unmatched(c) => write!(f, "{c}")?,
+                    NodeKind::Group(g) => {
+                        f.write_str(&g.kind.open())?;
+                        write(&g.children, f)?;
+                        if g.closed {
+                            f.write_str(g.kind.close())?;
+                        }
+                    }
+                }
+            }
+            Ok(())
+        }
+        write(&self.nodes, f)
+    }
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    pos: usize,
+    /// Open groups with their start offsets; the root list is `root`.
+    stack: Vec<(GroupKind, usize, Vec<Node>)>,
+    root: Vec<Node>,
+    text_start: Option<usize>,
+}
+
+impl<'a> Parser<'a> {
+    fn new(text: &'a str) -> Self {
+        Self {
+            text,
+            pos: 0,
+            stack: Vec::new(),
+            root: Vec::new(),
+            text_start: None,
+        }
+    }
+
+    fn run(mut self) -> Prompt {
+        while self.pos < self.text.len() {
+            let rest = &self.text[self.pos..];
+            let c = rest.chars().next().expect("non-empty rest");
+            match c {
+                '{' => self.open(GroupKind::Emphasis, 1),
+                '[' => self.open(GroupKind::Deemphasis, 1),
+                '}' => self.close_bracket(GroupKind::Emphasis, c),
+                ']' => self.close_bracket(GroupKind::Deemphasis, c),
+                ',' => self.single(NodeKind::Comma),
+                '|' => self.single(NodeKind::Pipe),
+                '\n' => self.single(NodeKind::Newline),
+                ':' if rest.starts_with("::")
+                    && matches!(self.stack.last(), Some((GroupKind::Weight(_), ..))) =>
+                {
+                    self.flush_text();
+                    self.pos += 2;
+                    self.close_top(true);
+                }
+                _ => {
+                    if let Some(len) = self.weight_opener(rest) {
+                        let raw = rest[..len - 2].to_string();
+                        self.open(GroupKind::Weight(raw), len);
+                    } else {
+                        self.text_start.get_or_insert(self.pos);
+                        self.pos += c.len_utf8();
+                    }
+                }
+            }
+        }
+        self.flush_text();
+        while !self.stack.is_empty() {
+            self.close_top(false);
+        }
+        Prompt { nodes: self.root }
+    }
+
+    /// Length of a `-1.5::` opener at the start of `rest`, if there is one and it
+    /// doesn't continue a word.
+    fn weight_opener(&self, rest: &str) -> Option<usize> {
+        let prev = self.text[..self.pos].chars().next_back();
+        if prev.is_some_and(|p| p.is_alphanumeric() || p == '.' || p == ':') {
+            return None;
+        }
+        let bytes = rest.as_bytes();
+        let mut i = usize::from(bytes.first() == Some(&b'-'));
+        let digits_start = i;
+        let mut seen_dot = false;
+        while i < bytes.len() && (bytes[i].is_ascii_digit() || (bytes[i] == b'.' && !seen_dot)) {
+            seen_dot |= bytes[i] == b'.';
+            i += 1;
+        }
+        let number = &rest[digits_start..i];
+        if !number.bytes().any(|b| b.is_ascii_digit()) || !rest[i..].starts_with("::") {
+            return None;
+        }
+        Some(i + 2)
+    }
+
+    fn children(&mut self) -> &mut Vec<Node> {
+        match self.stack.last_mut() {
+            Some((_, _, children)) => children,
+            None => &mut self.root,
+        }
+    }
+
+    fn flush_text(&mut self) {
+        if let Some(start) = self.text_start.take() {
+            let node = Node {
+                kind: NodeKind::Text(self.text[start..self.pos].to_string()),
+                span: start..self.pos,
+            };
+            self.children().push(node);
+        }
+    }
+
+    fn single(&mut self, kind: NodeKind) {
+        self.flush_text();
+        let span = self.pos..self.pos + 1;
+        self.pos += 1;
+        self.children().push(Node { kind, span });
+    }
+
+    fn open(&mut self, kind: GroupKind, len: usize) {
+        self.flush_text();
+        self.stack.push((kind, self.pos, Vec::new()));
+        self.pos += len;
+    }
+
+    /// `}` or `]`: closes the innermost group of that kind when nothing but
+    /// numeric weights is open inside it; those end with it, unclosed.
+    fn close_bracket(&mut self, kind: GroupKind, c: char) {
+        self.flush_text();
+        let target = self
+            .stack
+            .iter()
+            .rposition(|(k, ..)| !matches!(k, GroupKind::Weight(_)));
+        match target {
+            Some(idx) if self.stack[idx].0 == kind => {
+                while self.stack.len() > idx + 1 {
+                    self.close_top(false);
+                }
+                self.pos += 1;
+                self.close_top(true);
+            }
+            _ => self.single(NodeKind::Unmatched(c)),
+        }
+    }
+
+    fn close_top(&mut self, closed: bool) {
+        let (kind, start, children) = self.stack.pop().expect("open group");
+        let end = if closed {
+            self.pos
+        } else {
+            children
+                .last()
+                .map_or(start + kind.open().len(), |n| n.span.end)
+        };
+        let node = Node {
+            kind: NodeKind::Group(Group {
+                kind,
+                children,
+                closed,
+            }),
+            span: start..end,
+        };
+        self.children().push(node);
+    }
+}
+
+fn collapse_whitespace(s: &str) -> String {
+    s.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn is_unit(weight: f32) -> bool {
+    (weight - 1.0).abs() < 1e-3
+}
+
+/// Two decimals at most: `1.1`, `0.95`, `-0.8`.
+pub fn format_weight(weight: f32) -> String {
+    let s = format!("{weight:.2}");
+    let s = s.trim_end_matches('0').trim_end_matches('.');
+    if s == "-0" {
+        "0".to_string()
+    } else {
+        s.to_string()
+    }
+}
+
+/// Separator and whitespace state while writing one line or group.
+#[derive(Default)]
+struct LineState {
+    comma: bool,
+    space: bool,
+    content: bool,
+}
+
+impl LineState {
+    /// Before writing content: a pending comma becomes `, `, pending whitespace
+    /// one space, except at the start of the line or group.
+    fn flush(&mut self, out: &mut String) {
+        if self.comma {
+            out.push_str(", ");
+        } else if self.space && self.content {
+            out.push(' ');
+        }
+        self.comma = false;
+        self.space = false;
+        self.content = true;
+    }
+
+    /// At the end of a line or group a trailing comma is kept, whitespace isn't.
+    fn finish(&mut self, out: &mut String) {
+        if self.comma {
+            out.push(',');
+        }
+        *self = LineState::default();
+    }
+}
+
+fn write_normalized(nodes: &[Node], out: &mut String) {
+    let mut st = LineState::default();
+    for node in nodes {
+        match &node.kind {
+            NodeKind::Text(t) => {
+                for c in t.chars() {
+                    if c.is_whitespace() {
+                        st.space = true;
+                    } else {
+                        st.flush(out);
+                        out.push(c);
+                    }
+                }
+            }
+            NodeKind::Comma => st.comma = true,
+            NodeKind::Newline => {
+                st.finish(out);
+                out.push('\n');
+            }
+            NodeKind::Pipe => {
+                st.flush(out);
+                out.push('|');
+            }
+            NodeKind::Unmatched(c) => {
+                st.flush(out);
+                out.push(*c);
+            }
+            NodeKind::Group(g) => {
+                st.flush(out);
+                out.push_str(&g.kind.open());
+                write_normalized(&g.children, out);
+                if g.closed {
+                    out.push_str(g.kind.close());
+                }
+            }
+        }
+    }
+    st.finish(out);
+}
+
+/// The prompt as text runs of equal effective weight, without group syntax.
+fn weighted_runs(nodes: &[Node]) -> Vec<(f32, String)> {
+    fn walk(nodes: &[Node], weight: f32, out: &mut Vec<(f32, String)>) {
+        for node in nodes {
+            let piece = match &node.kind {
+                NodeKind::Group(g) => {
+                    walk(&g.children, weight * g.kind.weight(), out);
+                    continue;
+                }
+                NodeKind::Text(t) => t.clone(),
+                NodeKind::Comma => ",".to_string(),
+                NodeKind::Pipe => "|".to_string(),
+                NodeKind::Newline => "\n".to_string(),
+                NodeKind::Unmatched(c) => c.to_string(),
+            };
+            match out.last_mut() {
+                Some((w, text)) if (*w - weight).abs() < 1e-3 => text.push_str(&piece),
+                _ => out.push((weight, piece)),
+            }
+        }
+    }
+    let mut runs = Vec::new();
+    walk(nodes, 1.0, &mut runs);
+    // Keep separators and spacing at the edges of a weighted run outside it:
+    // `{a}, b` is `1.05::a::, b`, not `1.05::a, ::b`.
+    let mut out: Vec<(f32, String)> = Vec::new();
+    for (weight, text) in runs {
+        if is_unit(weight) {
+            push_run(&mut out, 1.0, &text);
+            continue;
+        }
+        let is_edge = |c: char| c.is_whitespace() || c == ',';
+        let start = text.len() - text.trim_start_matches(is_edge).len();
+        let end = text.trim_end_matches(is_edge).len().max(start);
+        push_run(&mut out, 1.0, &text[..start]);
+        push_run(&mut out, weight, &text[start..end]);
+        push_run(&mut out, 1.0, &text[end..]);
+    }
+    out
+}
+
+fn push_run(out: &mut Vec<(f32, String)>, weight: f32, text: &str) {
+    if text.is_empty() {
+        return;
+    }
+    match out.last_mut() {
+        Some((w, t)) if (*w - weight).abs() < 1e-3 => t.push_str(text),
+        _ => out.push((weight, text.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn group(prompt: &Prompt, i: usize) -> &Group {
+        match &prompt.nodes[i].kind {
+            NodeKind::Group(g) => g,
+            other => panic!("expected a group, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn round_trips() {
+        for text in [
+            "",
+            "masterpiece, {{best quality}}, [[blurry]]",
+            "1.2::red hair::, -0.5::hat::, 2::unclosed",
+            "a}, ]b[ {c",
+            "{a|b|}, [x | y]\nsecond line,, ",
+            "1girl, 0.8::{smile}, [teeth]::, 3.::x::",
+            "白色校服, {{蓝色眼睛}}, 1.1::长发::",
+            "ab::cd::, a1.2::x::, ::",
+        ] {
+            assert_eq!(Prompt::parse(text).to_string(), text);
+        }
+    }
+
+    #[test]
+    fn unmatched_brackets() {
+        let p = Prompt::parse("a}, [b");
+        assert_eq!(p.nodes[1].kind, NodeKind::Unmatched('}'));
+        let g = group(&p, 4);
+        assert_eq!(g.kind, GroupKind::Deemphasis);
+        assert!(!g.closed);
+        assert_eq!(p.nodes[4].span, 4..6);
+
+        // `}` can't close the `{` outside an open `[`; the `{` stays unclosed.
+        let p = Prompt::parse("{a [b} c]");
+        let g = group(&p, 0);
+        assert!(!g.closed);
+        assert_eq!(g.kind, GroupKind::Emphasis);
+        let inner = match &g.children[1].kind {
+            NodeKind::Group(g) => g,
+            other => panic!("expected a group, got {other:?}"),
+        };
+        assert!(inner.closed);
+        assert!(inner.children.contains(&Node {
+            kind: NodeKind::Unmatched('}'),
+            span: 5..6,
+        }));
+    }
+
+    #[test]
+    fn numeric_weights() {
+        let p = Prompt::parse("1.2::x::, y");
+        let g = group(&p, 0);
+        assert_eq!(g.kind, GroupKind::Weight("1.2".to_string()));
+        assert!(g.closed);
+        assert_eq!(p.nodes[0].span, 0..8);
+
+        let tags = p.tags();
+        assert_eq!(tags[0].text, "x");
+        assert!((tags[0].weight - 1.2).abs() < 1e-6);
+        assert_eq!(tags[1].weight, 1.0);
+
+        let p = Prompt::parse("-0.5::x");
+        let g = group(&p, 0);
+        assert_eq!(g.kind, GroupKind::Weight("-0.5".to_string()));
+        assert!(!g.closed);
+
+        // A number continuing a word isn't a weight.
+        let p = Prompt::parse("a1.2::x::");
+        assert!(
+            p.nodes
+                .iter()
+                .all(|n| !matches!(n.kind, NodeKind::Group(_)))
+        );
+
+        // `}` ends a weight opened inside the emphasis with it.
+        let p = Prompt::parse("{1.5::a} b");
+        let g = group(&p, 0);
+        assert!(g.closed);
+        match &g.children[0].kind {
+            NodeKind::Group(w) => assert!(!w.closed),
+            other => panic!("expected a group, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn effective_weights() {
+        let tags = Prompt::parse("{{hair}}, [eyes], 1.5::{a}::").tags();
+        let weights: Vec<f32> = tags.iter().map(|t| t.weight).collect();
+        assert!((weights[0] - 1.1025).abs() < 1e-4);
+        assert!((weights[1] - 1.0 / 1.05).abs() < 1e-4);
+        assert!((weights[2] - 1.575).abs() < 1e-4);
+    }
+
+    #[test]
+    fn normalizes() {
+        let p = Prompt::parse("a ,b,,  c\n { d ,e } ,\n1.2::  f  ::");
+        assert_eq!(p.normalized().to_string(), "a, b, c\n{d, e},\n1.2::f::");
+    }
+
+    #[test]
+    fn converts_weights() {
+        let p = Prompt::parse("{{a}}, b, [c]");
+        assert_eq!(p.to_numeric_weights().to_string(), "1.1::a::, b, 0.95::c::");
+
+        let p = Prompt::parse("1.1::a::, b, 0.95::c::, 1.16::d::");
+        assert_eq!(p.to_brace_weights().to_string(), "{{a}}, b, [c], {{{d}}}");
+
+        // No brace form for zero or negative weights.
+        let p = Prompt::parse("-1::a::, 0::b::");
+        assert_eq!(p.to_brace_weights().to_string(), "-1::a::, 0::b::");
+
+        let text = "{{a}}, [[b]], c";
+        let p = Prompt::parse(text).to_numeric_weights().to_brace_weights();
+        assert_eq!(p.to_string(), text);
+    }
+
+    #[test]
+    fn formats_weights() {
+        assert_eq!(format_weight(1.1025), "1.1");
+        assert_eq!(format_weight(0.95), "0.95");
+        assert_eq!(format_weight(2.0), "2");
+        assert_eq!(format_weight(-0.001), "0");
+    }
+}
@@ -0,0 +1,386 @@
+//! Dynamic prompt syntax: `{red|blue|green}` choices and `__name__` wildcards.
+//!
+//! Built on the [`ast`] parse, so choices follow the same grouping as emphasis:
+//! a closed `{}` group with a top-level `|` is a choice, any other group is kept
+//! as it is, so `{{masterpiece}}, {red|blue} hair` only picks between red and
+//! blue. Unbalanced brackets never form a choice.
+
+use std::collections::HashMap;
+
+use rand::{Rng, seq::IndexedRandom};
+
+use super::ast::{self, GroupKind, NodeKind};
+
+/// Choices and wildcards a job may expand into when generating every combination.
+pub const MAX_COMBINATIONS: usize = 100;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Node {
+    Text(String),
+    /// Emphasis, de-emphasis or a numeric weight, rendered with its syntax.
+    /// `close` is empty for a group left open.
+    Group {
+        open: String,
+        children: Vec<Node>,
+        close: &'static str,
+    },
+    /// `{a|b|c}`; options may be empty.
+    Choice(Vec<Vec<Node>>),
+    /// `__name__`
+    Wildcard(String),
+}
+
+pub fn parse(text: &str) -> Vec<Node> {
+    let mut out = Vec::new();
+    convert(&ast::Prompt::parse(text).nodes, &mut out);
+    out
+}
+
+/// Whether the text has choices or wildcards.
+pub fn is_dynamic(text: &str) -> bool {
+    fn any(nodes: &[Node]) -> bool {
+        nodes.iter().any(|n| match n {
+            Node::Text(_) => false,
+            Node::Group { children, .. } => any(children),
+            Node::Choice(_) | Node::Wildcard(_) => true,
+        })
+    }
+    any(&parse(text))
+}
+
+/// Wildcard names used directly by `nodes`, not by the wildcards' own lines.
+pub fn wildcard_names(nodes: &[Node]) -> Vec<&str> {
+    fn walk<'a>(nodes: &'a [Node], out: &mut Vec<&'a str>) {
+        for n in nodes {
+            match n {
+                Node::Text(_) => {}
+                Node::Group { children, .. } => walk(children, out),
+                Node::Choice(options) => options.iter().for_each(|o| walk(o, out)),
+                Node::Wildcard(name) => out.push(name),
+            }
+        }
+    }
+    let mut out = Vec::new();
+    walk(nodes, &mut out);
+    out
+}
+
+fn convert(nodes: &[ast::Node], out: &mut Vec<Node>) {
+    let is_pipe = |n: &ast::Node| n.kind == NodeKind::Pipe;
+    for node in nodes {
+        match &node.kind {
+            NodeKind::Text(t) => convert_text(t, out),
+            NodeKind::Comma => push_text(out, ","),
+            NodeKind::Pipe => push_text(out, "|"),
+            NodeKind::Newline => push_text(out, "\n"),
+            NodeKind::Unmatched(c) => push_text(out, c.encode_utf8(&mut [0; 4])),
+            NodeKind::Group(g)
+                if g.kind == GroupKind::Emphasis && g.closed && g.children.iter().any(is_pipe) =>
+            {
+                let options = g
+                    .children
+                    .split(is_pipe)
+                    .map(|option| {
+                        let mut nodes = Vec::new();
+                        convert(option, &mut nodes);
+                        nodes
+                    })
+                    .collect();
+                out.push(Node::Choice(options));
+            }
+            NodeKind::Group(g) => {
+                let mut children = Vec::new();
+                convert(&g.children, &mut children);
+                out.push(Node::Group {
+                    open: g.kind.open(),
+                    children,
+                    close: if g.closed { g.kind.close() } else { "" },
+                });
+            }
+        }
+    }
+}
+
+/// Text with `__name__` wildcards split out.
+fn convert_text(text: &str, out: &mut Vec<Node>) {
+    let mut plain = 0;
+    let mut i = 0;
+    while i < text.len() {
+        let rest = &text[i..];
+        if let Some(name) = rest.strip_prefix("__").and_then(wildcard_name) {
+            push_text(out, &text[plain..i]);
+            out.push(Node::Wildcard(name.to_string()));
+            i += name.len() + 4;
+            plain = i;
+            continue;
+        }
+        i += rest.chars().next().expect("non-empty rest").len_utf8();
+    }
+    push_text(out, &text[plain..]);
+}
+
+fn push_text(out: &mut Vec<Node>, text: &str) {
+    if text.is_empty() {
+        return;
+    }
+    match out.last_mut() {
+        Some(Node::Text(t)) => t.push_str(text),
+        _ => out.push(Node::Text(text.to_string())),
+    }
+}
+
+/// The name of a `__name__` token whose leading `__` was already consumed; the
+/// shortest match wins, as in `__a__b__`.
+fn wildcard_name(s: &str) -> Option<&str> {
+    if !s.starts_with(|c: char| c.is_ascii_alphanumeric()) {
+        return None;
+    }
+    for (i, c) in s.char_indices().skip(1) {
+        if s[i..].starts_with("__") {
+            return Some(&s[..i]);
+        }
+        if !(c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '/' | '.')) {
+            return None;
+        }
+    }
+    None
+}
+
+/// Wildcard lines by name, parsed; `None` for unknown wildcards.
+pub type WildcardLines = HashMap<String, Option<Vec<Vec<Node>>>>;
+
+/// Renders parsed prompts, either with random picks or as the n-th of every
+/// combination. Wildcards must already be loaded into `wildcards`.
+pub struct Expander<'a> {
+    wildcards: &'a WildcardLines,
+    max_depth: usize,
+    max_expansions: usize,
+    expansions: usize,
+    counts: HashMap<(&'a str, usize), u64>,
+    pub warnings: Vec<String>,
+}
+
+impl<'a> Expander<'a> {
+    pub fn new(wildcards: &'a WildcardLines, max_depth: usize, max_expansions: usize) -> Self {
+        Self {
+            wildcards,
+            max_depth,
+            max_expansions,
+            expansions: 0,
+            counts: HashMap::new(),
+            warnings: Vec::new(),
+        }
+    }
+
+    /// Pick one option of every choice and one line of every wildcard.
+    pub fn random(&mut self, nodes: &'a [Node], rng: &mut impl Rng) -> String {
+        let mut out = String::new();
+        self.random_into(nodes, 0, rng, &mut out);
+        out
+    }
+
+    /// Number of distinct renderings, saturating.
+    pub fn count(&mut self, nodes: &'a [Node]) -> u64 {
+        self.count_at(nodes, 0)
+    }
+
+    /// The `index`-th rendering, with the first node varying fastest.
+    /// Wraps around past `count`.
+    pub fn nth(&mut self, nodes: &'a [Node], index: u64) -> String {
+        let mut out = String::new();
+        let total = self.count_at(nodes, 0);
+        self.nth_into(nodes, 0, index % total, &mut out);
+        out
+    }
+
+    fn lines(&mut self, name: &'a str, depth: usize) -> Option<&'a [Vec<Node>]> {
+        if depth >= self.max_depth {
+            self.warnings
+                .push(format!("递归深度超过 {}，跳过 __{name}__", self.max_depth));
+            return None;
+        }
+        if self.expansions >= self.max_expansions {
+            self.warnings.push(format!(
+                "已达到展开上限({})，跳过 __{name}__",
+                self.max_expansions
+            ));
+            return None;
+        }
+        self.expansions += 1;
+        match self.wildcards.get(name) {
+            Some(Some(lines)) if !lines.is_empty() => Some(lines),
+            Some(Some(_)) => {
+                self.warnings.push(format!("通配符 {name} 为空，已移除"));
+                None
+            }
+            _ => {
+                self.warnings.push(format!("通配符不存在：{name}"));
+                None
+            }
+        }
+    }
+
+    fn random_into(
+        &mut self,
+        nodes: &'a [Node],
+        depth: usize,
+        rng: &mut impl Rng,
+        out: &mut String,
+    ) {
+        for node in nodes {
+            match node {
+                Node::Text(t) => out.push_str(t),
+                Node::Group {
+                    open,
+                    children,
+                    close,
+                } => {
+                    out.push_str(open);
+                    self.random_into(children, depth, rng, out);
+                    out.push_str(close);
+                }
+                Node::Choice(options) => {
+                    if let Some(option) = options.choose(rng) {
+                        self.random_into(option, depth, rng, out);
+                    }
+                }
+                Node::Wildcard(name) => {
+                    if let Some(line) = self.lines(name, depth).and_then(|l| l.choose(rng)) {
+                        self.random_into(line, depth + 1, rng, out);
+                    }
+                }
+            }
+        }
+    }
+
+    fn count_at(&mut self, nodes: &'a [Node], depth: usize) -> u64 {
+        nodes.iter().fold(1u64, |acc, node| {
+            acc.saturating_mul(self.count_node(node, depth))
+        })
+    }
+
+    fn count_node(&mut self, node: &'a Node, depth: usize) -> u64 {
+        match node {
+            Node::Text(_) => 1,
+            Node::Group { children, .. } => self.count_at(children, depth),
+            Node::Choice(options) => options
+                .iter()
+                .fold(0u64, |acc, o| acc.saturating_add(self.count_at(o, depth))),
+            Node::Wildcard(name) => {
+                if let Some(&n) = self.counts.get(&(name.as_str(), depth)) {
+                    return n;
+                }
+                let n = match self.wildcards.get(name) {
+                    Some(Some(lines)) if !lines.is_empty() && depth < self.max_depth => {
+                        lines.iter().fold(0u64, |acc, l| {
+                            acc.saturating_add(self.count_at(l, depth + 1))
+                        })
+                    }
+                    _ => 1,
+                };
+                self.counts.insert((name.as_str(), depth), n);
+                n
+            }
+        }
+    }
+
+    fn nth_into(&mut self, nodes: &'a [Node], depth: usize, mut index: u64, out: &mut String) {
+        for node in nodes {
+            let n = self.count_node(node, depth);
+            let here = index % n;
+            index /= n;
+            match node {
+                Node::Text(t) => out.push_str(t),
+                Node::Group {
+                    open,
+                    children,
+                    close,
+                } => {
+                    out.push_str(open);
+                    self.nth_into(children, depth, here, out);
+                    out.push_str(close);
+                }
+                Node::Choice(options) => {
+                    if let Some((option, rest)) = self.pick_nth(options, depth, here) {
+                        self.nth_into(option, depth, rest, out);
+                    }
+                }
+                Node::Wildcard(name) => {
+                    if let Some(lines) = self.lines(name, depth)
+                        && let Some((line, rest)) = self.pick_nth(lines, depth + 1, here)
+                    {
+                        self.nth_into(line, depth + 1, rest, out);
+                    }
+                }
+            }
+        }
+    }
+
+    /// The alternative containing rendering `index`, and the index within it.
+    fn pick_nth(
+        &mut self,
+        alternatives: &'a [Vec<Node>],
+        depth: usize,
+        mut index: u64,
+    ) -> Option<(&'a [Node], u64)> {
+        for alt in alternatives {
+            let n = self.count_at(alt, depth);
+            if index < n {
+                return Some((alt, index));
+            }
+            index -= n;
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn all(text: &str, wildcards: &WildcardLines) -> Vec<String> {
+        let nodes = parse(text);
+        let mut ex = Expander::new(wildcards, 4, 100);
+        let n = ex.count(&nodes);
+        (0..n).map(|i| ex.nth(&nodes, i)).collect()
+    }
+
+    #[test]
+    fn choices_and_emphasis() {
+        let none = WildcardLines::new();
+        assert_eq!(
+            all("{{masterpiece}}, {red|blue} hair", &none),
+            ["{{masterpiece}}, red hair", "{{masterpiece}}, blue hair"]
+        );
+        assert_eq!(
+            all("[{a|b}], 1.2::{c|}::", &none),
+            [
+                "[a], 1.2::c::",
+                "[b], 1.2::c::",
+                "[a], 1.2::::",
+                "[b], 1.2::::",
+            ]
+        );
+        // Text without choices comes back as written, unbalanced brackets too.
+        let text = "a}, {b | c, [d\n1.5::e";
+        assert!(!is_dynamic(text));
+        assert_eq!(all(text, &none), [text]);
+    }
+
+    #[test]
+    fn wildcards() {
+        let wildcards = WildcardLines::from([
+            (
+                "color".to_string(),
+                Some(vec![parse("red"), parse("{dark|light} blue")]),
+            ),
+            ("missing".to_string(), None),
+        ]);
+        let nodes = parse("__color__ hair, __missing__, a__b");
+        assert_eq!(wildcard_names(&nodes), ["color", "missing"]);
+        assert_eq!(
+            all("{__color__} hair", &wildcards),
+            ["{red} hair", "{dark blue} hair", "{light blue} hair",]
+        );
+    }
+}
@@ -0,0 +1,297 @@
+//! Prompt checks for the editor: bracket balance, duplicate and conflicting
+//! tags, empty groups, unknown snippets and empty character captions.
+
+use std::{collections::HashSet, ops::Range};
+
+use serde::{Deserialize, Serialize};
+
+use super::{
+    ast::{GroupKind, Node, NodeKind, Prompt},
+    snippet::snippet_refs,
+};
+use crate::dto::CharacterPrompt;
+
+/// Tags that shouldn't be in the same prompt, e.g. `["smile", "frown"]`.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ConflictRule {
+    pub tags: Vec<String>,
+    /// Shown instead of the default message.
+    pub message: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    /// The prompt won't do what it says: jobs refuse it when linting on submit.
+    Error,
+    Warning,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LintCode {
+    UnbalancedBracket,
+    DuplicateTag,
+    NegativeOverlap,
+    EmptyGroup,
+    ConflictingTags,
+    UnresolvedSnippet,
+    EmptyCharacterCaption,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LintIssue {
+    /// `positive`, `negative`, `character_prompts/0/prompt` or `character_prompts/0/uc`.
+    pub field: String,
+    /// UTF-16 offsets into the field's text, as JavaScript indexes strings.
+    pub start: usize,
+    pub end: usize,
+    pub code: LintCode,
+    pub severity: Severity,
+    pub message: String,
+}
+
+/// Lint a positive/negative pair and its character prompts.
+///
+/// `snippet_exists` tells whether a `<snippet:name>` resolves.
+pub fn lint(
+    positive: &str,
+    negative: &str,
+    characters: &[CharacterPrompt],
+    rules: &[ConflictRule],
+    snippet_exists: impl Fn(&str) -> bool,
+) -> Vec<LintIssue> {
+    let mut out = Vec::new();
+    lint_pair(
+        ("positive", positive),
+        ("negative", negative),
+        rules,
+        &snippet_exists,
+        &mut out,
+    );
+    for (i, c) in characters.iter().enumerate() {
+        let prompt_field = format!("character_prompts/{i}/prompt");
+        if c.enabled && c.prompt.trim().is_empty() {
+            out.push(LintIssue {
+                field: prompt_field.clone(),
+                start: 0,
+                end: 0,
+                code: LintCode::EmptyCharacterCaption,
+                severity: Severity::Error,
+                message: format!("character {} is enabled but has no prompt", i + 1),
+            });
+        }
+        lint_pair(
+            (&prompt_field, &c.prompt),
+            (&format!("character_prompts/{i}/uc"), &c.uc),
+            rules,
+            &snippet_exists,
+            &mut out,
+        );
+    }
+    // Fields in the order above, then by position.
+    let rank = |field: &str| match field {
+        "positive" => (0, 0),
+        "negative" => (0, 1),
+        _ => {
+            let mut parts = field.split('/').skip(1);
+            let index = parts.next().and_then(|i| i.parse().ok()).unwrap_or(0);
+            (index + 1, usize::from(parts.next() == Some("uc")))
+        }
+    };
+    out.sort_by_key(|i| (rank(&i.field), i.start));
+    out
+}
+
+fn lint_pair(
+    positive: (&str, &str),
+    negative: (&str, &str),
+    rules: &[ConflictRule],
+    snippet_exists: &impl Fn(&str) -> bool,
+    out: &mut Vec<LintIssue>,
+) {
+    let pos = FieldLint::new(positive.0, positive.1);
+    let neg = FieldLint::new(negative.0, negative.1);
+    for field in [&pos, &neg] {
+        field.structure(out);
+        field.duplicates(out);
+        field.snippets(snippet_exists, out);
+    }
+    pos.conflicts(rules, out);
+
+    let negative_tags: HashSet<&str> = neg.tags.iter().map(|(k, _)| k.as_str()).collect();
+    for (key, span) in &pos.tags {
+        if negative_tags.contains(key.as_str()) {
+            out.push(pos.issue(
+                span.clone(),
+                LintCode::NegativeOverlap,
+                Severity::Warning,
+                format!(
+                    "`{}` is also in the negative prompt",
+                    &pos.text[span.clone()]
+                ),
+            ));
+        }
+    }
+}
+
+struct FieldLint<'a> {
+    field: &'a str,
+    text: &'a str,
+    prompt: Prompt,
+    /// Tag keys (lowercase, `_` as space) with their byte spans.
+    tags: Vec<(String, Range<usize>)>,
+}
+
+impl<'a> FieldLint<'a> {
+    fn new(field: &'a str, text: &'a str) -> Self {
+        let prompt = Prompt::parse(text);
+        let tags = prompt
+            .tags()
+            .into_iter()
+            .map(|t| (tag_key(&t.text), t.span))
+            .collect();
+        Self {
+            field,
+            text,
+            prompt,
+            tags,
+        }
+    }
+
+    fn issue(
+        &self,
+        span: Range<usize>,
+        code: LintCode,
+        severity: Severity,
+        message: String,
+    ) -> LintIssue {
+        LintIssue {
+            field: self.field.to_string(),
+            start: utf16_offset(self.text, span.start),
+            end: utf16_offset(self.text, span.end),
+            code,
+            severity,
+            message,
+        }
+    }
+
+    /// Unbalanced brackets and empty groups.
+    fn structure(&self, out: &mut Vec<LintIssue>) {
+        fn walk(lint: &FieldLint<'_>, nodes: &[Node], out: &mut Vec<LintIssue>) {
+            for node in nodes {
+                match &node.kind {
+                    NodeKind::Unmatched(c) => out.push(lint.issue(
+                        node.span.clone(),
+                        LintCode::UnbalancedBracket,
+                        Severity::Error,
+                        format!("`{c}` has no opening bracket"),
+                    )),
+                    NodeKind::Group(g) => {
+                        let opener = match &g.kind {
+                            GroupKind::Emphasis => Some('{'),
+                            GroupKind::Deemphasis => Some('['),
+                            // An unclosed weight runs to the end of the prompt.
+                            GroupKind::Weight(_) => None,
+                        };
+                        if let Some(c) = opener.filter(|_| !g.closed) {
+                            out.push(lint.issue(
+                                node.span.start..node.span.start + 1,
+                                LintCode::UnbalancedBracket,
+                                Severity::Error,
+                                format!("`{c}` is never closed"),
+                            ));
+                        }
+                        let empty = Prompt {
+                            nodes: g.children.clone(),
+                        }
+                        .tags()
+                        .is_empty();
+                        if empty && g.closed {
+                            out.push(lint.issue(
+                                node.span.clone(),
+                                LintCode::EmptyGroup,
+                                Severity::Warning,
+                                "weight group has no tags".to_string(),
+                            ));
+                        }
+                        walk(lint, &g.children, out);
+                    }
+                    _ => {}
+                }
+            }
+        }
+        walk(self, &self.prompt.nodes, out);
+    }
+
+    fn duplicates(&self, out: &mut Vec<LintIssue>) {
+        let mut seen = HashSet::new();
+        for (key, span) in &self.tags {
+            if !seen.insert(key) {
+                out.push(self.issue(
+                    span.clone(),
+                    LintCode::DuplicateTag,
+                    Severity::Warning,
+                    format!("duplicate tag `{}`", &self.text[span.clone()]),
+                ));
+            }
+        }
+    }
+
+    fn snippets(&self, snippet_exists: &impl Fn(&str) -> bool, out: &mut Vec<LintIssue>) {
+        for r in snippet_refs(self.text) {
+            if r.name.is_empty() || !snippet_exists(r.name) {
+                out.push(self.issue(
+                    r.span,
+                    LintCode::UnresolvedSnippet,
+                    Severity::Error,
+                    format!("snippet `{}` does not exist", r.name),
+                ));
+            }
+        }
+    }
+
+    /// Every tag of a rule that has two or more of its tags in the prompt.
+    fn conflicts(&self, rules: &[ConflictRule], out: &mut Vec<LintIssue>) {
+        for rule in rules {
+            let keys: Vec<String> = rule.tags.iter().map(|t| tag_key(t)).collect();
+            let hits: Vec<&(String, Range<usize>)> =
+                self.tags.iter().filter(|(k, _)| keys.contains(k)).collect();
+            let mut distinct: Vec<&str> = hits.iter().map(|(k, _)| k.as_str()).collect();
+            distinct.sort_unstable();
+            distinct.dedup();
+            if distinct.len() < 2 {
+                continue;
+            }
+            for (key, span) in hits {
+                let message = rule.message.clone().unwrap_or_else(|| {
+                    let others: Vec<&str> = distinct.iter().copied().filter(|d| d != key).collect();
+                    format!(
+                        "`{}` conflicts with `{}`",
+                        &self.text[span.clone()],
+                        others.join("`, `")
+                    )
+                });
+                out.push(self.issue(
+                    span.clone(),
+                    LintCode::ConflictingTags,
+                    Severity::Warning,
+                    message,
+                ));
+            }
+        }
+    }
+}
+
+/// `Long_Hair` and `long hair` are the same tag.
+fn tag_key(tag: &str) -> String {
+    tag.to_lowercase()
+        .replace('_', " ")
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn utf16_offset(text: &str, byte: usize) -> usize {
+    text[..byte].encode_utf16().count()
+}
@@ -0,0 +1,16 @@
+use crate::config::AppConfig;
+
+pub mod ast;
+pub mod dynamic;
+pub mod lint;
+pub mod snippet;
+pub mod tags;
+
+pub fn format_str(cfg: &AppConfig, text: &str) -> String {
+    if !cfg.format_input {
+        return text.to_string();
+    }
+
+    // Normalize commas/spaces per line and inside groups, preserve newlines.
+    ast::Prompt::parse(text).normalized().to_string()
+}
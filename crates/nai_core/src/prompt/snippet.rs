@@ -0,0 +1,213 @@
+//! `<snippet:name key=value key="quoted value">` tokens and the `${key}`
+//! placeholders of snippet bodies.
+//!
+//! Snippet names are paths such as `char/alice/outfit`. Inside a snippet, a
+//! name is looked up in the snippet's folder first and then from the root;
+//! a leading `/` looks up from the root only.
+
+use std::ops::Range;
+
+/// A `<snippet:...>` token in a prompt.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SnippetRef<'a> {
+    pub name: &'a str,
+    /// `key=value` arguments in order, quotes removed.
+    pub args: Vec<(&'a str, &'a str)>,
+    /// Arguments that aren't `key=value`.
+    pub malformed: Vec<&'a str>,
+    /// Byte range of the whole token.
+    pub span: Range<usize>,
+}
+
+/// Every `<snippet:...>` token of `text`, in order. A `>` inside a quoted
+/// argument doesn't end the token.
+pub fn snippet_refs(text: &str) -> Vec<SnippetRef<'_>> {
+    let mut out = Vec::new();
+    let mut i = 0;
+    while let Some(off) = text[i..].find('<') {
+        let start = i + off;
+        let rest = &text[start + 1..];
+        let body_start = start + 1 + (rest.len() - rest.trim_start().len());
+        if let Some(after) = text[body_start..].strip_prefix("snippet:")
+            && let Some(close) = closing_bracket(after)
+            && close > 0
+        {
+            let inner_start = body_start + "snippet:".len();
+            let end = inner_start + close + 1;
+            out.push(parse_ref(
+                &text[inner_start..inner_start + close],
+                start..end,
+            ));
+            i = end;
+        } else {
+            i = start + 1;
+        }
+    }
+    out
+}
+
+/// Byte offset of the `>` closing a token whose body starts `s`.
+fn closing_bracket(s: &str) -> Option<usize> {
+    let mut quote = None;
+    for (i, c) in s.char_indices() {
+        match (quote, c) {
+            (None, '>') => return Some(i),
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(q), c) if c == q => quote = None,
+            _ => {}
+        }
+    }
+    None
+}
+
+fn parse_ref(inner: &str, span: Range<usize>) -> SnippetRef<'_> {
+    let inner = inner.trim();
+    let (name, mut rest) = inner.split_once(char::is_whitespace).unwrap_or((inner, ""));
+    let mut args = Vec::new();
+    let mut malformed = Vec::new();
+    loop {
+        rest = rest.trim_start();
+        if rest.is_empty() {
+            break;
+        }
+        let word_end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+        let Some(eq) = rest[..word_end].find('=').filter(|&eq| eq > 0) else {
+            malformed.push(&rest[..word_end]);
+            rest = &rest[word_end..];
+            continue;
+        };
+        let key = &rest[..eq];
+        let value = &rest[eq + 1..];
+        let quote = value.chars().next().filter(|c| matches!(c, '"' | '\''));
+        if let Some(q) = quote
+            && let Some(close) = value[1..].find(q)
+        {
+            args.push((key, &value[1..close + 1]));
+            rest = &value[close + 2..];
+        } else {
+            let end = value.find(char::is_whitespace).unwrap_or(value.len());
+            args.push((key, &value[..end]));
+            rest = &value[end..];
+        }
+    }
+    SnippetRef {
+        name,
+        args,
+        malformed,
+        span,
+    }
+}
+
+/// Replace every `${key}` of `body` with `value(key)`; keys it has no value
+/// for become empty and are returned.
+pub fn substitute<'v>(
+    body: &str,
+    value: impl Fn(&str) -> Option<&'v str>,
+) -> (String, Vec<String>) {
+    let mut out = String::with_capacity(body.len());
+    let mut missing: Vec<String> = Vec::new();
+    let mut rest = body;
+    while let Some(start) = rest.find("${") {
+        let Some(len) = rest[start + 2..].find('}') else {
+            break;
+        };
+        let key = rest[start + 2..start + 2 + len].trim();
+        out.push_str(&rest[..start]);
+        match value(key) {
+            Some(v) => out.push_str(v),
+            None if !missing.iter().any(|m| m == key) => missing.push(key.to_string()),
+            None => {}
+        }
+        rest = &rest[start + 3 + len..];
+    }
+    out.push_str(rest);
+    (out, missing)
+}
+
+/// `text` with the name of each `<snippet:...>` token replaced by
+/// `rename(name)` where that is `Some`, arguments kept; `None` when nothing
+/// changed.
+pub fn rewrite_refs(text: &str, rename: impl Fn(&str) -> Option<String>) -> Option<String> {
+    let mut out = String::with_capacity(text.len());
+    let mut last = 0;
+    for r in snippet_refs(text) {
+        let Some(to) = rename(r.name).filter(|to| to != r.name) else {
+            continue;
+        };
+        // `name` borrows from `text`.
+        let start = r.name.as_ptr() as usize - text.as_ptr() as usize;
+        out.push_str(&text[last..start]);
+        out.push_str(&to);
+        last = start + r.name.len();
+    }
+    if last == 0 {
+        return None;
+    }
+    out.push_str(&text[last..]);
+    Some(out)
+}
+
+/// Check a snippet name: `/`-separated segments, none empty, `.` or `..`, and
+/// nothing that would end a `<snippet:...>` token early.
+pub fn check_snippet_name(name: &str) -> anyhow::Result<()> {
+    if name.is_empty() {
+        anyhow::bail!("empty snippet name");
+    }
+    if name
+        .chars()
+        .any(|c| c.is_whitespace() || matches!(c, '<' | '>' | '"' | '\''))
+    {
+        anyhow::bail!("snippet name has whitespace, quotes or brackets: {name}");
+    }
+    if name.split('/').any(|seg| matches!(seg, "" | "." | "..")) {
+        anyhow::bail!("snippet name has an empty, `.` or `..` segment: {name}");
+    }
+    Ok(())
+}
+
+/// Folder of a snippet: `char/alice` for `char/alice/outfit`, empty at the root.
+pub fn snippet_folder(name: &str) -> &str {
+    name.rsplit_once('/').map_or("", |(folder, _)| folder)
+}
+
+/// Names `name`, written in a snippet of `folder`, may refer to, in lookup order.
+pub fn snippet_candidates(folder: &str, name: &str) -> Vec<String> {
+    if let Some(abs) = name.strip_prefix('/') {
+        return vec![abs.to_string()];
+    }
+    if folder.is_empty() {
+        return vec![name.to_string()];
+    }
+    vec![format!("{folder}/{name}"), name.to_string()]
+}
+
+/// The snippet `name`, written in a snippet of `folder`, refers to.
+pub fn resolve_snippet_name(
+    folder: &str,
+    name: &str,
+    exists: impl Fn(&str) -> bool,
+) -> Option<String> {
+    snippet_candidates(folder, name)
+        .into_iter()
+        .find(|c| exists(c))
+}
+
+/// How to write a reference to `target` in a snippet of `folder`: relative
+/// when it is inside the folder, otherwise from the root, with a leading `/`
+/// when the folder has a snippet of the same relative name.
+pub fn spell_snippet_name(folder: &str, target: &str, exists: impl Fn(&str) -> bool) -> String {
+    if folder.is_empty() {
+        return target.to_string();
+    }
+    if let Some(rel) = target
+        .strip_prefix(folder)
+        .and_then(|rest| rest.strip_prefix('/'))
+    {
+        return rel.to_string();
+    }
+    if exists(&format!("{folder}/{target}")) {
+        format!("/{target}")
+    } else {
+        target.to_string()
+    }
+}
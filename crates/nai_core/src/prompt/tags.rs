@@ -0,0 +1,39 @@
+//! Danbooru tag names in prompts.
+
+use std::collections::HashMap;
+
+use super::ast::Prompt;
+
+/// Danbooru's form of a tag: `Long Hair` is `long_hair`.
+pub fn danbooru_name(tag: &str) -> String {
+    tag.split_whitespace()
+        .collect::<Vec<_>>()
+        .join("_")
+        .to_lowercase()
+}
+
+/// How a Danbooru name is written in a prompt: underscores as spaces, except in
+/// emoticons of three characters or fewer like `^_^` and `o_o`.
+pub fn prompt_name(name: &str) -> String {
+    if name.chars().count() <= 3 {
+        name.to_string()
+    } else {
+        name.replace('_', " ")
+    }
+}
+
+/// Rewrite every tag of `text` found in `canonical`, keyed by `danbooru_name`,
+/// as the `prompt_name` of its canonical name. Other tags and all syntax are
+/// kept as written.
+pub fn replace_tags(text: &str, canonical: &HashMap<String, String>) -> String {
+    let mut out = text.to_string();
+    for tag in Prompt::parse(text).tags().into_iter().rev() {
+        if let Some(name) = canonical.get(&danbooru_name(&tag.text)) {
+            let replacement = prompt_name(name);
+            if text[tag.span.clone()] != replacement {
+                out.replace_range(tag.span, &replacement);
+            }
+        }
+    }
+    out
+}
@@ -0,0 +1,101 @@
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{dto::BaseGenerateRequest, services::normalize_seed};
+
+/// How seeds are picked across the images of one submission.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SeedMode {
+    /// A new random seed per image when `seed` is -1, otherwise `seed` for all
+    /// (the behaviour before seed modes existed).
+    #[default]
+    Random,
+    /// The same seed for every image; -1 picks one at random for the batch.
+    Fixed,
+    /// `seed`, `seed + 1`, `seed + 2`, ...; -1 starts from a random seed.
+    Increment,
+    /// Seeds from `seeds`, in order. `quantity` defaults to the list length and
+    /// wraps around when larger.
+    List,
+    /// One seed for the batch while `extra_noise_seed` counts up from its base.
+    /// Only for img2img and inpaint, the requests that have one.
+    Variations,
+}
+
+#[derive(Debug, Error)]
+pub enum SeedError {
+    #[error("seed_mode list needs a non-empty `seeds` list")]
+    EmptySeedList,
+    #[error("`seeds` is only used with seed_mode list")]
+    UnexpectedSeedList,
+    #[error("seed_mode variations needs an img2img or inpaint request")]
+    VariationsUnsupported,
+}
+
+impl BaseGenerateRequest {
+    /// Validate the seed mode and pin a random base seed, so every image of the
+    /// batch derives from the same concrete values.
+    ///
+    /// `extra_noise_seed` is the request's own field when it has one (img2img,
+    /// inpaint); a random base is picked for variations when it is unset.
+    pub fn resolve_seeds(
+        &mut self,
+        extra_noise_seed: Option<&mut Option<i64>>,
+    ) -> Result<(), SeedError> {
+        let mode = self.seed_mode.unwrap_or_default();
+        match (mode, &self.seeds) {
+            (SeedMode::List, None) => return Err(SeedError::EmptySeedList),
+            (SeedMode::List, Some(seeds)) if seeds.is_empty() => {
+                return Err(SeedError::EmptySeedList);
+            }
+            (_, Some(seeds)) if mode != SeedMode::List && !seeds.is_empty() => {
+                return Err(SeedError::UnexpectedSeedList);
+            }
+            _ => {}
+        }
+        match mode {
+            SeedMode::Random | SeedMode::List => {}
+            SeedMode::Fixed | SeedMode::Increment => {
+                self.seed = normalize_seed(self.seed) as i64;
+            }
+            SeedMode::Variations => {
+                let Some(noise) = extra_noise_seed else {
+                    return Err(SeedError::VariationsUnsupported);
+                };
+                self.seed = normalize_seed(self.seed) as i64;
+                *noise = Some(normalize_seed(noise.unwrap_or(-1)) as i64);
+            }
+        }
+        Ok(())
+    }
+
+    /// Number of images the submission generates.
+    pub fn iterations(&self) -> usize {
+        let default = match (self.seed_mode, &self.seeds) {
+            (Some(SeedMode::List), Some(seeds)) => seeds.len(),
+            _ => 1,
+        };
+        self.quantity.map_or(default, |q| q as usize).max(1)
+    }
+
+    /// Seed for the image at `index`; -1 still means random. Call after `resolve_seeds`.
+    pub fn seed_at(&self, index: usize) -> i64 {
+        match self.seed_mode.unwrap_or_default() {
+            SeedMode::Increment => self.seed.saturating_add(index as i64),
+            SeedMode::List => match &self.seeds {
+                Some(seeds) if !seeds.is_empty() => seeds[index % seeds.len()] as i64,
+                _ => self.seed,
+            },
+            SeedMode::Random | SeedMode::Fixed | SeedMode::Variations => self.seed,
+        }
+    }
+
+    /// `extra_noise_seed` for the image at `index` given the resolved base.
+    pub fn extra_noise_seed_at(&self, base: Option<i64>, index: usize) -> Option<i64> {
+        match self.seed_mode {
+            Some(SeedMode::Variations) => base.map(|b| b.saturating_add(index as i64)),
+            _ => base,
+        }
+    }
+}
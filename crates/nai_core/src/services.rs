@@ -1,5 +1,6 @@
 use rand::Rng;
 use serde_json::{Value, json};
+use uuid::Uuid;
 
 use crate::{
     config::AppConfig,
@@ -9,15 +10,16 @@ use crate::{
     },
     nai::NaiApi,
     outputs::OutputStore,
+    path_template::PathContext,
     prompt,
 };
 
-fn output_url(rel_path: &str) -> String {
+pub(crate) fn output_url(rel_path: &str) -> String {
     // Browser-friendly URL; front-end should prefix with backend base url.
     format!("/outputs/{}", rel_path.replace('\\', "/"))
 }
 
-fn normalize_seed(seed: i64) -> u64 {
+pub(crate) fn normalize_seed(seed: i64) -> u64 {
     if seed == -1 {
         let mut rng = rand::rng();
         rng.random_range(1_000_000_000u64..=9_999_999_999u64)
@@ -55,7 +57,7 @@ fn uc_preset_id(model: &str, preset: &str) -> i32 {
     }
 }
 
-fn quality_tags(model: &str) -> &'static str {
+pub(crate) fn quality_tags(model: &str) -> &'static str {
     match model {
         "nai-diffusion-4-5-full" => ", very aesthetic, masterpiece, no text",
         "nai-diffusion-4-5-curated" => {
@@ -82,6 +84,25 @@ fn skip_cfg_above_sigma(model: &str) -> f64 {
     }
 }
 
+fn path_context<'a>(
+    req: &'a BaseGenerateRequest,
+    kind: &'a str,
+    seed: u64,
+    prompt: &'a str,
+    job_id: Option<Uuid>,
+) -> PathContext<'a> {
+    PathContext {
+        kind,
+        seed,
+        model: Some(&req.model),
+        sampler: Some(&req.sampler),
+        job_id,
+        size: Some((req.width, req.height)),
+        prompt: Some(prompt),
+        preset: req.preset_name.as_deref(),
+    }
+}
+
 async fn preprocess_prompts(
     cfg: &AppConfig,
     _outputs: &OutputStore,
@@ -93,6 +114,7 @@ async fn preprocess_prompts(
     Ok((pos, neg))
 }
 
+#[allow(clippy::too_many_arguments)]
 fn build_text2image_payload(
     req: &BaseGenerateRequest,
     seed: u64,
@@ -228,6 +250,7 @@ fn apply_img2img(
     json_data["parameters"]["extra_noise_seed"] = json!(extra_noise_seed);
 }
 
+#[allow(clippy::too_many_arguments)]
 fn apply_inpaint(
     json_data: &mut Value,
     model: &str,
@@ -290,6 +313,7 @@ pub async fn generate_t2i(
     outputs: &OutputStore,
     nai: &dyn NaiApi,
     req: BaseGenerateRequest,
+    job_id: Option<Uuid>,
 ) -> anyhow::Result<GenerateResponse> {
     let seed = normalize_seed(req.seed);
     let add_quality_tags = req.add_quality_tags.unwrap_or(false);
@@ -320,7 +344,8 @@ pub async fn generate_t2i(
     );
     let zip_bytes = nai.generate_image_zip(&json_data).await?;
     let png = nai.zip_read_file(&zip_bytes, "image_0.png")?;
-    let output_path = outputs.save_png("text2image", seed, &png).await?;
+    let ctx = path_context(&req, "text2image", seed, &pos, job_id);
+    let output_path = outputs.save_png(&ctx, &png, req.output_format).await?;
     let url = output_url(&output_path);
     Ok(GenerateResponse {
         seed,
@@ -334,6 +359,7 @@ pub async fn generate_i2i(
     outputs: &OutputStore,
     nai: &dyn NaiApi,
     req: Img2ImgRequest,
+    job_id: Option<Uuid>,
 ) -> anyhow::Result<GenerateResponse> {
     let seed = normalize_seed(req.base.seed);
     let (pos, neg) =
@@ -370,7 +396,8 @@ pub async fn generate_i2i(
 
     let zip_bytes = nai.generate_image_zip(&json_data).await?;
     let png = nai.zip_read_file(&zip_bytes, "image_0.png")?;
-    let output_path = outputs.save_png("image2image", seed, &png).await?;
+    let ctx = path_context(&req.base, "image2image", seed, &pos, job_id);
+    let output_path = outputs.save_png(&ctx, &png, req.base.output_format).await?;
     let url = output_url(&output_path);
     Ok(GenerateResponse {
         seed,
@@ -384,6 +411,7 @@ pub async fn generate_inpaint(
     outputs: &OutputStore,
     nai: &dyn NaiApi,
     req: InpaintRequest,
+    job_id: Option<Uuid>,
 ) -> anyhow::Result<GenerateResponse> {
     let seed = normalize_seed(req.base.seed);
     let (pos, neg) =
@@ -422,7 +450,8 @@ pub async fn generate_inpaint(
 
     let zip_bytes = nai.generate_image_zip(&json_data).await?;
     let png = nai.zip_read_file(&zip_bytes, "image_0.png")?;
-    let output_path = outputs.save_png("inpaint", seed, &png).await?;
+    let ctx = path_context(&req.base, "inpaint", seed, &pos, job_id);
+    let output_path = outputs.save_png(&ctx, &png, req.base.output_format).await?;
     let url = output_url(&output_path);
     Ok(GenerateResponse {
         seed,
@@ -436,6 +465,7 @@ pub async fn generate_character(
     outputs: &OutputStore,
     nai: &dyn NaiApi,
     req: CharacterRequest,
+    job_id: Option<Uuid>,
 ) -> anyhow::Result<GenerateResponse> {
     let seed = normalize_seed(req.base.seed);
     let (pos, neg) =
@@ -474,7 +504,8 @@ pub async fn generate_character(
 
     let zip_bytes = nai.generate_image_zip(&json_data).await?;
     let png = nai.zip_read_file(&zip_bytes, "image_0.png")?;
-    let output_path = outputs.save_png("character", seed, &png).await?;
+    let ctx = path_context(&req.base, "character", seed, &pos, job_id);
+    let output_path = outputs.save_png(&ctx, &png, req.base.output_format).await?;
     let url = output_url(&output_path);
     Ok(GenerateResponse {
         seed,
@@ -498,21 +529,16 @@ pub async fn director_call(
             .enumerate()
         {
             if let Ok(png) = nai.zip_read_file(&zip_bytes, name) {
-                let path = outputs
-                    .save_png(
-                        &format!("director/remove_bg/{idx}"),
-                        rand::random::<u64>(),
-                        &png,
-                    )
-                    .await?;
+                let kind = format!("director/remove_bg/{idx}");
+                let ctx = PathContext::new(&kind, rand::random::<u64>());
+                let path = outputs.save_png(&ctx, &png, None).await?;
                 paths.push(path);
             }
         }
     } else {
         let png = nai.zip_read_file(&zip_bytes, "image_0.png")?;
-        let path = outputs
-            .save_png("director", rand::random::<u64>(), &png)
-            .await?;
+        let ctx = PathContext::new("director", rand::random::<u64>());
+        let path = outputs.save_png(&ctx, &png, None).await?;
         paths.push(path);
     }
 
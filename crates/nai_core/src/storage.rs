@@ -0,0 +1,204 @@
+use std::{
+    io::{Read, Seek, SeekFrom},
+    ops::Range,
+    path::{Path, PathBuf},
+};
+
+use async_trait::async_trait;
+
+use crate::outputs::{OutputError, cleanup_empty_parents, is_safe_rel_path, normalize_rel_path};
+
+/// One stored file. `key` is a relative path using '/'.
+#[derive(Debug, Clone)]
+pub struct StoredObject {
+    pub key: String,
+    pub size: u64,
+    pub modified_ms: i64,
+}
+
+/// Where outputs (and their thumbnail cache and trash) live.
+///
+/// Keys are relative paths using '/'. Components starting with '.' are internal
+/// (`.thumbs`, `.trash`) and are skipped when listing below them.
+#[async_trait]
+pub trait OutputStorage: Send + Sync {
+    /// Backend name for logs.
+    fn kind(&self) -> &'static str;
+
+    /// Directory backing the storage, for features that only make sense on a local
+    /// volume (e.g. the free-disk-space guard).
+    fn local_root(&self) -> Option<&Path> {
+        None
+    }
+
+    async fn put(&self, key: &str, bytes: Vec<u8>) -> Result<(), OutputError>;
+    /// `None` when the key doesn't exist.
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, OutputError>;
+    /// Bytes in `range`, which must lie within the object; `None` when the key
+    /// doesn't exist.
+    async fn get_range(&self, key: &str, range: Range<u64>)
+    -> Result<Option<Vec<u8>>, OutputError>;
+    async fn stat(&self, key: &str) -> Result<Option<StoredObject>, OutputError>;
+    /// Every object under `prefix` ("" for everything), skipping internal components.
+    async fn list(&self, prefix: &str) -> Result<Vec<StoredObject>, OutputError>;
+    /// Missing keys are ignored.
+    async fn delete(&self, key: &str) -> Result<(), OutputError>;
+    async fn rename(&self, from: &str, to: &str) -> Result<(), OutputError>;
+}
+
+/// Outputs in a local directory (the default).
+#[derive(Debug, Clone)]
+pub struct LocalStorage {
+    root: PathBuf,
+}
+
+impl LocalStorage {
+    pub fn new(root: PathBuf) -> Result<Self, OutputError> {
+        std::fs::create_dir_all(&root)?;
+        Ok(Self { root })
+    }
+
+    fn path(&self, key: &str) -> Result<PathBuf, OutputError> {
+        let key = normalize_rel_path(key);
+        if !is_safe_rel_path(&key) {
+            return Err(OutputError::InvalidPath);
+        }
+        Ok(self.root.join(key))
+    }
+}
+
+#[async_trait]
+impl OutputStorage for LocalStorage {
+    fn kind(&self) -> &'static str {
+        "local"
+    }
+
+    fn local_root(&self) -> Option<&Path> {
+        Some(&self.root)
+    }
+
+    async fn put(&self, key: &str, bytes: Vec<u8>) -> Result<(), OutputError> {
+        let path = self.path(key)?;
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        tokio::fs::write(&path, bytes).await?;
+        Ok(())
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, OutputError> {
+        match tokio::fs::read(self.path(key)?).await {
+            Ok(b) => Ok(Some(b)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn get_range(
+        &self,
+        key: &str,
+        range: Range<u64>,
+    ) -> Result<Option<Vec<u8>>, OutputError> {
+        let path = self.path(key)?;
+        let read = tokio::task::spawn_blocking(move || {
+            let mut file = std::fs::File::open(path)?;
+            file.seek(SeekFrom::Start(range.start))?;
+            let mut bytes = Vec::with_capacity((range.end - range.start) as usize);
+            file.take(range.end - range.start).read_to_end(&mut bytes)?;
+            Ok::<_, std::io::Error>(bytes)
+        })
+        .await
+        .map_err(std::io::Error::other)?;
+        match read {
+            Ok(b) => Ok(Some(b)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn stat(&self, key: &str) -> Result<Option<StoredObject>, OutputError> {
+        match tokio::fs::metadata(self.path(key)?).await {
+            Ok(meta) if meta.is_file() => Ok(Some(StoredObject {
+                key: normalize_rel_path(key),
+                size: meta.len(),
+                modified_ms: modified_ms(&meta),
+            })),
+            Ok(_) => Ok(None),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn list(&self, prefix: &str) -> Result<Vec<StoredObject>, OutputError> {
+        let dir = self.path(prefix)?;
+        let root = self.root.clone();
+        let objects = tokio::task::spawn_blocking(move || {
+            let mut out = Vec::new();
+            if dir.is_dir() {
+                walk_sync(&root, &dir, &mut out)?;
+            }
+            Ok::<_, OutputError>(out)
+        })
+        .await
+        .map_err(std::io::Error::other)??;
+        Ok(objects)
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), OutputError> {
+        let path = self.path(key)?;
+        match tokio::fs::remove_file(&path).await {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e.into()),
+        }
+        // Best-effort: clean empty parent dirs up to outputs root.
+        let _ = cleanup_empty_parents(&self.root, path.parent()).await;
+        Ok(())
+    }
+
+    async fn rename(&self, from: &str, to: &str) -> Result<(), OutputError> {
+        let src = self.path(from)?;
+        let dest = self.path(to)?;
+        if let Some(parent) = dest.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        tokio::fs::rename(&src, &dest).await?;
+        let _ = cleanup_empty_parents(&self.root, src.parent()).await;
+        Ok(())
+    }
+}
+
+fn walk_sync(root: &Path, dir: &Path, out: &mut Vec<StoredObject>) -> Result<(), OutputError> {
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let p = entry.path();
+        if entry
+            .file_name()
+            .to_str()
+            .is_none_or(|name| name.starts_with('.'))
+        {
+            continue;
+        }
+        let meta = entry.metadata()?;
+        if meta.is_dir() {
+            walk_sync(root, &p, out)?;
+        } else if meta.is_file()
+            && let Ok(rel) = p.strip_prefix(root)
+        {
+            out.push(StoredObject {
+                key: rel.to_string_lossy().replace('\\', "/"),
+                size: meta.len(),
+                modified_ms: modified_ms(&meta),
+            });
+        }
+    }
+    Ok(())
+}
+
+fn modified_ms(meta: &std::fs::Metadata) -> i64 {
+    meta.modified()
+        .ok()
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or(0)
+}
@@ -0,0 +1,260 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use thiserror::Error;
+
+use crate::{
+    dto::BaseGenerateRequest,
+    grid::{GridAxes, MAX_GRID_ITEMS},
+    services::normalize_seed,
+};
+
+/// X, Y and Z.
+pub const MAX_SWEEP_AXES: usize = 3;
+
+#[derive(Debug, Error)]
+pub enum SweepError {
+    #[error("a sweep takes 1 to {MAX_SWEEP_AXES} axes, got {0}")]
+    AxisCount(usize),
+    #[error("axis {0} has no values")]
+    EmptyAxis(usize),
+    #[error("sweep has {0} cells; the limit is {MAX_GRID_ITEMS}")]
+    TooManyCells(usize),
+    #[error("axis {axis}: unknown field path {path:?}")]
+    UnknownField { axis: usize, path: String },
+    #[error("axis {axis}: {path} = {value} is not a valid request: {source}")]
+    InvalidValue {
+        axis: usize,
+        path: String,
+        value: Value,
+        source: serde_json::Error,
+    },
+    #[error("axis {axis}: {search:?} does not appear in the prompts")]
+    SearchNotFound { axis: usize, search: String },
+    #[error("axis {0}: replacement values must be strings")]
+    NonStringReplacement(usize),
+    #[error("json error: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+/// What an axis changes.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SweepTarget {
+    /// A request field, as a JSON pointer (`/scale`) or dotted path
+    /// (`character_prompts.0.prompt`). Values replace the field as-is.
+    Field { path: String },
+    /// Replace `search` in every prompt (positive, negative and character prompts)
+    /// with each value, like A1111's "Prompt S/R".
+    Replace { search: String },
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SweepAxis {
+    #[serde(flatten)]
+    pub target: SweepTarget,
+    pub values: Vec<Value>,
+    /// Name shown on the grid; defaults to the field path.
+    pub label: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SweepRequest {
+    pub base: BaseGenerateRequest,
+    /// X, then optionally Y and Z.
+    pub axes: Vec<SweepAxis>,
+    /// Use one seed for every cell. A random one is picked when the base seed is -1.
+    #[serde(default)]
+    pub fixed_seed: bool,
+    /// Width of each grid cell; see `GridOptions::cell_width`.
+    pub cell_width: Option<u32>,
+}
+
+/// One generation of a sweep, at `(x, y, z)` on the grid.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SweepCell {
+    pub x: usize,
+    pub y: usize,
+    pub z: usize,
+    pub req: BaseGenerateRequest,
+}
+
+/// A sweep expanded into generations, in grid order (X fastest).
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SweepPlan {
+    pub cells: Vec<SweepCell>,
+    pub axes: GridAxes,
+    pub cell_width: Option<u32>,
+}
+
+impl SweepRequest {
+    pub fn expand(&self) -> Result<SweepPlan, SweepError> {
+        if self.axes.is_empty() || self.axes.len() > MAX_SWEEP_AXES {
+            return Err(SweepError::AxisCount(self.axes.len()));
+        }
+        let mut total = 1usize;
+        for (i, axis) in self.axes.iter().enumerate() {
+            if axis.values.is_empty() {
+                return Err(SweepError::EmptyAxis(i));
+            }
+            total = total.saturating_mul(axis.values.len());
+        }
+        if total > MAX_GRID_ITEMS {
+            return Err(SweepError::TooManyCells(total));
+        }
+
+        let mut base = self.base.clone();
+        if self.fixed_seed {
+            base.seed = normalize_seed(base.seed) as i64;
+        }
+        let base = serde_json::to_value(&base)?;
+        for (i, axis) in self.axes.iter().enumerate() {
+            axis.validate(i, &base)?;
+        }
+
+        let lens: Vec<usize> = self.axes.iter().map(|a| a.values.len()).collect();
+        let dim = |i: usize| lens.get(i).copied().unwrap_or(1);
+        let mut cells = Vec::with_capacity(total);
+        for z in 0..dim(2) {
+            for y in 0..dim(1) {
+                for x in 0..dim(0) {
+                    let mut req = base.clone();
+                    for (i, pos) in [x, y, z].into_iter().enumerate().take(self.axes.len()) {
+                        self.axes[i].apply(&mut req, pos);
+                    }
+                    let mut req: BaseGenerateRequest = serde_json::from_value(req)?;
+                    // Each cell is one image, whatever the axes set; seeds come from the
+                    // base (or a `seed` axis), not a seed mode.
+                    req.quantity = Some(1);
+                    req.grid = None;
+                    req.seed_mode = None;
+                    req.seeds = None;
+                    req.combinatorial = None;
+                    cells.push(SweepCell { x, y, z, req });
+                }
+            }
+        }
+
+        let labels = |i: usize| {
+            self.axes
+                .get(i)
+                .map(|a| (0..a.values.len()).map(|pos| a.value_label(pos)).collect())
+                .unwrap_or_default()
+        };
+        Ok(SweepPlan {
+            cells,
+            axes: GridAxes {
+                x: labels(0),
+                y: labels(1),
+                z: labels(2),
+            },
+            cell_width: self.cell_width,
+        })
+    }
+}
+
+impl SweepAxis {
+    fn name(&self) -> String {
+        match (&self.label, &self.target) {
+            (Some(label), _) => label.clone(),
+            (None, SweepTarget::Field { path }) => path.trim_start_matches('/').replace('/', "."),
+            (None, SweepTarget::Replace { search }) => search.clone(),
+        }
+    }
+
+    fn value_label(&self, pos: usize) -> String {
+        let value = match &self.values[pos] {
+            Value::String(s) => s.clone(),
+            other => other.to_string(),
+        };
+        match (&self.label, &self.target) {
+            // Replacement values already read as prompt text.
+            (None, SweepTarget::Replace { .. }) => value,
+            _ => format!("{}: {value}", self.name()),
+        }
+    }
+
+    fn validate(&self, axis: usize, base: &Value) -> Result<(), SweepError> {
+        match &self.target {
+            SweepTarget::Field { path } => {
+                if base.pointer(&json_pointer(path)).is_none() {
+                    return Err(SweepError::UnknownField {
+                        axis,
+                        path: path.clone(),
+                    });
+                }
+                for pos in 0..self.values.len() {
+                    let mut req = base.clone();
+                    self.apply(&mut req, pos);
+                    if let Err(source) = serde_json::from_value::<BaseGenerateRequest>(req) {
+                        return Err(SweepError::InvalidValue {
+                            axis,
+                            path: path.clone(),
+                            value: self.values[pos].clone(),
+                            source,
+                        });
+                    }
+                }
+            }
+            SweepTarget::Replace { search } => {
+                if self.values.iter().any(|v| !v.is_string()) {
+                    return Err(SweepError::NonStringReplacement(axis));
+                }
+                let mut found = false;
+                visit_prompts(&mut base.clone(), |p| found |= p.contains(search.as_str()));
+                if !found {
+                    return Err(SweepError::SearchNotFound {
+                        axis,
+                        search: search.clone(),
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn apply(&self, req: &mut Value, pos: usize) {
+        let value = &self.values[pos];
+        match &self.target {
+            SweepTarget::Field { path } => {
+                if let Some(slot) = req.pointer_mut(&json_pointer(path)) {
+                    *slot = value.clone();
+                }
+            }
+            SweepTarget::Replace { search } => {
+                let replacement = value.as_str().unwrap_or_default();
+                visit_prompts(req, |p| {
+                    if p.contains(search.as_str()) {
+                        *p = p.replace(search.as_str(), replacement);
+                    }
+                });
+            }
+        }
+    }
+}
+
+/// `scale` and `character_prompts.0.prompt` become `/scale` and
+/// `/character_prompts/0/prompt`; JSON pointers pass through.
+fn json_pointer(path: &str) -> String {
+    if path.starts_with('/') {
+        path.to_string()
+    } else {
+        format!("/{}", path.replace('.', "/"))
+    }
+}
+
+fn visit_prompts(req: &mut Value, mut f: impl FnMut(&mut String)) {
+    for key in ["positive", "negative"] {
+        if let Some(Value::String(p)) = req.get_mut(key) {
+            f(p);
+        }
+    }
+    if let Some(Value::Array(chars)) = req.get_mut("character_prompts") {
+        for c in chars {
+            for key in ["prompt", "uc"] {
+                if let Some(Value::String(p)) = c.get_mut(key) {
+                    f(p);
+                }
+            }
+        }
+    }
+}
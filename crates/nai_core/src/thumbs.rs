@@ -0,0 +1,127 @@
+use std::io::Cursor;
+
+use image::{DynamicImage, codecs::jpeg::JpegEncoder, imageops::FilterType};
+
+use crate::{
+    output_format::OutputFormat,
+    outputs::{OutputError, OutputStore, is_internal, is_safe_rel_path, normalize_rel_path},
+};
+
+/// Hidden cache directory under outputs root. Skipped by output scanners.
+pub const THUMBS_DIR: &str = ".thumbs";
+
+/// Widths the gallery may request. Anything else is rejected so the cache stays bounded.
+pub const THUMB_WIDTHS: [u32; 3] = [256, 512, 768];
+
+/// Width used for `OutputItem::thumb_url`.
+pub const DEFAULT_THUMB_WIDTH: u32 = 256;
+
+const JPEG_QUALITY: u8 = 82;
+
+pub fn thumb_url(rel_path: &str, width: u32) -> String {
+    format!("/thumbs/{width}/{}", normalize_rel_path(rel_path))
+}
+
+impl OutputStore {
+    /// Return JPEG thumbnail bytes for an output, generating and caching it on first use.
+    ///
+    /// The cache entry is rebuilt when the source file is newer than the thumbnail.
+    /// Formats that can't be decoded only have the thumbnails written at save time,
+    /// served however old they are.
+    pub async fn thumbnail(&self, rel: &str, width: u32) -> Result<Vec<u8>, OutputError> {
+        if !THUMB_WIDTHS.contains(&width) {
+            return Err(OutputError::UnsupportedThumbWidth(width));
+        }
+        let rel_norm = normalize_rel_path(rel);
+        if !is_safe_rel_path(&rel_norm) || is_internal(&rel_norm) {
+            return Err(OutputError::InvalidPath);
+        }
+
+        let storage = self.storage();
+        let cached = thumb_cache_key(&rel_norm, width);
+        let src = storage
+            .stat(&rel_norm)
+            .await?
+            .ok_or(OutputError::NotFound)?;
+        let decodable = OutputFormat::from_path(&rel_norm).is_none_or(|f| f.is_decodable());
+        if let Some(thumb) = storage.stat(&cached).await?
+            && (thumb.modified_ms >= src.modified_ms || !decodable)
+            && let Some(bytes) = storage.get(&cached).await?
+        {
+            return Ok(bytes);
+        }
+        if !decodable {
+            return Err(OutputError::NotFound);
+        }
+
+        let src_bytes = self.read(&rel_norm).await?;
+        let bytes = tokio::task::spawn_blocking(move || render_thumbnail(&src_bytes, width))
+            .await
+            .map_err(std::io::Error::other)??;
+
+        storage.put(&cached, bytes.clone()).await?;
+        Ok(bytes)
+    }
+
+    /// Write every thumbnail width from the original PNG. Used for formats the
+    /// thumbnailer can't decode, so their thumbnails never need the saved file.
+    pub(crate) async fn prime_thumbnails(
+        &self,
+        rel: &str,
+        png_bytes: &[u8],
+    ) -> Result<(), OutputError> {
+        let rel_norm = normalize_rel_path(rel);
+        let png = png_bytes.to_vec();
+        let thumbs = tokio::task::spawn_blocking(move || {
+            let img = image::load_from_memory(&png)?;
+            THUMB_WIDTHS
+                .iter()
+                .map(|&w| Ok((w, encode_thumbnail(&img, w)?)))
+                .collect::<Result<Vec<_>, OutputError>>()
+        })
+        .await
+        .map_err(std::io::Error::other)??;
+        for (width, bytes) in thumbs {
+            self.storage()
+                .put(&thumb_cache_key(&rel_norm, width), bytes)
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Best-effort removal of every cached thumbnail for an output.
+    pub async fn remove_thumbnails(&self, rel: &str) {
+        let rel_norm = normalize_rel_path(rel);
+        for width in THUMB_WIDTHS {
+            let _ = self
+                .storage()
+                .delete(&thumb_cache_key(&rel_norm, width))
+                .await;
+        }
+    }
+}
+
+pub(crate) fn thumb_cache_key(rel_norm: &str, width: u32) -> String {
+    format!("{THUMBS_DIR}/{width}/{rel_norm}.jpg")
+}
+
+fn render_thumbnail(src: &[u8], width: u32) -> Result<Vec<u8>, OutputError> {
+    encode_thumbnail(&image::load_from_memory(src)?, width)
+}
+
+fn encode_thumbnail(img: &DynamicImage, width: u32) -> Result<Vec<u8>, OutputError> {
+    // Never upscale: small images are re-encoded at their own size.
+    let resized;
+    let img = if img.width() > width {
+        let height = ((img.height() as u64 * width as u64) / img.width() as u64).max(1) as u32;
+        resized = img.resize_exact(width, height, FilterType::Triangle);
+        &resized
+    } else {
+        img
+    };
+
+    let mut buf = Cursor::new(Vec::new());
+    let encoder = JpegEncoder::new_with_quality(&mut buf, JPEG_QUALITY);
+    img.to_rgb8().write_with_encoder(encoder)?;
+    Ok(buf.into_inner())
+}
@@ -0,0 +1,116 @@
+//! CLIP's byte-level BPE, as used by `openai/clip-vit-large-patch14`.
+
+use std::{collections::HashMap, path::Path};
+
+use anyhow::Context;
+use regex::Regex;
+use serde::Deserialize;
+use unicode_normalization::UnicodeNormalization;
+
+const END_OF_WORD: &str = "</w>";
+
+pub struct ClipTokenizer {
+    ranks: HashMap<(String, String), usize>,
+    split: Regex,
+    /// Byte to the printable character BPE works on.
+    byte_chars: [char; 256],
+}
+
+#[derive(Deserialize)]
+struct TokenizerFile {
+    model: Model,
+}
+
+#[derive(Deserialize)]
+struct Model {
+    merges: Vec<Merge>,
+}
+
+/// `"a b"` in older files, `["a", "b"]` in newer ones.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Merge {
+    Joined(String),
+    Pair(String, String),
+}
+
+impl ClipTokenizer {
+    /// Load the merges of a Hugging Face `tokenizer.json`.
+    pub fn from_file(path: &Path) -> anyhow::Result<Self> {
+        let raw =
+            std::fs::read_to_string(path).with_context(|| format!("read {}", path.display()))?;
+        let file: TokenizerFile =
+            serde_json::from_str(&raw).with_context(|| format!("parse {}", path.display()))?;
+        let mut ranks = HashMap::with_capacity(file.model.merges.len());
+        for (rank, merge) in file.model.merges.into_iter().enumerate() {
+            let pair = match merge {
+                Merge::Pair(a, b) => (a, b),
+                Merge::Joined(s) => match s.split_once(' ') {
+                    Some((a, b)) => (a.to_string(), b.to_string()),
+                    None => anyhow::bail!("{}: invalid merge {s:?}", path.display()),
+                },
+            };
+            ranks.entry(pair).or_insert(rank);
+        }
+        Ok(Self {
+            ranks,
+            split: Regex::new(r"'s|'t|'re|'ve|'m|'ll|'d|\p{L}+|\p{N}|[^\s\p{L}\p{N}]+")
+                .expect("valid regex"),
+            byte_chars: byte_chars(),
+        })
+    }
+
+    /// Tokens of `text`, without the start and end tokens.
+    pub fn count(&self, text: &str) -> usize {
+        let text = text.nfc().collect::<String>().to_lowercase();
+        self.split
+            .find_iter(&text)
+            .map(|m| self.count_word(m.as_str()))
+            .sum()
+    }
+
+    fn count_word(&self, word: &str) -> usize {
+        let mut symbols: Vec<String> = word
+            .bytes()
+            .map(|b| self.byte_chars[b as usize].to_string())
+            .collect();
+        if let Some(last) = symbols.last_mut() {
+            last.push_str(END_OF_WORD);
+        }
+        // Merge the lowest-ranked pair until none is left.
+        loop {
+            let best = symbols
+                .windows(2)
+                .enumerate()
+                .filter_map(|(i, w)| {
+                    self.ranks
+                        .get(&(w[0].clone(), w[1].clone()))
+                        .map(|&r| (r, i))
+                })
+                .min();
+            let Some((_, i)) = best else { break };
+            let merged = format!("{}{}", symbols[i], symbols[i + 1]);
+            symbols[i] = merged;
+            symbols.remove(i + 1);
+        }
+        symbols.len()
+    }
+}
+
+/// GPT-2's reversible byte to character table: printable bytes map to
+/// themselves, the rest to characters from U+0100 on.
+fn byte_chars() -> [char; 256] {
+    let mut out = ['\0'; 256];
+    let mut next = 256u32;
+    for (b, slot) in out.iter_mut().enumerate() {
+        let printable = matches!(b, 33..=126 | 161..=172 | 174..=255);
+        *slot = if printable {
+            char::from(b as u8)
+        } else {
+            let c = char::from_u32(next).expect("valid char");
+            next += 1;
+            c
+        };
+    }
+    out
+}
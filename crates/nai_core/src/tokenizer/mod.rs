@@ -0,0 +1,195 @@
+//! Offline token counts against the text encoder of each model: CLIP for v3,
+//! T5 for v4 and v4.5. NovelAI silently truncates prompts past the limit.
+//!
+//! Vocabularies are Hugging Face `tokenizer.json` files under `tokenizer_dir`:
+//! `clip/tokenizer.json` from `openai/clip-vit-large-patch14` and
+//! `t5/tokenizer.json` from `google/t5-v1_1-xxl`.
+
+use std::path::Path;
+
+use serde::Serialize;
+use tracing::warn;
+
+use crate::{
+    dto::{BaseGenerateRequest, CharacterPrompt},
+    prompt::ast::Prompt,
+    services::quality_tags,
+};
+
+pub mod clip;
+pub mod t5;
+
+use clip::ClipTokenizer;
+use t5::T5Tokenizer;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Encoder {
+    Clip,
+    T5,
+}
+
+impl Encoder {
+    pub fn for_model(model: &str) -> Self {
+        match model {
+            "nai-diffusion-3" | "nai-diffusion-furry-3" => Encoder::Clip,
+            _ => Encoder::T5,
+        }
+    }
+
+    /// Tokens the model reads from each side of the prompt: three CLIP windows
+    /// of 75 for v3, one T5 context for v4.
+    pub fn limit(self) -> usize {
+        match self {
+            Encoder::Clip => 225,
+            Encoder::T5 => 512,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Encoder::Clip => "CLIP",
+            Encoder::T5 => "T5",
+        }
+    }
+}
+
+/// Tokenizers found under `tokenizer_dir`; counting with a missing one gives
+/// `None`.
+#[derive(Default)]
+pub struct Tokenizers {
+    clip: Option<ClipTokenizer>,
+    t5: Option<T5Tokenizer>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CharacterTokens {
+    pub prompt: usize,
+    pub uc: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TokenReport {
+    pub encoder: Encoder,
+    pub limit: usize,
+    /// Base prompt, with quality tags when they are added.
+    pub base: usize,
+    pub negative: usize,
+    /// Every character prompt of the request, in order; v3 models have none.
+    pub characters: Vec<CharacterTokens>,
+    /// Base prompt and enabled character prompts.
+    pub positive_total: usize,
+    /// Negative prompt and enabled character negatives.
+    pub negative_total: usize,
+}
+
+impl TokenReport {
+    /// One warning per side over the limit.
+    pub fn warnings(&self) -> Vec<String> {
+        [
+            ("正面提示词", self.positive_total),
+            ("负面提示词", self.negative_total),
+        ]
+        .into_iter()
+        .filter(|(_, n)| *n > self.limit)
+        .map(|(side, n)| {
+            format!(
+                "{side}共 {n} 个 token，超过 {} 上限 {}，超出部分会被截断",
+                self.encoder.as_str(),
+                self.limit
+            )
+        })
+        .collect()
+    }
+}
+
+impl Tokenizers {
+    /// Load whichever tokenizers exist under `dir`; missing or invalid files
+    /// are logged and skipped.
+    pub fn load(dir: &Path) -> Self {
+        fn load_one<T>(path: &Path, from_file: fn(&Path) -> anyhow::Result<T>) -> Option<T> {
+            if !path.is_file() {
+                return None;
+            }
+            from_file(path)
+                .inspect_err(|e| warn!(path = %path.display(), "tokenizer not loaded: {e:#}"))
+                .ok()
+        }
+        Self {
+            clip: load_one(&dir.join("clip/tokenizer.json"), ClipTokenizer::from_file),
+            t5: load_one(&dir.join("t5/tokenizer.json"), T5Tokenizer::from_file),
+        }
+    }
+
+    pub fn has(&self, encoder: Encoder) -> bool {
+        match encoder {
+            Encoder::Clip => self.clip.is_some(),
+            Encoder::T5 => self.t5.is_some(),
+        }
+    }
+
+    /// Tokens of a prompt as the encoder sees it, without weight syntax.
+    pub fn count(&self, encoder: Encoder, text: &str) -> Option<usize> {
+        let text = Prompt::parse(text).plain_text();
+        match encoder {
+            Encoder::Clip => self.clip.as_ref().map(|t| t.count(&text)),
+            Encoder::T5 => self.t5.as_ref().map(|t| t.count(&text)),
+        }
+    }
+
+    /// Counts for the prompts of a request whose snippets and wildcards are
+    /// already expanded.
+    pub fn report(&self, base: &BaseGenerateRequest) -> Option<TokenReport> {
+        self.report_prompts(
+            &base.model,
+            &base.positive,
+            &base.negative,
+            base.character_prompts.as_deref().unwrap_or_default(),
+            base.add_quality_tags == Some(true),
+        )
+    }
+
+    /// Counts for expanded prompts as they'd be sent to `model`. The undesired
+    /// content preset is added by NovelAI and isn't counted.
+    pub fn report_prompts(
+        &self,
+        model: &str,
+        positive: &str,
+        negative: &str,
+        characters: &[CharacterPrompt],
+        add_quality_tags: bool,
+    ) -> Option<TokenReport> {
+        let encoder = Encoder::for_model(model);
+        let count = |text: &str| self.count(encoder, text);
+        let base = if add_quality_tags {
+            count(&format!("{positive}{}", quality_tags(model)))?
+        } else {
+            count(positive)?
+        };
+        let negative = count(negative)?;
+        let mut report = TokenReport {
+            encoder,
+            limit: encoder.limit(),
+            base,
+            negative,
+            characters: Vec::new(),
+            positive_total: base,
+            negative_total: negative,
+        };
+        // v3 models take no character prompts.
+        if encoder == Encoder::T5 {
+            for c in characters {
+                let tokens = CharacterTokens {
+                    prompt: count(&c.prompt)?,
+                    uc: count(&c.uc)?,
+                };
+                if c.enabled {
+                    report.positive_total += tokens.prompt;
+                    report.negative_total += tokens.uc;
+                }
+                report.characters.push(tokens);
+            }
+        }
+        Some(report)
+    }
+}
@@ -0,0 +1,113 @@
+//! T5's SentencePiece unigram model, as used by `google/t5-v1_1-xxl`.
+
+use std::{collections::HashMap, path::Path};
+
+use anyhow::Context;
+use serde::Deserialize;
+use unicode_normalization::UnicodeNormalization;
+
+/// Marks the start of a word.
+const SPACE: char = '▁';
+
+/// Penalty of an unknown character against the lowest-scored piece, as in
+/// SentencePiece.
+const UNKNOWN_PENALTY: f64 = 10.0;
+
+pub struct T5Tokenizer {
+    scores: HashMap<String, f64>,
+    /// Longest piece, in characters.
+    max_len: usize,
+    unknown_score: f64,
+}
+
+#[derive(Deserialize)]
+struct TokenizerFile {
+    model: Model,
+}
+
+#[derive(Deserialize)]
+struct Model {
+    vocab: Vec<(String, f64)>,
+}
+
+impl T5Tokenizer {
+    /// Load the vocabulary of a Hugging Face `tokenizer.json`.
+    pub fn from_file(path: &Path) -> anyhow::Result<Self> {
+        let raw =
+            std::fs::read_to_string(path).with_context(|| format!("read {}", path.display()))?;
+        let file: TokenizerFile =
+            serde_json::from_str(&raw).with_context(|| format!("parse {}", path.display()))?;
+        if file.model.vocab.is_empty() {
+            anyhow::bail!("{}: empty vocabulary", path.display());
+        }
+        let min_score = file
+            .model
+            .vocab
+            .iter()
+            .map(|(_, s)| *s)
+            .fold(f64::INFINITY, f64::min);
+        let max_len = file
+            .model
+            .vocab
+            .iter()
+            .map(|(p, _)| p.chars().count())
+            .max()
+            .unwrap_or(1);
+        Ok(Self {
+            scores: file.model.vocab.into_iter().collect(),
+            max_len,
+            unknown_score: min_score - UNKNOWN_PENALTY,
+        })
+    }
+
+    /// Tokens of `text`, without the end token.
+    ///
+    /// NFKC stands in for the model's own normalization table, which differs
+    /// only for rare characters.
+    pub fn count(&self, text: &str) -> usize {
+        let text: String = text.nfkc().collect();
+        text.split_whitespace()
+            .map(|word| self.count_word(&format!("{SPACE}{word}")))
+            .sum()
+    }
+
+    /// Pieces of the highest-scoring segmentation, with runs of unknown
+    /// characters counted as one piece.
+    fn count_word(&self, word: &str) -> usize {
+        let bounds: Vec<usize> = word
+            .char_indices()
+            .map(|(i, _)| i)
+            .chain([word.len()])
+            .collect();
+        let n = bounds.len() - 1;
+        // best[i]: (score, pieces, ends in an unknown) of the first i chars.
+        let mut best: Vec<Option<(f64, usize, bool)>> = vec![None; n + 1];
+        best[0] = Some((0.0, 0, false));
+        for start in 0..n {
+            let Some((score, pieces, after_unknown)) = best[start] else {
+                continue;
+            };
+            let mut single_known = false;
+            for end in start + 1..=n.min(start + self.max_len) {
+                if let Some(s) = self.scores.get(&word[bounds[start]..bounds[end]]) {
+                    single_known |= end == start + 1;
+                    relax(&mut best[end], (score + s, pieces + 1, false));
+                }
+            }
+            if !single_known {
+                let pieces = if after_unknown { pieces } else { pieces + 1 };
+                relax(
+                    &mut best[start + 1],
+                    (score + self.unknown_score, pieces, true),
+                );
+            }
+        }
+        best[n].map_or(0, |(_, pieces, _)| pieces)
+    }
+}
+
+fn relax(slot: &mut Option<(f64, usize, bool)>, candidate: (f64, usize, bool)) {
+    if slot.is_none_or(|(score, _, _)| candidate.0 > score) {
+        *slot = Some(candidate);
+    }
+}
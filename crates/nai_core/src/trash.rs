@@ -0,0 +1,165 @@
+use std::path::Path;
+use std::time::Duration;
+
+use chrono::Utc;
+use tracing::warn;
+
+use crate::{
+    dto::{TrashEntry, TrashRestoreResponse},
+    outputs::{OutputError, OutputStore, is_internal, is_safe_rel_path, normalize_rel_path},
+    thumbs::{THUMB_WIDTHS, thumb_cache_key},
+    util,
+};
+
+/// Hidden trash directory under outputs root. Each entry lives in `.trash/<id>/`
+/// next to an `entry.json` describing where it came from, with its cached
+/// thumbnails under `thumbs/`.
+pub const TRASH_DIR: &str = ".trash";
+
+const ENTRY_FILE: &str = "entry.json";
+
+impl OutputStore {
+    /// Move files into the trash. Missing or unsafe paths are skipped.
+    pub async fn trash_rel_files(
+        &self,
+        rel_paths: &[String],
+    ) -> Result<Vec<TrashEntry>, OutputError> {
+        let storage = self.storage();
+        let mut entries = Vec::new();
+        for rel in rel_paths {
+            let rel_norm = normalize_rel_path(rel);
+            if !is_safe_rel_path(&rel_norm) || is_internal(&rel_norm) {
+                continue;
+            }
+            let Some(obj) = storage.stat(&rel_norm).await? else {
+                continue;
+            };
+
+            let id = util::random_str(12);
+            let file_name = Path::new(&rel_norm)
+                .file_name()
+                .and_then(|s| s.to_str())
+                .unwrap_or("file")
+                .to_string();
+            let entry = TrashEntry {
+                trash_path: format!("{TRASH_DIR}/{id}/{file_name}"),
+                id,
+                original_path: rel_norm.clone(),
+                deleted_at_ms: Utc::now().timestamp_millis(),
+                size: obj.size,
+            };
+
+            let entry_key = entry_key(&entry.id);
+            storage
+                .put(&entry_key, serde_json::to_vec_pretty(&entry)?)
+                .await?;
+            if let Err(e) = storage.rename(&rel_norm, &entry.trash_path).await {
+                let _ = storage.delete(&entry_key).await;
+                return Err(e);
+            }
+
+            // Thumbnails go along: formats the thumbnailer can't decode
+            // couldn't get them back.
+            for width in THUMB_WIDTHS {
+                let thumb = thumb_cache_key(&rel_norm, width);
+                if let Ok(Some(_)) = storage.stat(&thumb).await {
+                    let _ = storage.rename(&thumb, &thumb_key(&entry.id, width)).await;
+                }
+            }
+            entries.push(entry);
+        }
+        Ok(entries)
+    }
+
+    /// Trash entries, newest first.
+    pub async fn list_trash(&self) -> Result<Vec<TrashEntry>, OutputError> {
+        let storage = self.storage();
+        let mut out = Vec::new();
+        for obj in storage.list(TRASH_DIR).await? {
+            if !obj.key.ends_with(&format!("/{ENTRY_FILE}")) {
+                continue;
+            }
+            let Some(bytes) = storage.get(&obj.key).await? else {
+                continue;
+            };
+            match serde_json::from_slice::<TrashEntry>(&bytes) {
+                Ok(entry) => out.push(entry),
+                Err(e) => warn!(path = %obj.key, error = %e, "invalid trash entry"),
+            }
+        }
+        out.sort_by_key(|e| std::cmp::Reverse(e.deleted_at_ms));
+        Ok(out)
+    }
+
+    /// Move entries back to their original paths. Entries whose original path is
+    /// occupied again stay in the trash and are reported as conflicts.
+    pub async fn restore_trash(&self, ids: &[String]) -> Result<TrashRestoreResponse, OutputError> {
+        let storage = self.storage();
+        let mut report = TrashRestoreResponse::default();
+        for entry in self.list_trash().await? {
+            if !ids.contains(&entry.id) {
+                continue;
+            }
+            if storage.stat(&entry.original_path).await?.is_some() {
+                report.conflicts.push(entry.original_path);
+                continue;
+            }
+            storage
+                .rename(&entry.trash_path, &entry.original_path)
+                .await?;
+            for width in THUMB_WIDTHS {
+                let thumb = thumb_key(&entry.id, width);
+                if let Ok(Some(_)) = storage.stat(&thumb).await {
+                    let cached = thumb_cache_key(&entry.original_path, width);
+                    let _ = storage.rename(&thumb, &cached).await;
+                }
+            }
+            let _ = storage.delete(&entry_key(&entry.id)).await;
+            report.restored.push(entry.original_path);
+        }
+        Ok(report)
+    }
+
+    /// Permanently delete trash entries; all of them when `ids` is `None`.
+    pub async fn empty_trash(&self, ids: Option<&[String]>) -> Result<usize, OutputError> {
+        let storage = self.storage();
+        let mut purged = Vec::new();
+        for entry in self.list_trash().await? {
+            if ids.is_some_and(|ids| !ids.contains(&entry.id)) {
+                continue;
+            }
+            storage.delete(&entry.trash_path).await?;
+            for width in THUMB_WIDTHS {
+                storage.delete(&thumb_key(&entry.id, width)).await?;
+            }
+            storage.delete(&entry_key(&entry.id)).await?;
+            purged.push(entry.original_path);
+        }
+        self.forget_metadata(&purged).await;
+        Ok(purged.len())
+    }
+
+    /// Permanently delete trash entries older than `max_age`.
+    pub async fn purge_trash_older_than(&self, max_age: Duration) -> Result<usize, OutputError> {
+        let cutoff = Utc::now().timestamp_millis() - max_age.as_millis() as i64;
+        let expired: Vec<String> = self
+            .list_trash()
+            .await?
+            .into_iter()
+            .filter(|e| e.deleted_at_ms < cutoff)
+            .map(|e| e.id)
+            .collect();
+        if expired.is_empty() {
+            return Ok(0);
+        }
+        self.empty_trash(Some(&expired)).await
+    }
+}
+
+fn entry_key(id: &str) -> String {
+    format!("{TRASH_DIR}/{id}/{ENTRY_FILE}")
+}
+
+fn thumb_key(id: &str, width: u32) -> String {
+    format!("{TRASH_DIR}/{id}/thumbs/{width}.jpg")
+}
@@ -0,0 +1,167 @@
+use std::ops::Range;
+
+use async_trait::async_trait;
+use futures::TryStreamExt;
+use object_store::{ObjectStore, PutPayload, aws::AmazonS3Builder, path::Path};
+use tracing::info;
+
+use nai_core::{
+    config::S3Config,
+    outputs::{OutputError, is_safe_rel_path, normalize_rel_path},
+    storage::{OutputStorage, StoredObject},
+};
+
+/// Outputs in an S3-compatible bucket (AWS S3, MinIO, ...).
+///
+/// Credentials fall back to the standard `AWS_*` environment variables when the
+/// `s3_*` settings are not given.
+#[derive(Debug)]
+pub struct S3Storage {
+    store: Box<dyn ObjectStore>,
+    prefix: String,
+}
+
+impl S3Storage {
+    pub fn new(cfg: &S3Config) -> anyhow::Result<Self> {
+        let mut builder = AmazonS3Builder::from_env()
+            .with_bucket_name(&cfg.bucket)
+            .with_region(&cfg.region);
+        if let Some(endpoint) = &cfg.endpoint {
+            // MinIO and most self-hosted servers want path-style requests.
+            builder = builder
+                .with_endpoint(endpoint)
+                .with_allow_http(endpoint.starts_with("http://"))
+                .with_virtual_hosted_style_request(false);
+        }
+        if let Some(key) = &cfg.access_key_id {
+            builder = builder.with_access_key_id(key);
+        }
+        if let Some(secret) = &cfg.secret_access_key {
+            builder = builder.with_secret_access_key(secret);
+        }
+        let store = builder.build()?;
+        info!(
+            bucket = %cfg.bucket,
+            endpoint = cfg.endpoint.as_deref().unwrap_or("aws"),
+            prefix = %cfg.prefix,
+            "s3 output storage"
+        );
+        Ok(Self {
+            store: Box::new(store),
+            prefix: cfg.prefix.clone(),
+        })
+    }
+
+    fn path(&self, key: &str) -> Result<Path, OutputError> {
+        let key = normalize_rel_path(key);
+        let key = key.trim_matches('/');
+        if !key.is_empty() && !is_safe_rel_path(key) {
+            return Err(OutputError::InvalidPath);
+        }
+        let full = match (self.prefix.is_empty(), key.is_empty()) {
+            (true, _) => key.to_string(),
+            (false, true) => self.prefix.clone(),
+            (false, false) => format!("{}/{key}", self.prefix),
+        };
+        Path::parse(full).map_err(|_| OutputError::InvalidPath)
+    }
+
+    fn key_of(&self, location: &Path) -> String {
+        let full = location.as_ref();
+        if self.prefix.is_empty() {
+            return full.to_string();
+        }
+        full.strip_prefix(&self.prefix)
+            .map(|k| k.trim_start_matches('/'))
+            .unwrap_or(full)
+            .to_string()
+    }
+}
+
+fn backend(err: object_store::Error) -> OutputError {
+    OutputError::Backend(err.into())
+}
+
+#[async_trait]
+impl OutputStorage for S3Storage {
+    fn kind(&self) -> &'static str {
+        "s3"
+    }
+
+    async fn put(&self, key: &str, bytes: Vec<u8>) -> Result<(), OutputError> {
+        self.store
+            .put(&self.path(key)?, PutPayload::from(bytes))
+            .await
+            .map_err(backend)?;
+        Ok(())
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, OutputError> {
+        match self.store.get(&self.path(key)?).await {
+            Ok(res) => Ok(Some(res.bytes().await.map_err(backend)?.to_vec())),
+            Err(object_store::Error::NotFound { .. }) => Ok(None),
+            Err(e) => Err(backend(e)),
+        }
+    }
+
+    async fn get_range(
+        &self,
+        key: &str,
+        range: Range<u64>,
+    ) -> Result<Option<Vec<u8>>, OutputError> {
+        match self.store.get_range(&self.path(key)?, range).await {
+            Ok(bytes) => Ok(Some(bytes.to_vec())),
+            Err(object_store::Error::NotFound { .. }) => Ok(None),
+            Err(e) => Err(backend(e)),
+        }
+    }
+
+    async fn stat(&self, key: &str) -> Result<Option<StoredObject>, OutputError> {
+        match self.store.head(&self.path(key)?).await {
+            Ok(meta) => Ok(Some(StoredObject {
+                key: self.key_of(&meta.location),
+                size: meta.size,
+                modified_ms: meta.last_modified.timestamp_millis(),
+            })),
+            Err(object_store::Error::NotFound { .. }) => Ok(None),
+            Err(e) => Err(backend(e)),
+        }
+    }
+
+    async fn list(&self, prefix: &str) -> Result<Vec<StoredObject>, OutputError> {
+        let base = self.path(prefix)?;
+        let base_key = self.key_of(&base);
+        let metas: Vec<_> = self
+            .store
+            .list(Some(&base))
+            .try_collect()
+            .await
+            .map_err(backend)?;
+        Ok(metas
+            .into_iter()
+            .map(|meta| StoredObject {
+                key: self.key_of(&meta.location),
+                size: meta.size,
+                modified_ms: meta.last_modified.timestamp_millis(),
+            })
+            .filter(|o| {
+                let below = o.key.strip_prefix(&base_key).unwrap_or(&o.key);
+                !below.split('/').any(|c| c.starts_with('.'))
+            })
+            .collect())
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), OutputError> {
+        match self.store.delete(&self.path(key)?).await {
+            Ok(()) | Err(object_store::Error::NotFound { .. }) => Ok(()),
+            Err(e) => Err(backend(e)),
+        }
+    }
+
+    async fn rename(&self, from: &str, to: &str) -> Result<(), OutputError> {
+        self.store
+            .rename(&self.path(from)?, &self.path(to)?)
+            .await
+            .map_err(backend)
+    }
+}
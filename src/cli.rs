@@ -0,0 +1,124 @@
+//! `nai-ui export` and `nai-ui import`: move a setup between machines as a
+//! settings bundle, without starting the server or a NovelAI token.
+
+use std::io::{Read, Write};
+
+use anyhow::{Context, bail};
+use nai_api::{BundleSection, ConflictStrategy, Database, SettingsBundle, SettingsStores};
+use nai_core::config::AppConfig;
+
+const USAGE: &str = "\
+usage:
+  nai-ui export [FILE|-] [--sections LIST]
+  nai-ui import FILE|- [--sections LIST] [--conflict skip|overwrite|rename] [--apply]
+
+LIST is comma-separated: presets,prompt_presets,character_presets,snippets,last_generation
+(all when omitted). Import is a dry run unless --apply is given; its report goes to stdout.";
+
+pub enum Command {
+    Export {
+        path: Option<String>,
+        sections: Vec<BundleSection>,
+    },
+    Import {
+        path: String,
+        sections: Vec<BundleSection>,
+        conflict: ConflictStrategy,
+        apply: bool,
+    },
+}
+
+impl Command {
+    /// `None` when `args` (without the program name) don't name a command,
+    /// so the server should start.
+    pub fn parse(args: &[String]) -> anyhow::Result<Option<Self>> {
+        let Some((command, rest)) = args.split_first() else {
+            return Ok(None);
+        };
+        if !matches!(command.as_str(), "export" | "import") {
+            return Ok(None);
+        }
+
+        let mut path = None;
+        let mut sections = Vec::new();
+        let mut conflict = ConflictStrategy::default();
+        let mut apply = false;
+        let mut rest = rest.iter();
+        while let Some(arg) = rest.next() {
+            match arg.as_str() {
+                "--sections" => {
+                    let list = rest.next().context("--sections needs a value")?;
+                    sections = BundleSection::parse_list(list)?;
+                }
+                "--conflict" if command == "import" => {
+                    let value = rest.next().context("--conflict needs a value")?;
+                    conflict = serde_json::from_value(value.as_str().into())
+                        .map_err(|_| anyhow::anyhow!("unknown conflict strategy: {value}"))?;
+                }
+                "--apply" if command == "import" => apply = true,
+                "-h" | "--help" => {
+                    println!("{USAGE}");
+                    std::process::exit(0);
+                }
+                s if s.starts_with("--") => bail!("unknown option {s}\n\n{USAGE}"),
+                _ if path.is_none() => path = Some(arg.clone()),
+                _ => bail!("unexpected argument {arg}\n\n{USAGE}"),
+            }
+        }
+
+        Ok(Some(match command.as_str() {
+            "export" => Command::Export { path, sections },
+            _ => Command::Import {
+                path: path.with_context(|| format!("import needs a FILE\n\n{USAGE}"))?,
+                sections,
+                conflict,
+                apply,
+            },
+        }))
+    }
+
+    pub async fn run(self) -> anyhow::Result<()> {
+        let output_dir = AppConfig::output_dir();
+        let db = Database::sqlite(output_dir.join("nai-ui.sqlite"))?;
+        let stores = SettingsStores::open(&db)?;
+
+        match self {
+            Command::Export { path, sections } => {
+                let bundle = stores.export(&sections).await?;
+                let json = serde_json::to_vec_pretty(&bundle)?;
+                match path.as_deref() {
+                    None | Some("-") => {
+                        let mut stdout = std::io::stdout().lock();
+                        stdout.write_all(&json)?;
+                        stdout.write_all(b"\n")?;
+                    }
+                    Some(path) => {
+                        std::fs::write(path, json).with_context(|| format!("write {path}"))?
+                    }
+                }
+            }
+            Command::Import {
+                path,
+                sections,
+                conflict,
+                apply,
+            } => {
+                let bytes = if path == "-" {
+                    let mut bytes = Vec::new();
+                    std::io::stdin().read_to_end(&mut bytes)?;
+                    bytes
+                } else {
+                    std::fs::read(&path).with_context(|| format!("read {path}"))?
+                };
+                let bundle: SettingsBundle =
+                    serde_json::from_slice(&bytes).with_context(|| format!("parse {path}"))?;
+                let report = stores.import(bundle, &sections, conflict, !apply).await?;
+                println!("{}", serde_json::to_string_pretty(&report)?);
+                if !apply {
+                    eprintln!("dry run; pass --apply to write {} items", report.written());
+                }
+            }
+        }
+        Ok(())
+    }
+}
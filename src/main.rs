@@ -1,11 +1,20 @@
+mod cli;
+
 use std::sync::Arc;
 
 use axum::Router;
 use nai_api::{
-    AppState, CharacterPresetStore, Database, LastGenerationStore, PresetStore, PromptPresetStore,
-    PromptSnippetStore,
+    AppState, CharacterPresetStore, Database, DiskGuard, LastGenerationStore,
+    OutputAnnotationStore, OutputCounterStore, OutputMetadataDb, PresetStore, PromptPresetStore,
+    PromptSnippetStore, RevisionStore, TagStore, WildcardStore,
+};
+use nai_core::{
+    config::{AppConfig, StorageConfig},
+    job::JobStore,
+    outputs::OutputStore,
+    storage::{LocalStorage, OutputStorage},
+    tokenizer::{Encoder, Tokenizers},
 };
-use nai_core::{config::AppConfig, job::JobStore, outputs::OutputStore};
 use nai_nai::NaiClient;
 use tokio::net::TcpListener;
 use tokio::sync::Semaphore;
@@ -15,6 +24,11 @@ use tracing_subscriber::{EnvFilter, fmt, layer::SubscriberExt, util::SubscriberI
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Some(command) = cli::Command::parse(&args)? {
+        return command.run().await;
+    }
+
     tracing_subscriber::registry()
         .with(EnvFilter::try_from_default_env().unwrap_or_else(|_| "info".into()))
         .with(fmt::layer())
@@ -24,12 +38,27 @@ async fn main() -> anyhow::Result<()> {
 
     let config = AppConfig::load()?;
     let nai_cli = NaiClient::new(config.token.clone(), config.proxy.clone())?;
-    let outputs = OutputStore::new(&config)?;
 
     let db = Database::sqlite(config.output_dir.join("nai-ui.sqlite"))?;
     db.health_check()?;
 
+    let output_counters = OutputCounterStore::new(db.clone())?;
+    output_counters.migrate_legacy_json(&config.output_dir)?;
+    let output_metadata = OutputMetadataDb::new(db.clone())?;
+    let storage: Arc<dyn OutputStorage> = match &config.storage {
+        StorageConfig::Local => Arc::new(LocalStorage::new(config.output_dir.clone())?),
+        StorageConfig::S3(s3) => Arc::new(nai_s3::S3Storage::new(s3)?),
+    };
+    let outputs = OutputStore::new(
+        &config,
+        storage.clone(),
+        Arc::new(output_counters),
+        Arc::new(output_metadata),
+    )
+    .await?;
+
     let last_generation = LastGenerationStore::new(db.clone())?;
+    let output_annotations = OutputAnnotationStore::new(db.clone())?;
 
     let presets = PresetStore::new(db.clone())?;
     presets
@@ -49,17 +78,48 @@ async fn main() -> anyhow::Result<()> {
     let character_presets = CharacterPresetStore::new(db.clone())?;
 
     let prompt_snippets = PromptSnippetStore::new(db.clone())?;
+    let revisions = RevisionStore::new(db.clone())?;
+    let wildcards = WildcardStore::new(db.clone(), config.wildcards_dir.clone())?;
+    let tags = TagStore::new(db.clone())?;
+    let tokenizers = Arc::new(Tokenizers::load(&config.tokenizer_dir));
 
     let jobs = JobStore::new();
     let job_sem = Arc::new(Semaphore::new(1));
+    // Outputs in a bucket don't fill the local disk; the guard only applies to local storage.
+    let min_free_disk_mb = if storage.local_root().is_some() {
+        config.min_free_disk_mb
+    } else {
+        0
+    };
+    let disk_guard = DiskGuard::new(config.output_dir.clone(), min_free_disk_mb);
 
     info!(bind = %config.bind, output_dir = %config.output_dir.display(), "config loaded");
+    info!(backend = storage.kind(), "output storage");
     info!(
         format_input = config.format_input,
+        normalize_tags = config.normalize_tags,
         cool_time = config.cool_time,
         cool_jitter = config.cool_jitter,
         "job pacing"
     );
+    info!(
+        format = config.output_format.extension(),
+        quality = config.output_quality,
+        "output format"
+    );
+    info!(
+        tokenizer_dir = %config.tokenizer_dir.display(),
+        clip = tokenizers.has(Encoder::Clip),
+        t5 = tokenizers.has(Encoder::T5),
+        "tokenizers"
+    );
+    info!(trash_days = config.trash_days, "outputs trash");
+    info!(
+        retention_days = ?config.retention.max_age_days,
+        retention_max_mb = ?config.retention.max_total_mb,
+        min_free_disk_mb,
+        "outputs retention"
+    );
     info!(max_concurrent_jobs = 1, "job queue");
 
     let state = Arc::new(AppState {
@@ -67,15 +127,23 @@ async fn main() -> anyhow::Result<()> {
         db,
         nai: nai_cli,
         outputs,
+        output_annotations,
         jobs,
         job_sem,
+        disk_guard,
         last_generation,
         presets,
         prompt_presets,
         character_presets,
         prompt_snippets,
+        revisions,
+        wildcards,
+        tags,
+        tokenizers,
     });
 
+    nai_api::spawn_maintenance(state.clone());
+
     let app: Router;
 
     #[cfg(debug_assertions)]
@@ -44,6 +44,22 @@ export async function apiPost<TReq, TRes>(
   return (await res.json()) as TRes;
 }
 
+export async function apiPostText<TRes>(
+  path: string,
+  body: string | Blob
+): Promise<TRes> {
+  const res = await fetch(resolveUrl(path), {
+    method: "POST",
+    headers: { "content-type": "text/plain" },
+    body,
+  });
+  if (!res.ok) {
+    const err = await readErrorBody(res);
+    throw new Error(`POST ${path} failed: ${res.status} ${err}`);
+  }
+  return (await res.json()) as TRes;
+}
+
 export async function apiPut<TReq, TRes>(
   path: string,
   body: TReq
@@ -1,17 +1,22 @@
-import { apiDelete, apiGet, apiPost, apiPut } from "./client";
+import { apiDelete, apiGet, apiPost, apiPostText, apiPut } from "./client";
 import type {
   Anlas,
   BaseGenerateRequest,
+  BundleSection,
   CharacterPresetGetResponse,
   CharacterPresetPutRequest,
   CharacterPresetRenameRequest,
   CharacterPresetsListResponse,
   CharacterRequest,
+  ConflictStrategy,
   DirectorPromptRequest,
   DirectorRequest,
   DirectorResponse,
   GenerateResponse,
+  GridOptions,
+  GridResponse,
   Health,
+  ImportReport,
   Img2ImgRequest,
   InpaintRequest,
   JobStatus,
@@ -27,16 +32,45 @@ import type {
   PresetPutRequest,
   PresetRenameRequest,
   PresetsListResponse,
+  PromptFormatRequest,
+  PromptFormatResponse,
+  PromptLintRequest,
+  PromptLintResponse,
+  PromptTokensRequest,
+  PromptTokensResponse,
+  PromptSnippetFolderMoveRequest,
+  PromptSnippetFolderMoveResponse,
+  PromptSnippetFolderResponse,
   PromptSnippetGetResponse,
   PromptSnippetPreviewRequest,
   PromptSnippetPreviewResponse,
   PromptSnippetPutRequest,
   PromptSnippetRenameRequest,
+  PromptSnippetRenameResponse,
+  PromptSnippetsGraphResponse,
   PromptSnippetsListResponse,
+  SnippetFormat,
   PromptPresetGetResponse,
   PromptPresetPutRequest,
   PromptPresetRenameRequest,
   PromptPresetsListResponse,
+  RevisionDiff,
+  RevisionGetResponse,
+  RevisionKind,
+  RevisionsListResponse,
+  SettingsBundle,
+  SettingsImportReport,
+  SweepRequest,
+  TagGetResponse,
+  TagImportReport,
+  TagsCompleteResponse,
+  TagsNormalizeRequest,
+  TagsNormalizeResponse,
+  WildcardGetResponse,
+  WildcardPreviewRequest,
+  WildcardPreviewResponse,
+  WildcardPutRequest,
+  WildcardsListResponse,
 } from "./types";
 
 export const endpoints = {
@@ -104,13 +138,76 @@ export const endpoints = {
       req
     ),
 
-  promptSnippetsList: (params?: { q?: string; tags?: string[] }) => {
+  promptFormat: (req: PromptFormatRequest) =>
+    apiPost<PromptFormatRequest, PromptFormatResponse>("/api/prompt/format", req),
+  promptLint: (req: PromptLintRequest) =>
+    apiPost<PromptLintRequest, PromptLintResponse>("/api/prompt/lint", req),
+  promptTokens: (req: PromptTokensRequest) =>
+    apiPost<PromptTokensRequest, PromptTokensResponse>("/api/prompt/tokens", req),
+
+  promptSnippetsList: (params?: {
+    q?: string;
+    tags?: string[];
+    tag_mode?: "any" | "all";
+    limit?: number;
+    offset?: number;
+  }) => {
     const search = new URLSearchParams();
     if (params?.q) search.set("q", params.q);
     if (params?.tags?.length) search.set("tags", params.tags.join(","));
+    if (params?.tag_mode) search.set("tag_mode", params.tag_mode);
+    if (params?.limit != null) search.set("limit", String(params.limit));
+    if (params?.offset != null) search.set("offset", String(params.offset));
     const suffix = search.toString() ? `?${search.toString()}` : "";
     return apiGet<PromptSnippetsListResponse>(`/api/prompt_snippets${suffix}`);
   },
+  promptSnippetsFolder: (path = "") =>
+    apiGet<PromptSnippetFolderResponse>(
+      `/api/prompt_snippets/folder?path=${encodeURIComponent(path)}`
+    ),
+  promptSnippetsFolderMove: (req: PromptSnippetFolderMoveRequest) =>
+    apiPost<PromptSnippetFolderMoveRequest, PromptSnippetFolderMoveResponse>(
+      "/api/prompt_snippets/folder/move",
+      req
+    ),
+  promptSnippetsImport: (
+    file: string | Blob,
+    params: {
+      format: SnippetFormat;
+      conflict?: ConflictStrategy;
+      dry_run?: boolean;
+      folder?: string;
+    }
+  ) => {
+    const search = new URLSearchParams({ format: params.format });
+    if (params.conflict) search.set("conflict", params.conflict);
+    if (params.dry_run != null) search.set("dry_run", String(params.dry_run));
+    if (params.folder) search.set("folder", params.folder);
+    return apiPostText<ImportReport>(
+      `/api/prompt_snippets/import?${search.toString()}`,
+      file
+    );
+  },
+  settingsImport: (
+    bundle: SettingsBundle,
+    params: {
+      sections?: BundleSection[];
+      conflict?: ConflictStrategy;
+      dry_run?: boolean;
+    } = {}
+  ) => {
+    const search = new URLSearchParams();
+    if (params.sections?.length)
+      search.set("sections", params.sections.join(","));
+    if (params.conflict) search.set("conflict", params.conflict);
+    if (params.dry_run != null) search.set("dry_run", String(params.dry_run));
+    return apiPost<SettingsBundle, SettingsImportReport>(
+      `/api/import?${search.toString()}`,
+      bundle
+    );
+  },
+  promptSnippetsGraph: () =>
+    apiGet<PromptSnippetsGraphResponse>("/api/prompt_snippets/graph"),
   promptSnippetGet: (name: string) =>
     apiGet<PromptSnippetGetResponse>(
       `/api/prompt_snippet?name=${encodeURIComponent(name)}`
@@ -125,7 +222,7 @@ export const endpoints = {
       `/api/prompt_snippet?name=${encodeURIComponent(name)}`
     ),
   promptSnippetRename: (req: PromptSnippetRenameRequest) =>
-    apiPost<PromptSnippetRenameRequest, { ok: boolean }>(
+    apiPost<PromptSnippetRenameRequest, PromptSnippetRenameResponse>(
       "/api/prompt_snippet/rename",
       req
     ),
@@ -135,6 +232,49 @@ export const endpoints = {
       req
     ),
 
+  wildcardsList: () => apiGet<WildcardsListResponse>("/api/wildcards"),
+  wildcardGet: (name: string) =>
+    apiGet<WildcardGetResponse>(
+      `/api/wildcard?name=${encodeURIComponent(name)}`
+    ),
+  wildcardPut: (req: WildcardPutRequest) =>
+    apiPut<WildcardPutRequest, { ok: boolean }>("/api/wildcard", req),
+  wildcardDelete: (name: string) =>
+    apiDelete<{ ok: boolean }>(
+      `/api/wildcard?name=${encodeURIComponent(name)}`
+    ),
+  wildcardPreview: (req: WildcardPreviewRequest) =>
+    apiPost<WildcardPreviewRequest, WildcardPreviewResponse>(
+      "/api/wildcard/preview",
+      req
+    ),
+
+  revisionsList: (kind: RevisionKind, name: string, model?: string) => {
+    const search = new URLSearchParams({ kind, name });
+    if (model) search.set("model", model);
+    return apiGet<RevisionsListResponse>(`/api/revisions?${search.toString()}`);
+  },
+  revisionGet: (id: number) => apiGet<RevisionGetResponse>(`/api/revision/${id}`),
+  revisionsDiff: (from: number, to: number) =>
+    apiGet<RevisionDiff>(`/api/revisions/diff?from=${from}&to=${to}`),
+  revisionRestore: (id: number) =>
+    apiPost<Record<string, never>, { ok: boolean }>(
+      `/api/revision/${id}/restore`,
+      {}
+    ),
+
+  tagsImport: (csv: string, kind: "tags" | "implications" = "tags") =>
+    apiPostText<TagImportReport>(`/api/tags/import?kind=${kind}`, csv),
+  tagsComplete: (q: string, limit?: number) => {
+    const search = new URLSearchParams({ q });
+    if (limit) search.set("limit", String(limit));
+    return apiGet<TagsCompleteResponse>(`/api/tags/complete?${search.toString()}`);
+  },
+  tagGet: (name: string) =>
+    apiGet<TagGetResponse>(`/api/tag?name=${encodeURIComponent(name)}`),
+  tagsNormalize: (req: TagsNormalizeRequest) =>
+    apiPost<TagsNormalizeRequest, TagsNormalizeResponse>("/api/tags/normalize", req),
+
   characterPresetsList: () =>
     apiGet<CharacterPresetsListResponse>("/api/character_presets"),
   characterPresetGet: (name: string) =>
@@ -173,8 +313,12 @@ export const endpoints = {
     apiPost<InpaintRequest, JobSubmitResponse>("/api/jobs/inpaint", req),
   jobCharacter: (req: CharacterRequest) =>
     apiPost<CharacterRequest, JobSubmitResponse>("/api/jobs/character", req),
+  jobSweep: (req: SweepRequest) =>
+    apiPost<SweepRequest, JobSubmitResponse>("/api/jobs/sweep", req),
   jobsList: () => apiGet<JobsListResponse>("/api/jobs"),
   jobStatus: (id: string) => apiGet<JobStatus>(`/api/jobs/${id}`),
+  jobGrid: (id: string, opts: GridOptions) =>
+    apiPost<GridOptions, GridResponse>(`/api/jobs/${id}/grid`, opts),
 
   directorRemoveBg: (req: DirectorRequest) =>
     apiPost<DirectorRequest, DirectorResponse>("/api/director/remove_bg", req),
@@ -31,6 +31,10 @@ export type BaseGenerateRequest = {
   noise_schedule?: string | null;
   cfg_rescale?: number | null;
   seed: number;
+  seed_mode?: "random" | "fixed" | "increment" | "list" | "variations" | null;
+  seeds?: number[] | null;
+  wildcard_seed?: number | null;
+  combinatorial?: boolean | null;
   add_quality_tags?: boolean | null;
   undesired_content_preset?: string | null;
   sm?: boolean | null;
@@ -41,6 +45,35 @@ export type BaseGenerateRequest = {
   reference_image_multiple?: string[] | null;
   reference_information_extracted_multiple?: number[] | null;
   reference_strength_multiple?: number[] | null;
+  preset_name?: string | null;
+  output_format?: "png" | "webp" | "jpeg" | "avif" | null;
+  grid?: GridOptions | null;
+};
+
+export type GridOptions = {
+  columns?: number | null;
+  cell_width?: number | null;
+  captions?: boolean | null;
+};
+
+export type SweepAxis = (
+  | { type: "field"; path: string }
+  | { type: "replace"; search: string }
+) & {
+  values: unknown[];
+  label?: string | null;
+};
+
+export type SweepRequest = {
+  base: BaseGenerateRequest;
+  axes: SweepAxis[];
+  fixed_seed?: boolean;
+  cell_width?: number | null;
+};
+
+export type GridResponse = {
+  output_path: string;
+  url: string;
 };
 
 export type Img2ImgRequest = BaseGenerateRequest & {
@@ -77,6 +110,16 @@ export type OutputItem = {
   op_type: string;
   date: string;
   filename: string;
+  thumb_url: string;
+  size: number;
+  modified_ms: number;
+  annotation: OutputAnnotation;
+};
+
+export type OutputAnnotation = {
+  favorite: boolean;
+  rating: number | null;
+  tags: string[];
 };
 
 export type OutputsResponse = {
@@ -91,10 +134,12 @@ export type OutputsDeleteRequest = {
 
 export type OutputsDeleteResponse = {
   deleted: number;
+  trash_ids: string[];
 };
 
 export type JobSubmitResponse = {
   job_id: string;
+  wildcard_seed?: number;
 };
 
 export type JobStatus =
@@ -102,7 +147,7 @@ export type JobStatus =
   | { status: "running" }
   | { status: "cancelled" }
   | { status: "failed"; error: string }
-  | { status: "succeeded"; outputs: GenerateResponse[] };
+  | { status: "succeeded"; outputs: GenerateResponse[]; grid?: GridResponse };
 
 export type JobSummary = {
   id: string;
@@ -208,20 +253,83 @@ export type PromptPresetRenameRequest = {
   to: string;
 };
 
+export type SnippetParam = {
+  name: string;
+  default?: string | null;
+};
+
 export type PromptSnippet = {
   body: string;
   tags: string[];
   description?: string | null;
+  params?: SnippetParam[];
+};
+
+export type PromptFormatRequest = {
+  text: string;
+  weights?: "braces" | "numeric" | null;
+};
+
+export type PromptFormatResponse = {
+  text: string;
+};
+
+export type PromptLintRequest = {
+  positive: string;
+  negative: string;
+  character_prompts?: CharacterPrompt[];
+};
+
+export type PromptLintIssue = {
+  field: string;
+  start: number;
+  end: number;
+  code:
+    | "unbalanced_bracket"
+    | "duplicate_tag"
+    | "negative_overlap"
+    | "empty_group"
+    | "conflicting_tags"
+    | "unresolved_snippet"
+    | "empty_character_caption";
+  severity: "error" | "warning";
+  message: string;
+};
+
+export type PromptLintResponse = {
+  issues: PromptLintIssue[];
+};
+
+export type PromptTokensRequest = {
+  model: string;
+  positive: string;
+  negative: string;
+  character_prompts?: CharacterPrompt[];
+  add_quality_tags?: boolean;
+};
+
+export type PromptTokensResponse = {
+  encoder: "clip" | "t5";
+  limit: number;
+  base: number;
+  negative: number;
+  characters: { prompt: number; uc: number }[];
+  positive_total: number;
+  negative_total: number;
+  warnings: string[];
 };
 
 export type PromptSnippetSummary = {
   name: string;
   tags: string[];
   description?: string | null;
+  highlight?: string | null;
 };
 
 export type PromptSnippetsListResponse = {
   items: PromptSnippetSummary[];
+  next_offset: number;
+  has_more: boolean;
 };
 
 export type PromptSnippetGetResponse = {
@@ -236,6 +344,92 @@ export type PromptSnippetPutRequest = {
 export type PromptSnippetRenameRequest = {
   from: string;
   to: string;
+  update_references?: boolean;
+};
+
+export type SnippetReferrer = {
+  kind: "prompt_snippet" | "prompt_preset" | "character_preset" | "last_generation";
+  name: string;
+};
+
+export type PromptSnippetRenameResponse = {
+  ok: boolean;
+  updated: SnippetReferrer[];
+};
+
+export type SnippetEdge = {
+  from: SnippetReferrer;
+  to: string;
+};
+
+export type SnippetSubfolder = {
+  path: string;
+  count: number;
+};
+
+export type PromptSnippetFolderResponse = {
+  path: string;
+  folders: SnippetSubfolder[];
+  snippets: string[];
+};
+
+export type PromptSnippetFolderMoveRequest = {
+  from: string;
+  to: string;
+  update_references?: boolean;
+};
+
+export type PromptSnippetFolderMoveResponse = {
+  ok: boolean;
+  moved: number;
+  updated: SnippetReferrer[];
+};
+
+export type SnippetFormat = "json" | "yaml" | "styles_csv" | "text_zip";
+
+export type ConflictStrategy = "skip" | "overwrite" | "rename";
+
+export type ImportReport = {
+  dry_run: boolean;
+  created: string[];
+  overwritten: string[];
+  renamed: { from: string; to: string }[];
+  skipped: string[];
+  unchanged: string[];
+  rejected: { name: string; error: string }[];
+};
+
+export type BundleSection =
+  | "presets"
+  | "prompt_presets"
+  | "character_presets"
+  | "snippets"
+  | "last_generation";
+
+export type SettingsBundle = {
+  version: number;
+  exported_at_ms: number;
+  presets: Record<string, Record<string, GeneratePreset>>;
+  prompt_presets: Record<string, PromptPreset>;
+  character_presets: Record<string, CharacterSlotPreset>;
+  snippets: Record<string, PromptSnippet>;
+  last_generation: LastGenerationRecord | null;
+};
+
+export type SettingsImportReport = {
+  dry_run: boolean;
+  presets: ImportReport;
+  prompt_presets: ImportReport;
+  character_presets: ImportReport;
+  snippets: ImportReport;
+  last_generation: ImportReport;
+};
+
+export type PromptSnippetsGraphResponse = {
+  snippets: string[];
+  edges: SnippetEdge[];
+  missing: string[];
+  cycles: string[][];
 };
 
 export type PromptSnippetPreviewRequest = {
@@ -249,6 +443,113 @@ export type PromptSnippetPreviewResponse = {
   warnings: string[];
 };
 
+export type WildcardSummary = {
+  name: string;
+  count: number;
+  source: "database" | "file";
+};
+
+export type WildcardsListResponse = {
+  items: WildcardSummary[];
+};
+
+export type WildcardGetResponse = {
+  lines: string[] | null;
+};
+
+export type WildcardPutRequest = {
+  name: string;
+  lines: string[];
+};
+
+export type WildcardPreviewRequest = {
+  positive: string;
+  negative: string;
+  seed?: number | null;
+};
+
+export type WildcardPreviewResponse = {
+  positive: string;
+  negative: string;
+  seed: number;
+  combinations: number;
+  warnings: string[];
+};
+
+export type RevisionKind =
+  | "prompt_snippet"
+  | "prompt_preset"
+  | "character_preset"
+  | "preset";
+
+export type RevisionSummary = {
+  id: number;
+  created_at_ms: number;
+  deleted: boolean;
+};
+
+export type RevisionsListResponse = {
+  items: RevisionSummary[];
+};
+
+export type Revision = {
+  id: number;
+  kind: RevisionKind;
+  model: string;
+  name: string;
+  created_at_ms: number;
+  value: unknown | null;
+};
+
+export type RevisionGetResponse = {
+  revision: Revision | null;
+};
+
+export type RevisionDiff = {
+  from: number;
+  to: number;
+  diff: string;
+};
+
+export type TagSuggestion = {
+  name: string;
+  category: number;
+  post_count: number;
+  alias?: string;
+  match: "prefix" | "alias" | "fuzzy";
+};
+
+export type TagsCompleteResponse = {
+  items: TagSuggestion[];
+};
+
+export type TagInfo = {
+  name: string;
+  category: number;
+  post_count: number;
+  aliases: string[];
+  implies: string[];
+};
+
+export type TagGetResponse = {
+  tag: TagInfo | null;
+};
+
+export type TagImportReport = {
+  tags: number;
+  aliases: number;
+  implications: number;
+  skipped: number;
+};
+
+export type TagsNormalizeRequest = {
+  text: string;
+};
+
+export type TagsNormalizeResponse = {
+  text: string;
+};
+
 export type CharacterSlotPreset = {
   prompt: string;
   uc: string;
@@ -10,3 +10,18 @@ export function outputsUrl(relPath: string): string {
   const normalized = relPath.replaceAll("\\\\", "/").replaceAll("\\", "/");
   return assetUrl(`/outputs/${normalized}`);
 }
+
+export function promptSnippetsExportUrl(
+  format: "json" | "yaml" | "styles_csv" | "text_zip",
+  folder = ""
+): string {
+  const search = new URLSearchParams({ format });
+  if (folder) search.set("folder", folder);
+  return assetUrl(`/api/prompt_snippets/export?${search.toString()}`);
+}
+
+export function settingsExportUrl(sections: string[] = []): string {
+  const search = new URLSearchParams();
+  if (sections.length) search.set("sections", sections.join(","));
+  return assetUrl(`/api/export?${search.toString()}`);
+}